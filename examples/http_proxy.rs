@@ -50,6 +50,10 @@ async fn proxy(client: HttpClient, req: Request<Body>) -> Result<Response<Body>,
         // Proxy-Connection: Keep-Alive
         // ```
         //
+        // or, from an HTTP/2 client, a `CONNECT` stream whose `:authority`
+        // is `www.domain.com:443`. Either way, the tunnel is available by
+        // upgrading the request once the response is sent.
+        //
         // When HTTP method is CONNECT we should return an empty body
         // then we can eventually upgrade the connection and talk a new protocol.
         //
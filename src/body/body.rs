@@ -2,6 +2,8 @@ use std::borrow::Cow;
 #[cfg(feature = "stream")]
 use std::error::Error as StdError;
 use std::fmt;
+#[cfg(feature = "runtime")]
+use std::time::Instant;
 
 use bytes::Bytes;
 use futures_channel::{mpsc, oneshot};
@@ -20,6 +22,9 @@ use crate::proto::DecodedLength;
 use crate::upgrade::OnUpgrade;
 
 type BodySender = mpsc::Sender<Result<Bytes, crate::Error>>;
+type TrailersSender = oneshot::Sender<HeaderMap>;
+#[cfg(feature = "compression")]
+type BoxBody = Pin<Box<dyn HttpBody<Data = Bytes, Error = Box<dyn StdError + Send + Sync>> + Send>>;
 
 /// A stream of `Bytes`, used when receiving bodies.
 ///
@@ -39,6 +44,7 @@ enum Kind {
         content_length: DecodedLength,
         want_tx: watch::Sender,
         rx: mpsc::Receiver<Result<Bytes, crate::Error>>,
+        trailers_rx: oneshot::Receiver<HeaderMap>,
     },
     #[cfg(feature = "http2")]
     H2 {
@@ -52,6 +58,8 @@ enum Kind {
             Pin<Box<dyn Stream<Item = Result<Bytes, Box<dyn StdError + Send + Sync>>> + Send>>,
         >,
     ),
+    #[cfg(feature = "compression")]
+    Boxed(SyncWrapper<BoxBody>),
 }
 
 struct Extra {
@@ -65,6 +73,10 @@ struct Extra {
     /// connection yet.
     delayed_eof: Option<DelayEof>,
     on_upgrade: OnUpgrade,
+    /// The `Client`'s `request_timeout`, which also covers reading the
+    /// response body.
+    #[cfg(feature = "runtime")]
+    deadline: Option<tokio::time::Sleep>,
 }
 
 type DelayEofUntil = oneshot::Receiver<Never>;
@@ -95,6 +107,7 @@ enum DelayEof {
 pub struct Sender {
     want_rx: watch::Receiver,
     tx: BodySender,
+    trailers_tx: Option<TrailersSender>,
 }
 
 const WANT_PENDING: usize = 1;
@@ -126,6 +139,7 @@ impl Body {
 
     pub(crate) fn new_channel(content_length: DecodedLength, wanter: bool) -> (Sender, Body) {
         let (tx, rx) = mpsc::channel(0);
+        let (trailers_tx, trailers_rx) = oneshot::channel();
 
         // If wanter is true, `Sender::poll_ready()` won't becoming ready
         // until the `Body` has been polled for data once.
@@ -133,11 +147,16 @@ impl Body {
 
         let (want_tx, want_rx) = watch::channel(want);
 
-        let tx = Sender { want_rx, tx };
+        let tx = Sender {
+            want_rx,
+            tx,
+            trailers_tx: Some(trailers_tx),
+        };
         let rx = Body::new(Kind::Chan {
             content_length,
             want_tx,
             rx,
+            trailers_rx,
         });
 
         (tx, rx)
@@ -175,6 +194,15 @@ impl Body {
         Body::new(Kind::Wrapped(SyncWrapper::new(Box::pin(mapped))))
     }
 
+    /// Wrap another `HttpBody`, keeping its trailers.
+    #[cfg(feature = "compression")]
+    pub(crate) fn boxed<B>(body: B) -> Body
+    where
+        B: HttpBody<Data = Bytes, Error = Box<dyn StdError + Send + Sync>> + Send + 'static,
+    {
+        Body::new(Kind::Boxed(SyncWrapper::new(Box::pin(body))))
+    }
+
     /// Converts this `Body` into a `Future` of a pending HTTP upgrade.
     ///
     /// See [the `upgrade` module](crate::upgrade) for more.
@@ -188,6 +216,14 @@ impl Body {
         Body { kind, extra: None }
     }
 
+    /// Clone this body, if it's a full body that can be sent again.
+    pub(crate) fn try_clone(&self) -> Option<Body> {
+        match self.kind {
+            Kind::Once(ref val) => Some(Body::new(Kind::Once(val.clone()))),
+            _ => None,
+        }
+    }
+
     #[cfg(feature = "http2")]
     pub(crate) fn h2(
         recv: h2::RecvStream,
@@ -214,6 +250,26 @@ impl Body {
         self.extra_mut().delayed_eof = Some(DelayEof::NotEof(fut));
     }
 
+    /// Fail reading the body with a request timeout once `deadline` passes.
+    #[cfg(feature = "runtime")]
+    pub(crate) fn set_deadline(&mut self, deadline: Instant) {
+        self.extra_mut().deadline = Some(tokio::time::sleep_until(deadline.into()));
+    }
+
+    #[cfg(feature = "runtime")]
+    fn poll_deadline(&mut self, cx: &mut task::Context<'_>) -> crate::Result<()> {
+        if let Some(deadline) = self
+            .extra
+            .as_mut()
+            .and_then(|extra| extra.deadline.as_mut())
+        {
+            if Pin::new(deadline).poll(cx).is_ready() {
+                return Err(crate::Error::new_request_timeout());
+            }
+        }
+        Ok(())
+    }
+
     fn take_delayed_eof(&mut self) -> Option<DelayEof> {
         self.extra
             .as_mut()
@@ -225,6 +281,8 @@ impl Body {
             Box::new(Extra {
                 delayed_eof: None,
                 on_upgrade: OnUpgrade::none(),
+                #[cfg(feature = "runtime")]
+                deadline: None,
             })
         })
     }
@@ -265,6 +323,7 @@ impl Body {
                 content_length: ref mut len,
                 ref mut rx,
                 ref mut want_tx,
+                ..
             } => {
                 want_tx.send(WANT_READY);
 
@@ -297,6 +356,12 @@ impl Body {
                 Some(res) => Poll::Ready(Some(res.map_err(crate::Error::new_body))),
                 None => Poll::Ready(None),
             },
+
+            #[cfg(feature = "compression")]
+            Kind::Boxed(ref mut b) => match ready!(b.get_mut().as_mut().poll_data(cx)) {
+                Some(res) => Poll::Ready(Some(res.map_err(crate::Error::new_body))),
+                None => Poll::Ready(None),
+            },
         }
     }
 
@@ -325,14 +390,30 @@ impl HttpBody for Body {
         mut self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
     ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        #[cfg(feature = "runtime")]
+        {
+            if let Err(err) = self.poll_deadline(cx) {
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
         self.poll_eof(cx)
     }
 
     fn poll_trailers(
-        #[cfg_attr(not(feature = "http2"), allow(unused_mut))] mut self: Pin<&mut Self>,
-        #[cfg_attr(not(feature = "http2"), allow(unused))] cx: &mut task::Context<'_>,
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
     ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        #[cfg(feature = "runtime")]
+        self.poll_deadline(cx)?;
         match self.kind {
+            Kind::Chan {
+                ref mut trailers_rx,
+                ..
+            } => match ready!(Pin::new(trailers_rx).poll(cx)) {
+                Ok(t) => Poll::Ready(Ok(Some(t))),
+                // the sender was dropped without sending any trailers
+                Err(_) => Poll::Ready(Ok(None)),
+            },
             #[cfg(feature = "http2")]
             Kind::H2 {
                 recv: ref mut h2,
@@ -345,6 +426,12 @@ impl HttpBody for Body {
                 }
                 Err(e) => Poll::Ready(Err(crate::Error::new_h2(e))),
             },
+            #[cfg(feature = "compression")]
+            Kind::Boxed(ref mut b) => b
+                .get_mut()
+                .as_mut()
+                .poll_trailers(cx)
+                .map_err(crate::Error::new_body),
             _ => Poll::Ready(Ok(None)),
         }
     }
@@ -357,6 +444,8 @@ impl HttpBody for Body {
             Kind::H2 { recv: ref h2, .. } => h2.is_end_stream(),
             #[cfg(feature = "stream")]
             Kind::Wrapped(..) => false,
+            #[cfg(feature = "compression")]
+            Kind::Boxed(..) => false,
         }
     }
 
@@ -378,6 +467,8 @@ impl HttpBody for Body {
             Kind::Once(None) => SizeHint::with_exact(0),
             #[cfg(feature = "stream")]
             Kind::Wrapped(..) => SizeHint::default(),
+            #[cfg(feature = "compression")]
+            Kind::Boxed(..) => SizeHint::default(),
             Kind::Chan { content_length, .. } => opt_len!(content_length),
             #[cfg(feature = "http2")]
             Kind::H2 { content_length, .. } => opt_len!(content_length),
@@ -540,6 +631,26 @@ impl Sender {
             .map_err(|err| err.into_inner().expect("just sent Ok"))
     }
 
+    /// Send trailers on this channel.
+    ///
+    /// Trailers can only be sent once, after all data has been sent. On
+    /// HTTP/1, they are only written if the body uses the chunked transfer
+    /// coding, and otherwise ignored.
+    pub async fn send_trailers(&mut self, trailers: HeaderMap) -> crate::Result<()> {
+        // trailers don't take up room in the data buffer, so only wait
+        // for the receiver to want the body
+        futures_util::future::poll_fn(|cx| self.poll_want(cx)).await?;
+        self.try_send_trailers(trailers)
+            .map_err(|_| crate::Error::new_closed())
+    }
+
+    pub(crate) fn try_send_trailers(&mut self, trailers: HeaderMap) -> Result<(), HeaderMap> {
+        match self.trailers_tx.take() {
+            Some(tx) => tx.send(trailers),
+            None => Err(trailers),
+        }
+    }
+
     /// Aborts the body in an abnormal fashion.
     pub fn abort(self) {
         let _ = self
@@ -576,6 +687,8 @@ mod tests {
     use std::mem;
     use std::task::Poll;
 
+    use http::HeaderMap;
+
     use super::{Body, DecodedLength, HttpBody, Sender, SizeHint};
 
     #[test]
@@ -596,7 +709,7 @@ mod tests {
 
         assert_eq!(
             mem::size_of::<Sender>(),
-            mem::size_of::<usize>() * 4,
+            mem::size_of::<usize>() * 5,
             "Sender"
         );
 
@@ -653,6 +766,34 @@ mod tests {
         assert!(err.is_body_write_aborted(), "{:?}", err);
     }
 
+    #[tokio::test]
+    async fn channel_trailers() {
+        let (mut tx, mut rx) = Body::channel();
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", "abc".parse().unwrap());
+
+        tx.send_data("chunk 1".into()).await.expect("send data");
+        tx.send_trailers(trailers.clone())
+            .await
+            .expect("send trailers");
+        drop(tx);
+
+        let chunk1 = rx.data().await.expect("item 1").expect("chunk 1");
+        assert_eq!(chunk1, "chunk 1");
+        assert!(rx.data().await.is_none());
+        assert_eq!(rx.trailers().await.expect("trailers"), Some(trailers));
+    }
+
+    #[tokio::test]
+    async fn channel_no_trailers() {
+        let (tx, mut rx) = Body::channel();
+        drop(tx);
+
+        assert!(rx.data().await.is_none());
+        assert_eq!(rx.trailers().await.expect("trailers"), None);
+    }
+
     #[test]
     fn channel_buffers_one() {
         let (mut tx, _rx) = Body::channel();
@@ -17,6 +17,8 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use bytes::Bytes;
+#[cfg(feature = "http2")]
+use futures_core::Stream;
 use futures_util::future::{self, Either, FutureExt as _};
 use pin_project::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -56,6 +58,8 @@ where
 /// The sender side of an established connection.
 pub struct SendRequest<B> {
     dispatch: dispatch::Sender<Request<B>, Response<Body>>,
+    #[cfg(feature = "http2")]
+    push_promises: Option<PushPromises>,
 }
 
 /// A future that processes all HTTP state for the IO object.
@@ -79,8 +83,11 @@ pub struct Builder {
     pub(super) exec: Exec,
     h1_writev: Option<bool>,
     h1_title_case_headers: bool,
+    h1_preserve_header_case: bool,
+    h1_preserve_header_order: bool,
     h1_read_buf_exact_size: Option<usize>,
     h1_max_buf_size: Option<usize>,
+    h1_max_headers: Option<usize>,
     #[cfg(feature = "http2")]
     h2_builder: proto::h2::client::Config,
     version: Proto,
@@ -137,6 +144,25 @@ pub(super) struct Http2SendRequest<B> {
     dispatch: dispatch::UnboundedSender<Request<B>, Response<Body>>,
 }
 
+/// A stream of the responses pushed by an HTTP2 server.
+///
+/// Yields a `PushPromise` for each `PUSH_PROMISE` received on the
+/// connection. A limited number of pushed streams are buffered until they
+/// are taken from this stream, and further pushes are refused while the
+/// buffer is full. Dropping it refuses any further pushes.
+#[cfg(feature = "http2")]
+#[must_use = "streams do nothing unless polled"]
+pub struct PushPromises {
+    rx: futures_channel::mpsc::Receiver<proto::h2::client::PushedResponse>,
+}
+
+/// A response promised by an HTTP2 server.
+#[cfg(feature = "http2")]
+pub struct PushPromise {
+    request: Request<()>,
+    response: ResponseFuture,
+}
+
 // ===== impl SendRequest
 
 impl<B> SendRequest<B> {
@@ -163,6 +189,16 @@ impl<B> SendRequest<B> {
         self.dispatch.is_closed()
     }
 
+    /// Takes the stream of responses pushed by the server.
+    ///
+    /// Returns `None` if this is not an HTTP2 connection with
+    /// `Builder::http2_enable_push` set, or if the stream was already taken.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn push_promises(&mut self) -> Option<PushPromises> {
+        self.push_promises.take()
+    }
+
     #[cfg(feature = "http2")]
     pub(super) fn into_http2(self) -> Http2SendRequest<B> {
         Http2SendRequest {
@@ -279,6 +315,56 @@ impl<B> fmt::Debug for SendRequest<B> {
     }
 }
 
+// ===== impl PushPromises
+
+#[cfg(feature = "http2")]
+impl Stream for PushPromises {
+    type Item = PushPromise;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.rx).poll_next(cx).map(|pushed| {
+            pushed.map(|(request, rx)| PushPromise {
+                request,
+                response: ResponseFuture {
+                    inner: ResponseFutureState::Waiting(rx),
+                },
+            })
+        })
+    }
+}
+
+#[cfg(feature = "http2")]
+impl fmt::Debug for PushPromises {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PushPromises").finish()
+    }
+}
+
+// ===== impl PushPromise
+
+#[cfg(feature = "http2")]
+impl PushPromise {
+    /// Returns the request that the server promised to answer.
+    pub fn request(&self) -> &Request<()> {
+        &self.request
+    }
+
+    /// Consumes the promise, returning the request and a future of the
+    /// pushed response.
+    pub fn into_parts(self) -> (Request<()>, ResponseFuture) {
+        (self.request, self.response)
+    }
+}
+
+#[cfg(feature = "http2")]
+impl fmt::Debug for PushPromise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PushPromise")
+            .field("request", &self.request)
+            .finish()
+    }
+}
+
 // ===== impl Http2SendRequest
 
 #[cfg(feature = "http2")]
@@ -445,7 +531,10 @@ impl Builder {
             h1_writev: None,
             h1_read_buf_exact_size: None,
             h1_title_case_headers: false,
+            h1_preserve_header_case: false,
+            h1_preserve_header_order: false,
             h1_max_buf_size: None,
+            h1_max_headers: None,
             #[cfg(feature = "http2")]
             h2_builder: Default::default(),
             version: Proto::Http1,
@@ -488,6 +577,53 @@ impl Builder {
         self
     }
 
+    /// Set the maximum number of headers allowed in a response.
+    ///
+    /// If the server sends more headers than this, the response is
+    /// rejected with a parse error.
+    ///
+    /// Up to 100 headers are parsed using storage on the stack. A larger
+    /// maximum means the storage is allocated on the heap for every
+    /// response instead, which is somewhat slower.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is 100.
+    pub fn http1_max_headers(&mut self, val: usize) -> &mut Self {
+        self.h1_max_headers = Some(val);
+        self
+    }
+
+    /// Set whether to record the original case of response header names.
+    ///
+    /// The original names are stored in the extensions of the `Response`.
+    /// If those extensions are moved to a `Request`, such as when proxying,
+    /// its headers are written with the recorded case, taking precedence
+    /// over `http1_title_case_headers`.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is false.
+    pub fn http1_preserve_header_case(&mut self, enabled: bool) -> &mut Self {
+        self.h1_preserve_header_case = enabled;
+        self
+    }
+
+    /// Set whether to record the original order of response headers.
+    ///
+    /// The original order is stored in the extensions of the `Response`.
+    /// If those extensions are moved to a `Request`, such as when proxying,
+    /// its headers are written in the recorded order. Headers that weren't
+    /// part of the original message are written afterwards.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is false.
+    pub fn http1_preserve_header_order(&mut self, enabled: bool) -> &mut Self {
+        self.h1_preserve_header_order = enabled;
+        self
+    }
+
     /// Sets whether HTTP2 is required.
     ///
     /// Default is false.
@@ -566,6 +702,19 @@ impl Builder {
         self
     }
 
+    /// Sets whether the server may push responses on HTTP2 connections.
+    ///
+    /// If enabled, the pushed responses are available from
+    /// [`SendRequest::push_promises`].
+    ///
+    /// Default is false.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_enable_push(&mut self, enabled: bool) -> &mut Self {
+        self.h2_builder.enable_push = enabled;
+        self
+    }
+
     /// Sets an interval for HTTP2 Ping frames should be sent to keep a
     /// connection alive.
     ///
@@ -642,6 +791,8 @@ impl Builder {
             trace!("client handshake {:?}", opts.version);
 
             let (tx, rx) = dispatch::channel();
+            #[cfg(feature = "http2")]
+            let mut push_promises = None;
             let proto = match opts.version {
                 Proto::Http1 => {
                     let mut conn = proto::Conn::new(io);
@@ -655,27 +806,53 @@ impl Builder {
                     if opts.h1_title_case_headers {
                         conn.set_title_case_headers();
                     }
+                    if opts.h1_preserve_header_case {
+                        conn.set_preserve_header_case();
+                    }
+                    if opts.h1_preserve_header_order {
+                        conn.set_preserve_header_order();
+                    }
                     if let Some(sz) = opts.h1_read_buf_exact_size {
                         conn.set_read_buf_exact_size(sz);
                     }
                     if let Some(max) = opts.h1_max_buf_size {
                         conn.set_max_buf_size(max);
                     }
+                    if let Some(max) = opts.h1_max_headers {
+                        conn.set_http1_max_headers(max);
+                    }
                     let cd = proto::h1::dispatch::Client::new(rx);
                     let dispatch = proto::h1::Dispatcher::new(cd, conn);
                     ProtoClient::H1(dispatch)
                 }
                 #[cfg(feature = "http2")]
                 Proto::Http2 => {
-                    let h2 =
-                        proto::h2::client::handshake(io, rx, &opts.h2_builder, opts.exec.clone())
-                            .await?;
+                    let push_tx = if opts.h2_builder.enable_push {
+                        let (push_tx, push_rx) =
+                            futures_channel::mpsc::channel(proto::h2::client::PUSH_PROMISE_BUFFER);
+                        push_promises = Some(PushPromises { rx: push_rx });
+                        Some(push_tx)
+                    } else {
+                        None
+                    };
+                    let h2 = proto::h2::client::handshake(
+                        io,
+                        rx,
+                        push_tx,
+                        &opts.h2_builder,
+                        opts.exec.clone(),
+                    )
+                    .await?;
                     ProtoClient::H2(h2)
                 }
             };
 
             Ok((
-                SendRequest { dispatch: tx },
+                SendRequest {
+                    dispatch: tx,
+                    #[cfg(feature = "http2")]
+                    push_promises,
+                },
                 Connection { inner: Some(proto) },
             ))
         }
@@ -0,0 +1,191 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{self, Poll};
+
+use tower_service::Service;
+
+use super::{resolve, Name, Resolve, ResolvedAddr, ResolvedAddrs};
+
+/// A resolver asking a second resolver when the first one fails.
+///
+/// The fallback resolver is asked if the primary one returns an error, or
+/// no addresses. Fallbacks can be chained, by using a `FallbackResolver` as
+/// the fallback of another.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(all(unix, feature = "runtime"))]
+/// # fn doc() -> std::io::Result<()> {
+/// use hyper::client::HttpConnector;
+/// use hyper::client::connect::dns::{DnsResolver, FallbackResolver, GaiResolver};
+///
+/// // Ask the name servers directly, and the system if that fails.
+/// let resolver = FallbackResolver::new(DnsResolver::from_system_conf()?, GaiResolver::new());
+///
+/// let connector = HttpConnector::new_with_resolver(resolver);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FallbackResolver<P, F> {
+    primary: P,
+    fallback: F,
+}
+
+/// A future to resolve a name returned by `FallbackResolver`.
+#[must_use = "futures do nothing unless polled"]
+pub struct FallbackFuture {
+    fut: Pin<Box<dyn Future<Output = Result<ResolvedAddrs, BoxError>> + Send>>,
+}
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+impl<P, F> FallbackResolver<P, F> {
+    /// Construct a new `FallbackResolver`, asking `fallback` for names that
+    /// `primary` can't resolve.
+    pub fn new(primary: P, fallback: F) -> FallbackResolver<P, F> {
+        FallbackResolver { primary, fallback }
+    }
+}
+
+impl<P, F> Service<Name> for FallbackResolver<P, F>
+where
+    P: Resolve,
+    P::Future: Send + 'static,
+    F: Resolve + Clone + Send + 'static,
+    F::Future: Send,
+{
+    type Response = ResolvedAddrs;
+    type Error = BoxError;
+    type Future = FallbackFuture;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The fallback is only made ready if it's needed.
+        self.primary.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let primary = self.primary.resolve(name.clone());
+        let mut fallback = self.fallback.clone();
+        FallbackFuture {
+            fut: Box::pin(async move {
+                match primary.await {
+                    Ok(addrs) => {
+                        let addrs = collect(addrs);
+                        if !addrs.is_empty() {
+                            return Ok(addrs);
+                        }
+                        debug!("no addresses for {:?}, trying fallback resolver", name);
+                    }
+                    Err(err) => {
+                        let err: BoxError = err.into();
+                        debug!("resolving {:?} failed, trying fallback: {}", name, err);
+                    }
+                }
+                let addrs = resolve(&mut fallback, name).await.map_err(Into::into)?;
+                Ok(collect(addrs))
+            }),
+        }
+    }
+}
+
+impl<P: fmt::Debug, F: fmt::Debug> fmt::Debug for FallbackResolver<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackResolver")
+            .field("primary", &self.primary)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+fn collect<I>(addrs: I) -> ResolvedAddrs
+where
+    I: Iterator,
+    I::Item: ResolvedAddr,
+{
+    ResolvedAddrs::new(addrs.map(|addr| addr.into_socket_addr(0)).collect())
+}
+
+impl Future for FallbackFuture {
+    type Output = Result<ResolvedAddrs, BoxError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.fut.as_mut().poll(cx)
+    }
+}
+
+impl fmt::Debug for FallbackFuture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("FallbackFuture")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::net::{IpAddr, SocketAddr};
+    use std::task::{self, Poll};
+
+    use futures_util::future;
+    use tower_service::Service;
+
+    use super::super::Name;
+    use super::FallbackResolver;
+
+    /// A resolver returning `addrs`, or an error if `None`.
+    #[derive(Clone)]
+    struct StaticResolver(Option<Vec<IpAddr>>);
+
+    impl Service<Name> for StaticResolver {
+        type Response = std::vec::IntoIter<IpAddr>;
+        type Error = io::Error;
+        type Future = future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Name) -> Self::Future {
+            future::ready(match self.0 {
+                Some(ref addrs) => Ok(addrs.clone().into_iter()),
+                None => Err(io::Error::new(io::ErrorKind::Other, "static error")),
+            })
+        }
+    }
+
+    async fn resolve(primary: StaticResolver, fallback: StaticResolver) -> Vec<SocketAddr> {
+        let mut resolver = FallbackResolver::new(primary, fallback);
+        let addrs = resolver
+            .call("example.domain".parse().unwrap())
+            .await
+            .expect("resolve");
+        addrs.collect()
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_error_or_no_addresses() {
+        let primary = vec![IpAddr::from([10, 0, 0, 1])];
+        let fallback = vec![IpAddr::from([10, 0, 0, 2])];
+        let expected = |ip: IpAddr| vec![SocketAddr::new(ip, 0)];
+
+        let addrs = resolve(
+            StaticResolver(Some(primary.clone())),
+            StaticResolver(Some(fallback.clone())),
+        )
+        .await;
+        assert_eq!(addrs, expected(primary[0]));
+
+        let addrs = resolve(StaticResolver(None), StaticResolver(Some(fallback.clone()))).await;
+        assert_eq!(addrs, expected(fallback[0]));
+
+        let addrs = resolve(
+            StaticResolver(Some(Vec::new())),
+            StaticResolver(Some(fallback.clone())),
+        )
+        .await;
+        assert_eq!(addrs, expected(fallback[0]));
+    }
+}
@@ -4,6 +4,11 @@
 //!
 //! - A [`GaiResolver`](GaiResolver) that is the default resolver for the
 //!   `HttpConnector`.
+//! - A [`DnsResolver`](DnsResolver) that asks name servers itself, and
+//!   caches their answers.
+//! - Resolvers wrapping others, to [override](OverrideResolver) the
+//!   addresses of some names, or [fall back](FallbackResolver) to another
+//!   resolver.
 //! - The `Name` type used as an argument to custom resolvers.
 //!
 //! # Resolvers are `Service`s
@@ -11,6 +16,10 @@
 //! A resolver is just a
 //! `Service<Name, Response = impl Iterator<Item = IpAddr>>`.
 //!
+//! A resolver may also return an `Iterator<Item = SocketAddr>`. The port of
+//! each `SocketAddr` is then connected to instead of the port of the
+//! destination, unless it is `0`.
+//!
 //! A simple resolver that ignores the name and always returns a specific
 //! address:
 //!
@@ -32,7 +41,15 @@ use std::{fmt, io, vec};
 use tokio::task::JoinHandle;
 use tower_service::Service;
 
-pub(super) use self::sealed::Resolve;
+pub use self::fallback::{FallbackFuture, FallbackResolver};
+pub use self::overrides::{OverrideFuture, OverrideResolver};
+pub use self::resolver::{DnsAddrs, DnsFuture, DnsResolver};
+pub(crate) use self::sealed::{Resolve, ResolvedAddr};
+
+mod fallback;
+mod overrides;
+mod resolver;
+mod wire;
 
 /// A domain name to resolve into IP addresses.
 #[derive(Clone, Hash, Eq, PartialEq)]
@@ -56,6 +73,13 @@ pub struct GaiFuture {
     inner: JoinHandle<Result<IpAddrs, io::Error>>,
 }
 
+/// An iterator of socket addresses returned from resolvers wrapping others.
+///
+/// A port of `0` stands for the port of the destination.
+pub struct ResolvedAddrs {
+    iter: vec::IntoIter<SocketAddr>,
+}
+
 impl Name {
     pub(super) fn new(host: String) -> Name {
         Name { host }
@@ -172,6 +196,32 @@ impl fmt::Debug for GaiAddrs {
     }
 }
 
+impl ResolvedAddrs {
+    fn new(addrs: Vec<SocketAddr>) -> ResolvedAddrs {
+        ResolvedAddrs {
+            iter: addrs.into_iter(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.iter.as_slice().is_empty()
+    }
+}
+
+impl Iterator for ResolvedAddrs {
+    type Item = SocketAddr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+impl fmt::Debug for ResolvedAddrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter.as_slice()).finish()
+    }
+}
+
 pub(super) struct IpAddrs {
     iter: vec::IntoIter<SocketAddr>,
 }
@@ -230,8 +280,23 @@ impl IpAddrs {
         }
     }
 
-    pub(super) fn is_empty(&self) -> bool {
-        self.iter.as_slice().is_empty()
+    /// Orders the addresses alternating between the preferred and the
+    /// fallback address family, as in RFC 8305.
+    pub(super) fn interleave(
+        self,
+        local_addr_ipv4: Option<Ipv4Addr>,
+        local_addr_ipv6: Option<Ipv6Addr>,
+    ) -> IpAddrs {
+        let (mut preferred, mut fallback) =
+            self.split_by_preference(local_addr_ipv4, local_addr_ipv6);
+        let mut addrs = Vec::with_capacity(preferred.len() + fallback.len());
+        loop {
+            match (preferred.next(), fallback.next()) {
+                (None, None) => break,
+                (first, second) => addrs.extend(first.into_iter().chain(second)),
+            }
+        }
+        IpAddrs::new(addrs)
     }
 
     pub(super) fn len(&self) -> usize {
@@ -312,13 +377,34 @@ impl Future for TokioThreadpoolGaiFuture {
 */
 
 mod sealed {
-    use super::{IpAddr, Name};
+    use super::{IpAddr, Name, SocketAddr};
     use crate::common::{task, Future, Poll};
     use tower_service::Service;
 
+    // An address returned by a resolver, which can also pick the port.
+    pub trait ResolvedAddr {
+        fn into_socket_addr(self, default_port: u16) -> SocketAddr;
+    }
+
+    impl ResolvedAddr for IpAddr {
+        fn into_socket_addr(self, default_port: u16) -> SocketAddr {
+            SocketAddr::new(self, default_port)
+        }
+    }
+
+    impl ResolvedAddr for SocketAddr {
+        fn into_socket_addr(mut self, default_port: u16) -> SocketAddr {
+            if self.port() == 0 {
+                self.set_port(default_port);
+            }
+            self
+        }
+    }
+
     // "Trait alias" for `Service<Name, Response = Addrs>`
     pub trait Resolve {
-        type Addrs: Iterator<Item = IpAddr>;
+        type Addr: ResolvedAddr;
+        type Addrs: Iterator<Item = Self::Addr>;
         type Error: Into<Box<dyn std::error::Error + Send + Sync>>;
         type Future: Future<Output = Result<Self::Addrs, Self::Error>>;
 
@@ -329,9 +415,11 @@ mod sealed {
     impl<S> Resolve for S
     where
         S: Service<Name>,
-        S::Response: Iterator<Item = IpAddr>,
+        S::Response: Iterator,
+        <S::Response as Iterator>::Item: ResolvedAddr,
         S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
     {
+        type Addr = <S::Response as Iterator>::Item;
         type Addrs = S::Response;
         type Error = S::Error;
         type Future = S::Future;
@@ -394,19 +482,40 @@ mod tests {
         assert!(preferred.next().unwrap().is_ipv6());
         assert!(fallback.next().unwrap().is_ipv4());
 
-        let (mut preferred, fallback) = IpAddrs {
+        let (mut preferred, mut fallback) = IpAddrs {
             iter: vec![v4_addr, v6_addr].into_iter(),
         }
         .split_by_preference(Some(ip_v4), None);
         assert!(preferred.next().unwrap().is_ipv4());
-        assert!(fallback.is_empty());
+        assert!(fallback.next().is_none());
 
-        let (mut preferred, fallback) = IpAddrs {
+        let (mut preferred, mut fallback) = IpAddrs {
             iter: vec![v4_addr, v6_addr].into_iter(),
         }
         .split_by_preference(None, Some(ip_v6));
         assert!(preferred.next().unwrap().is_ipv6());
-        assert!(fallback.is_empty());
+        assert!(fallback.next().is_none());
+    }
+
+    #[test]
+    fn ip_addrs_interleave() {
+        let v4 = |i| SocketAddr::from(([10, 0, 0, i], 80));
+        let v6 = |i| SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, i as u16], 80));
+
+        let addrs = IpAddrs::new(vec![v6(1), v6(2), v6(3), v4(1), v4(2)])
+            .interleave(None, None)
+            .collect::<Vec<_>>();
+        assert_eq!(addrs, vec![v6(1), v4(1), v6(2), v4(2), v6(3)]);
+
+        let addrs = IpAddrs::new(vec![v4(1), v4(2), v6(1)])
+            .interleave(None, None)
+            .collect::<Vec<_>>();
+        assert_eq!(addrs, vec![v4(1), v6(1), v4(2)]);
+
+        let addrs = IpAddrs::new(vec![v6(1), v4(1), v4(2)])
+            .interleave(Some(Ipv4Addr::LOCALHOST), None)
+            .collect::<Vec<_>>();
+        assert_eq!(addrs, vec![v4(1), v4(2)]);
     }
 
     #[test]
@@ -417,6 +526,14 @@ mod tests {
         assert_eq!(name.to_string(), DOMAIN);
     }
 
+    #[test]
+    fn resolved_addr_ports() {
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        assert_eq!(ip.into_socket_addr(80), SocketAddr::new(ip, 80));
+        assert_eq!(SocketAddr::new(ip, 0).into_socket_addr(80).port(), 80);
+        assert_eq!(SocketAddr::new(ip, 8443).into_socket_addr(80).port(), 8443);
+    }
+
     #[test]
     fn ip_addrs_try_parse_v6() {
         let dst = ::http::Uri::from_static("http://[::1]:8080/");
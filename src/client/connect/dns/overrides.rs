@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{self, Poll};
+
+use tower_service::Service;
+
+use super::{Name, Resolve, ResolvedAddr, ResolvedAddrs};
+
+/// A resolver answering some names with fixed addresses, and the others
+/// with a wrapped resolver.
+///
+/// This can pin a name to the addresses of a test server, or move a name
+/// to new servers without waiting on DNS.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "runtime")]
+/// # fn doc() {
+/// use hyper::client::HttpConnector;
+/// use hyper::client::connect::dns::{GaiResolver, OverrideResolver};
+///
+/// let mut resolver = OverrideResolver::new(GaiResolver::new());
+/// // Requests to `api.internal` go to port 8443 of either address...
+/// resolver.set_override(
+///     "api.internal",
+///     vec![([10, 0, 0, 5], 8443).into(), ([10, 0, 0, 6], 8443).into()],
+/// );
+/// // ...and to `db.internal` on the port of their URL.
+/// resolver.set_override("db.internal", vec![([10, 0, 1, 5], 0).into()]);
+///
+/// let connector = HttpConnector::new_with_resolver(resolver);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct OverrideResolver<R> {
+    overrides: Arc<HashMap<String, Vec<SocketAddr>>>,
+    inner: R,
+}
+
+/// A future to resolve a name returned by `OverrideResolver`.
+#[must_use = "futures do nothing unless polled"]
+pub struct OverrideFuture {
+    fut: Pin<Box<dyn Future<Output = Result<ResolvedAddrs, BoxError>> + Send>>,
+}
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+impl<R> OverrideResolver<R> {
+    /// Construct a new `OverrideResolver`, resolving names without an
+    /// override with `inner`.
+    pub fn new(inner: R) -> OverrideResolver<R> {
+        OverrideResolver {
+            overrides: Arc::new(HashMap::new()),
+            inner,
+        }
+    }
+
+    /// Set the addresses to resolve `host` to.
+    ///
+    /// Connections are made to the port of each address, unless it is `0`,
+    /// in which case the port of the destination is used. This replaces any
+    /// addresses previously set for `host`.
+    pub fn set_override(&mut self, host: &str, addrs: Vec<SocketAddr>) {
+        Arc::make_mut(&mut self.overrides).insert(normalize(host), addrs);
+    }
+
+    /// Remove the override of `host`, so that it is resolved by the wrapped
+    /// resolver again.
+    pub fn remove_override(&mut self, host: &str) {
+        Arc::make_mut(&mut self.overrides).remove(&normalize(host));
+    }
+
+    /// Add the addresses listed in a hosts file, such as `/etc/hosts`.
+    ///
+    /// Each line of the file is an IP address followed by the names it is an
+    /// address of, and `#` starts a comment. The addresses are added to any
+    /// that were already set for those names.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the file can't be read.
+    pub fn load_hosts_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let hosts = std::fs::read_to_string(path)?;
+        let overrides = Arc::make_mut(&mut self.overrides);
+        for (host, ip) in parse_hosts(&hosts) {
+            overrides
+                .entry(normalize(host))
+                .or_default()
+                .push(SocketAddr::new(ip, 0));
+        }
+        Ok(())
+    }
+}
+
+impl<R> Service<Name> for OverrideResolver<R>
+where
+    R: Resolve,
+    R::Future: Send + 'static,
+{
+    type Response = ResolvedAddrs;
+    type Error = BoxError;
+    type Future = OverrideFuture;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        if let Some(addrs) = self.overrides.get(&normalize(name.as_str())) {
+            trace!("resolving {:?} with an override", name);
+            let addrs = ResolvedAddrs::new(addrs.clone());
+            return OverrideFuture {
+                fut: Box::pin(async move { Ok(addrs) }),
+            };
+        }
+
+        let fut = self.inner.resolve(name);
+        OverrideFuture {
+            fut: Box::pin(async move {
+                let addrs = fut.await.map_err(Into::into)?;
+                Ok(ResolvedAddrs::new(
+                    addrs.map(|addr| addr.into_socket_addr(0)).collect(),
+                ))
+            }),
+        }
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for OverrideResolver<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverrideResolver")
+            .field("overrides", &self.overrides)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl Future for OverrideFuture {
+    type Output = Result<ResolvedAddrs, BoxError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.fut.as_mut().poll(cx)
+    }
+}
+
+impl fmt::Debug for OverrideFuture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("OverrideFuture")
+    }
+}
+
+/// Names are matched ignoring case, and a trailing dot.
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn parse_hosts(hosts: &str) -> impl Iterator<Item = (&str, IpAddr)> {
+    hosts.lines().flat_map(|line| {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let mut words = line.split_whitespace();
+        // Lines that don't start with an address are skipped.
+        let ip = words.next().and_then(|ip| ip.parse::<IpAddr>().ok());
+        words.filter_map(move |host| ip.map(|ip| (host, ip)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::{IpAddr, SocketAddr};
+    use std::task::{self, Poll};
+
+    use futures_util::future;
+    use tower_service::Service;
+
+    use super::super::Name;
+    use super::{parse_hosts, OverrideResolver};
+
+    #[derive(Clone)]
+    struct StaticResolver(IpAddr);
+
+    impl Service<Name> for StaticResolver {
+        type Response = std::vec::IntoIter<IpAddr>;
+        type Error = Infallible;
+        type Future = future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Name) -> Self::Future {
+            future::ok(vec![self.0].into_iter())
+        }
+    }
+
+    async fn resolve(
+        resolver: &mut OverrideResolver<StaticResolver>,
+        host: &str,
+    ) -> Vec<SocketAddr> {
+        let addrs = resolver.call(host.parse().unwrap()).await.expect("resolve");
+        addrs.collect()
+    }
+
+    #[tokio::test]
+    async fn overrides_names() {
+        let mut resolver = OverrideResolver::new(StaticResolver([10, 0, 0, 1].into()));
+        let pinned = vec![SocketAddr::from(([10, 0, 0, 5], 8443))];
+        resolver.set_override("API.internal.", pinned.clone());
+
+        assert_eq!(resolve(&mut resolver, "api.internal").await, pinned);
+        assert_eq!(
+            resolve(&mut resolver, "other.internal").await,
+            vec![SocketAddr::from(([10, 0, 0, 1], 0))]
+        );
+
+        resolver.remove_override("api.internal");
+        assert_eq!(
+            resolve(&mut resolver, "api.internal").await,
+            vec![SocketAddr::from(([10, 0, 0, 1], 0))]
+        );
+    }
+
+    #[test]
+    fn parses_hosts() {
+        let hosts = "\
+            # The loopback\n\
+            127.0.0.1 localhost loopback # comment\n\
+            ::1\tlocalhost ip6-localhost\n\
+            not-an-ip example.domain\n\
+            \n\
+            10.0.0.1 example.domain\n\
+        ";
+        let parsed = parse_hosts(hosts).collect::<Vec<_>>();
+        let v4: IpAddr = [127, 0, 0, 1].into();
+        let v6: IpAddr = "::1".parse().unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                ("localhost", v4),
+                ("loopback", v4),
+                ("localhost", v6),
+                ("ip6-localhost", v6),
+                ("example.domain", [10, 0, 0, 1].into()),
+            ]
+        );
+    }
+}
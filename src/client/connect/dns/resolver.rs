@@ -0,0 +1,724 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{self, Poll};
+use std::time::{Duration, Instant};
+use std::{fmt, vec};
+
+use futures_channel::oneshot;
+use futures_util::future::join;
+use tower_service::Service;
+
+use super::wire::{self, Answer};
+use super::Name;
+
+/// A resolver asking name servers directly, which caches their answers.
+///
+/// Unlike the [`GaiResolver`](super::GaiResolver), this doesn't block a
+/// thread for each lookup. It sends `A` and `AAAA` queries over UDP, or TCP
+/// if a response is too large, and asks the next name server if one doesn't
+/// respond in time.
+///
+/// Answers are cached for as long as their TTL allows, and so is a name not
+/// existing. Concurrent lookups of the same name share a single set of
+/// queries. Clones of a `DnsResolver` share the same cache.
+///
+/// Names are queried as given, without the search domains of the system,
+/// and `/etc/hosts` isn't read.
+#[derive(Clone)]
+pub struct DnsResolver {
+    config: Arc<Config>,
+    cache: Arc<Mutex<Cache>>,
+}
+
+#[derive(Clone)]
+struct Config {
+    name_servers: Vec<SocketAddr>,
+    timeout: Duration,
+    max_ttl: Duration,
+    negative_ttl: Duration,
+    cache_size: usize,
+}
+
+struct Cache {
+    entries: HashMap<Name, Entry>,
+}
+
+enum Entry {
+    Found(Vec<IpAddr>, Instant),
+    NotFound(Instant),
+    /// Lookups waiting for the queries in flight.
+    Pending(Vec<oneshot::Sender<io::Result<Vec<IpAddr>>>>),
+}
+
+/// What the name servers had to say about a name.
+enum Outcome {
+    Found(Vec<IpAddr>, Duration),
+    NotFound(Duration),
+    Failed(io::Error),
+}
+
+/// The `Entry::Pending` of a lookup in flight.
+///
+/// If the lookup task is dropped before completing, such as when it panics
+/// or the runtime shuts down, the entry is removed. This drops its waiters,
+/// canceling their lookups, and lets the next lookup of the name retry.
+struct PendingLookup {
+    cache: Arc<Mutex<Cache>>,
+    name: Option<Name>,
+}
+
+/// An iterator of IP addresses returned from `DnsResolver`.
+pub struct DnsAddrs {
+    iter: vec::IntoIter<IpAddr>,
+}
+
+/// A future to resolve a name returned by `DnsResolver`.
+pub struct DnsFuture {
+    inner: Inner,
+}
+
+enum Inner {
+    Cached(Option<io::Result<Vec<IpAddr>>>),
+    Waiting(oneshot::Receiver<io::Result<Vec<IpAddr>>>),
+}
+
+// ===== impl DnsResolver =====
+
+impl DnsResolver {
+    /// Construct a new `DnsResolver` asking the given name servers, in order.
+    pub fn new<I>(name_servers: I) -> DnsResolver
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        DnsResolver {
+            config: Arc::new(Config {
+                name_servers: name_servers.into_iter().collect(),
+                timeout: Duration::from_secs(5),
+                max_ttl: Duration::from_secs(24 * 60 * 60),
+                negative_ttl: Duration::from_secs(30),
+                cache_size: 1024,
+            }),
+            cache: Arc::new(Mutex::new(Cache {
+                entries: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Construct a new `DnsResolver` asking the name servers configured in
+    /// `/etc/resolv.conf`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the file can't be read, or doesn't list any name
+    /// servers.
+    #[cfg(unix)]
+    pub fn from_system_conf() -> io::Result<DnsResolver> {
+        let conf = std::fs::read_to_string("/etc/resolv.conf")?;
+        let name_servers = parse_resolv_conf(&conf);
+        if name_servers.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no name servers in /etc/resolv.conf",
+            ));
+        }
+        Ok(DnsResolver::new(name_servers))
+    }
+
+    /// Set how long to wait for the response of a name server, before
+    /// asking the next one.
+    ///
+    /// Default is 5 seconds.
+    pub fn set_timeout(&mut self, dur: Duration) {
+        self.config_mut().timeout = dur;
+    }
+
+    /// Set the longest time to cache addresses for, even if their TTL is
+    /// longer.
+    ///
+    /// Default is 1 day.
+    pub fn set_max_ttl(&mut self, dur: Duration) {
+        self.config_mut().max_ttl = dur;
+    }
+
+    /// Set the longest time to cache that a name has no addresses for.
+    ///
+    /// This is also how long it is cached if the name server doesn't say
+    /// for how long to. Setting it to zero disables negative caching.
+    ///
+    /// Default is 30 seconds.
+    pub fn set_negative_ttl(&mut self, dur: Duration) {
+        self.config_mut().negative_ttl = dur;
+    }
+
+    /// Set the maximum number of names to cache.
+    ///
+    /// Default is 1024.
+    pub fn set_cache_size(&mut self, size: usize) {
+        self.config_mut().cache_size = size;
+    }
+
+    // private
+
+    fn config_mut(&mut self) -> &mut Config {
+        // If the are DnsResolver clones, this will clone the inner
+        // config. So mutating the config won't ever affect previous
+        // clones.
+        Arc::make_mut(&mut self.config)
+    }
+
+    fn spawn_lookup(&self, name: Name) {
+        let config = self.config.clone();
+        let cache = self.cache.clone();
+        tokio::spawn(async move {
+            let pending = PendingLookup {
+                cache,
+                name: Some(name),
+            };
+            let outcome = lookup(&config, pending.name()).await;
+            pending.complete(outcome, config.cache_size);
+        });
+    }
+}
+
+impl Service<Name> for DnsResolver {
+    type Response = DnsAddrs;
+    type Error = io::Error;
+    type Future = DnsFuture;
+
+    fn poll_ready(&mut self, _cx: &mut task::Context<'_>) -> Poll<Result<(), io::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let mut cache = self.cache.lock().unwrap();
+        let now = Instant::now();
+        match cache.entries.get_mut(&name) {
+            Some(Entry::Found(addrs, expires)) if *expires > now => {
+                trace!("dns cache hit for {:?}", name);
+                return DnsFuture {
+                    inner: Inner::Cached(Some(Ok(addrs.clone()))),
+                };
+            }
+            Some(Entry::NotFound(expires)) if *expires > now => {
+                trace!("dns negative cache hit for {:?}", name);
+                return DnsFuture {
+                    inner: Inner::Cached(Some(Err(not_found(&name)))),
+                };
+            }
+            Some(Entry::Pending(waiters)) => {
+                trace!("dns lookup of {:?} already in flight", name);
+                let (tx, rx) = oneshot::channel();
+                waiters.push(tx);
+                return DnsFuture {
+                    inner: Inner::Waiting(rx),
+                };
+            }
+            _ => (),
+        }
+
+        let (tx, rx) = oneshot::channel();
+        cache.entries.insert(name.clone(), Entry::Pending(vec![tx]));
+        drop(cache);
+        self.spawn_lookup(name);
+        DnsFuture {
+            inner: Inner::Waiting(rx),
+        }
+    }
+}
+
+impl fmt::Debug for DnsResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsResolver")
+            .field("name_servers", &self.config.name_servers)
+            .finish()
+    }
+}
+
+/// Look up both the IPv4 and IPv6 addresses of a name.
+async fn lookup(config: &Config, name: &Name) -> Outcome {
+    debug!("resolving host={:?}", name.as_str());
+    let (v4, v6) = join(
+        query(config, name, wire::TYPE_A),
+        query(config, name, wire::TYPE_AAAA),
+    )
+    .await;
+
+    let negative_ttl = |ttl: Option<u32>| match ttl {
+        Some(secs) => Duration::from_secs(secs.into()).min(config.negative_ttl),
+        None => config.negative_ttl,
+    };
+    match (v4, v6) {
+        (Ok(Answer::Addrs(mut v4, ttl4)), Ok(Answer::Addrs(v6, ttl6))) => {
+            v4.extend(v6);
+            let ttl = Duration::from_secs(ttl4.min(ttl6).into());
+            Outcome::Found(v4, ttl.min(config.max_ttl))
+        }
+        (Ok(Answer::Addrs(addrs, ttl)), _) | (_, Ok(Answer::Addrs(addrs, ttl))) => {
+            let ttl = Duration::from_secs(ttl.into());
+            Outcome::Found(addrs, ttl.min(config.max_ttl))
+        }
+        (Ok(Answer::NotFound(ttl4)), Ok(Answer::NotFound(ttl6))) => {
+            Outcome::NotFound(negative_ttl(ttl4).min(negative_ttl(ttl6)))
+        }
+        (Err(err), _) | (_, Err(err)) => Outcome::Failed(err),
+    }
+}
+
+/// Ask each name server in turn, until one answers.
+async fn query(config: &Config, name: &Name, qtype: u16) -> io::Result<Answer> {
+    let mut last_err = None;
+    for &server in &config.name_servers {
+        match wire::query(server, name.as_str(), qtype, config.timeout).await {
+            Ok(answer) => return Ok(answer),
+            Err(err) => {
+                debug!("dns query to {} failed: {}", server, err);
+                if err.kind() == io::ErrorKind::InvalidInput {
+                    return Err(err);
+                }
+                last_err = Some(err);
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "no name servers")))
+}
+
+fn not_found(name: &Name) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no addresses found for {}", name),
+    )
+}
+
+#[cfg(unix)]
+fn parse_resolv_conf(conf: &str) -> Vec<SocketAddr> {
+    conf.lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            if words.next() != Some("nameserver") {
+                return None;
+            }
+            // Link-local IPv6 servers with a `%scope` aren't supported.
+            let ip = words.next()?.parse::<IpAddr>().ok()?;
+            Some(SocketAddr::new(ip, 53))
+        })
+        .collect()
+}
+
+// ===== impl Cache =====
+
+impl Cache {
+    /// Store the outcome of a lookup, and hand it to the waiting lookups.
+    fn complete(&mut self, name: Name, outcome: Outcome, max_entries: usize) {
+        let waiters = match self.entries.remove(&name) {
+            Some(Entry::Pending(waiters)) => waiters,
+            _ => Vec::new(),
+        };
+
+        let now = Instant::now();
+        let no_ttl = Duration::from_secs(0);
+        let result = match outcome {
+            Outcome::Found(addrs, ttl) => {
+                if ttl > no_ttl {
+                    let entry = Entry::Found(addrs.clone(), now + ttl);
+                    self.insert(name, entry, now, max_entries);
+                }
+                Ok(addrs)
+            }
+            Outcome::NotFound(ttl) => {
+                let err = not_found(&name);
+                if ttl > no_ttl {
+                    self.insert(name, Entry::NotFound(now + ttl), now, max_entries);
+                }
+                Err(err)
+            }
+            Outcome::Failed(err) => Err(err),
+        };
+
+        for tx in waiters {
+            let result = match result {
+                Ok(ref addrs) => Ok(addrs.clone()),
+                Err(ref err) => Err(io::Error::new(err.kind(), err.to_string())),
+            };
+            let _ = tx.send(result);
+        }
+    }
+
+    fn insert(&mut self, name: Name, entry: Entry, now: Instant, max_entries: usize) {
+        if self.entries.len() >= max_entries {
+            self.entries.retain(|_, entry| match *entry {
+                Entry::Found(_, expires) | Entry::NotFound(expires) => expires > now,
+                Entry::Pending(_) => true,
+            });
+        }
+        if self.entries.len() >= max_entries {
+            // Make room by dropping the entry closest to expiring.
+            let soonest = self
+                .entries
+                .iter()
+                .filter_map(|(name, entry)| match *entry {
+                    Entry::Found(_, expires) | Entry::NotFound(expires) => Some((expires, name)),
+                    Entry::Pending(_) => None,
+                })
+                .min_by_key(|&(expires, _)| expires)
+                .map(|(_, name)| name.clone());
+            match soonest {
+                Some(name) => {
+                    self.entries.remove(&name);
+                }
+                None => return,
+            }
+        }
+        self.entries.insert(name, entry);
+    }
+}
+
+// ===== impl PendingLookup =====
+
+impl PendingLookup {
+    fn name(&self) -> &Name {
+        self.name.as_ref().expect("lookup already complete")
+    }
+
+    fn complete(mut self, outcome: Outcome, max_entries: usize) {
+        let name = self.name.take().expect("lookup already complete");
+        self.cache
+            .lock()
+            .unwrap()
+            .complete(name, outcome, max_entries);
+    }
+}
+
+impl Drop for PendingLookup {
+    fn drop(&mut self) {
+        let name = match self.name.take() {
+            Some(name) => name,
+            None => return,
+        };
+        // Don't panic again if the lock was poisoned while unwinding.
+        if let Ok(mut cache) = self.cache.lock() {
+            if let Some(Entry::Pending(_)) = cache.entries.get(&name) {
+                trace!("dns lookup of {:?} was dropped", name);
+                cache.entries.remove(&name);
+            }
+        }
+    }
+}
+
+// ===== impl DnsFuture =====
+
+impl Future for DnsFuture {
+    type Output = Result<DnsAddrs, io::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let result = match self.inner {
+            Inner::Cached(ref mut result) => result.take().expect("polled after complete"),
+            Inner::Waiting(ref mut rx) => match ready!(Pin::new(rx).poll(cx)) {
+                Ok(result) => result,
+                Err(_canceled) => Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "dns lookup was canceled",
+                )),
+            },
+        };
+        Poll::Ready(result.map(|addrs| DnsAddrs {
+            iter: addrs.into_iter(),
+        }))
+    }
+}
+
+impl fmt::Debug for DnsFuture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("DnsFuture")
+    }
+}
+
+// ===== impl DnsAddrs =====
+
+impl Iterator for DnsAddrs {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+impl fmt::Debug for DnsAddrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("DnsAddrs")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_util::FutureExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, UdpSocket};
+    use tower_service::Service;
+
+    use super::super::wire::TYPE_A;
+    use super::super::Name;
+    use super::DnsResolver;
+
+    #[derive(Clone)]
+    struct Reply {
+        rcode: u8,
+        ttl: u32,
+        addrs: Vec<IpAddr>,
+        truncated: bool,
+        delay: Duration,
+    }
+
+    impl Reply {
+        fn addrs(addrs: &[IpAddr], ttl: u32) -> Reply {
+            Reply {
+                rcode: 0,
+                ttl,
+                addrs: addrs.to_vec(),
+                truncated: false,
+                delay: Duration::from_secs(0),
+            }
+        }
+
+        fn nxdomain() -> Reply {
+            Reply {
+                rcode: 3,
+                ..Reply::addrs(&[], 0)
+            }
+        }
+
+        /// Encode the response to `query`, with the addresses of its type.
+        fn encode(&self, query: &[u8], truncate: bool) -> Vec<u8> {
+            let qtype = u16::from_be_bytes([query[query.len() - 4], query[query.len() - 3]]);
+            let addrs = self
+                .addrs
+                .iter()
+                .filter(|ip| ip.is_ipv4() == (qtype == TYPE_A))
+                .collect::<Vec<_>>();
+
+            let mut msg = query.to_vec();
+            msg[2] = if truncate { 0x83 } else { 0x81 };
+            msg[3] = 0x80 | self.rcode;
+            msg[7] = if truncate { 0 } else { addrs.len() as u8 };
+            if truncate {
+                return msg;
+            }
+            for ip in addrs {
+                msg.extend_from_slice(&[0xc0, 0x0c, 0, qtype as u8, 0, 1]);
+                msg.extend_from_slice(&self.ttl.to_be_bytes());
+                match ip {
+                    IpAddr::V4(ip) => {
+                        msg.extend_from_slice(&[0, 4]);
+                        msg.extend_from_slice(&ip.octets());
+                    }
+                    IpAddr::V6(ip) => {
+                        msg.extend_from_slice(&[0, 16]);
+                        msg.extend_from_slice(&ip.octets());
+                    }
+                }
+            }
+            msg
+        }
+    }
+
+    /// A stand-in name server, answering every query with `reply` over both
+    /// UDP and TCP, and counting the queries.
+    async fn name_server(reply: Reply) -> (SocketAddr, Arc<AtomicUsize>) {
+        let udp = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = udp.local_addr().unwrap();
+        let tcp = TcpListener::bind(addr).await.unwrap();
+        let queries = Arc::new(AtomicUsize::new(0));
+
+        let udp_reply = reply.clone();
+        let udp_queries = queries.clone();
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            loop {
+                let (n, from) = udp.recv_from(&mut buf).await.unwrap();
+                udp_queries.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(udp_reply.delay).await;
+                let msg = udp_reply.encode(&buf[..n], udp_reply.truncated);
+                udp.send_to(&msg, from).await.unwrap();
+            }
+        });
+        tokio::spawn(async move {
+            loop {
+                let (mut sock, _) = tcp.accept().await.unwrap();
+                let mut len = [0; 2];
+                sock.read_exact(&mut len).await.unwrap();
+                let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+                sock.read_exact(&mut buf).await.unwrap();
+                let msg = reply.encode(&buf, false);
+                sock.write_all(&(msg.len() as u16).to_be_bytes())
+                    .await
+                    .unwrap();
+                sock.write_all(&msg).await.unwrap();
+            }
+        });
+        (addr, queries)
+    }
+
+    async fn resolve(resolver: &mut DnsResolver, name: &str) -> std::io::Result<Vec<IpAddr>> {
+        let addrs = resolver.call(name.parse::<Name>().unwrap()).await?;
+        Ok(addrs.collect())
+    }
+
+    fn ips() -> Vec<IpAddr> {
+        vec![[10, 0, 0, 1].into(), "::1".parse().unwrap()]
+    }
+
+    #[tokio::test]
+    async fn resolves_and_caches() {
+        let (addr, queries) = name_server(Reply::addrs(&ips(), 60)).await;
+        let mut resolver = DnsResolver::new(vec![addr]);
+
+        assert_eq!(
+            resolve(&mut resolver, "example.domain").await.unwrap(),
+            ips()
+        );
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+        assert_eq!(
+            resolve(&mut resolver, "example.domain").await.unwrap(),
+            ips()
+        );
+        assert_eq!(queries.load(Ordering::SeqCst), 2, "cached");
+
+        resolve(&mut resolver, "other.domain").await.unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_isnt_cached() {
+        let (addr, queries) = name_server(Reply::addrs(&ips(), 0)).await;
+        let mut resolver = DnsResolver::new(vec![addr]);
+
+        resolve(&mut resolver, "example.domain").await.unwrap();
+        resolve(&mut resolver, "example.domain").await.unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn caches_not_found() {
+        let (addr, queries) = name_server(Reply::nxdomain()).await;
+        let mut resolver = DnsResolver::new(vec![addr]);
+
+        let err = resolve(&mut resolver, "missing.domain").await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        let err = resolve(&mut resolver, "missing.domain").await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(queries.load(Ordering::SeqCst), 2, "cached");
+
+        let mut uncached = DnsResolver::new(vec![addr]);
+        uncached.set_negative_ttl(Duration::from_secs(0));
+        resolve(&mut uncached, "missing.domain").await.unwrap_err();
+        resolve(&mut uncached, "missing.domain").await.unwrap_err();
+        assert_eq!(queries.load(Ordering::SeqCst), 6);
+    }
+
+    #[tokio::test]
+    async fn coalesces_concurrent_lookups() {
+        let reply = Reply {
+            delay: Duration::from_millis(50),
+            ..Reply::addrs(&ips(), 60)
+        };
+        let (addr, queries) = name_server(reply).await;
+        let resolver = DnsResolver::new(vec![addr]);
+
+        let (a, b) = futures_util::future::join(
+            resolve(&mut resolver.clone(), "example.domain"),
+            resolve(&mut resolver.clone(), "example.domain"),
+        )
+        .await;
+        assert_eq!(a.unwrap(), ips());
+        assert_eq!(b.unwrap(), ips());
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn truncated_retries_over_tcp() {
+        let reply = Reply {
+            truncated: true,
+            ..Reply::addrs(&ips(), 60)
+        };
+        let (addr, _) = name_server(reply).await;
+        let mut resolver = DnsResolver::new(vec![addr]);
+
+        assert_eq!(
+            resolve(&mut resolver, "example.domain").await.unwrap(),
+            ips()
+        );
+    }
+
+    #[tokio::test]
+    async fn next_server_after_timeout() {
+        // Bound, but never answering.
+        let silent = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let (addr, _) = name_server(Reply::addrs(&ips(), 60)).await;
+        let mut resolver = DnsResolver::new(vec![silent.local_addr().unwrap(), addr]);
+        resolver.set_timeout(Duration::from_millis(50));
+
+        assert_eq!(
+            resolve(&mut resolver, "example.domain").await.unwrap(),
+            ips()
+        );
+    }
+
+    #[test]
+    fn dropped_lookup_cancels_waiters() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        // Bound, but never answering.
+        let silent = rt
+            .block_on(UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))))
+            .unwrap();
+        let mut resolver = DnsResolver::new(vec![silent.local_addr().unwrap()]);
+
+        let name = "example.domain".parse::<Name>().unwrap();
+        let lookup = rt.block_on(async { resolver.call(name) });
+        rt.block_on(async { tokio::time::sleep(Duration::from_millis(10)).await });
+        assert_eq!(resolver.cache.lock().unwrap().entries.len(), 1);
+
+        // Shutting down the runtime drops the lookup task...
+        drop(rt);
+        assert!(resolver.cache.lock().unwrap().entries.is_empty());
+
+        // ...which cancels the lookups waiting on it.
+        let err = lookup.now_or_never().expect("canceled").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parse_resolv_conf() {
+        let conf = "\
+            # comment\n\
+            search example.domain\n\
+            nameserver 10.0.0.53\n\
+            nameserver ::1\n\
+            nameserver fe80::1%eth0\n\
+            options ndots:2\n\
+        ";
+        assert_eq!(
+            super::parse_resolv_conf(conf),
+            vec![
+                SocketAddr::from(([10, 0, 0, 53], 53)),
+                "[::1]:53".parse().unwrap(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,397 @@
+//! DNS messages, and asking a name server with them over UDP or TCP.
+//!
+//! Only what's needed to look up `A` and `AAAA` records is supported, see
+//! RFC 1035 for the message format.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use futures_util::future::poll_fn;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpStream, UdpSocket};
+
+pub(super) const TYPE_A: u16 = 1;
+pub(super) const TYPE_AAAA: u16 = 28;
+const TYPE_SOA: u16 = 6;
+const CLASS_IN: u16 = 1;
+
+const FLAG_QR: u16 = 0x8000;
+const FLAG_TC: u16 = 0x0200;
+const FLAG_RD: u16 = 0x0100;
+const RCODE_MASK: u16 = 0x000f;
+const RCODE_NXDOMAIN: u16 = 3;
+
+const HEADER_LEN: usize = 12;
+const MAX_UDP_LEN: usize = 4096;
+
+/// The answer of a name server to a query.
+#[derive(Debug, PartialEq)]
+pub(super) enum Answer {
+    /// The addresses found, and the lowest TTL of their records.
+    Addrs(Vec<IpAddr>, u32),
+    /// The name doesn't exist, or has no records of the type asked for.
+    ///
+    /// This has the TTL to cache that for, from the `SOA` record of the
+    /// zone, if the server sent one.
+    NotFound(Option<u32>),
+}
+
+/// Ask `server` for the records of type `qtype` of `name`.
+///
+/// The query is sent over UDP, and sent again over TCP if the response
+/// didn't fit in a datagram.
+pub(super) async fn query(
+    server: SocketAddr,
+    name: &str,
+    qtype: u16,
+    timeout: Duration,
+) -> io::Result<Answer> {
+    let id = next_id();
+    let msg = encode_query(id, name, qtype)?;
+
+    let mut resp = with_timeout(timeout, query_udp(server, id, &msg)).await?;
+    if flags(&resp) & FLAG_TC != 0 {
+        trace!("dns response for {:?} truncated, retrying over TCP", name);
+        resp = with_timeout(timeout, query_tcp(server, id, &msg)).await?;
+    }
+    decode_answer(&resp, qtype)
+}
+
+async fn with_timeout<F>(timeout: Duration, fut: F) -> io::Result<Vec<u8>>
+where
+    F: std::future::Future<Output = io::Result<Vec<u8>>>,
+{
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(res) => res,
+        Err(_elapsed) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "dns query timed out",
+        )),
+    }
+}
+
+async fn query_udp(server: SocketAddr, id: u16, msg: &[u8]) -> io::Result<Vec<u8>> {
+    let local = if server.is_ipv4() {
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))
+    } else {
+        SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(server).await?;
+    socket.send(msg).await?;
+
+    let mut buf = vec![0; MAX_UDP_LEN];
+    loop {
+        let n = socket.recv(&mut buf).await?;
+        // Anything that isn't the response to this query is ignored, instead
+        // of failing the query because of a late or spoofed datagram.
+        if is_response(&buf[..n], id) {
+            buf.truncate(n);
+            return Ok(buf);
+        }
+        trace!("ignoring unexpected dns datagram from {}", server);
+    }
+}
+
+async fn query_tcp(server: SocketAddr, id: u16, msg: &[u8]) -> io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(server).await?;
+
+    let mut req = Vec::with_capacity(2 + msg.len());
+    req.extend_from_slice(&(msg.len() as u16).to_be_bytes());
+    req.extend_from_slice(msg);
+    write_all(&mut stream, &req).await?;
+
+    let mut len = [0; 2];
+    read_exact(&mut stream, &mut len).await?;
+    let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+    read_exact(&mut stream, &mut buf).await?;
+    if !is_response(&buf, id) {
+        return Err(malformed());
+    }
+    Ok(buf)
+}
+
+/// Pick a query ID that's hard for others to guess.
+fn next_id() -> u16 {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish() as u16
+}
+
+pub(super) fn encode_query(id: u16, name: &str, qtype: u16) -> io::Result<Vec<u8>> {
+    let name = name.trim_end_matches('.');
+    if name.is_empty() || name.len() > 253 {
+        return Err(invalid_name(name));
+    }
+
+    let mut buf = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&FLAG_RD.to_be_bytes());
+    // One question, and no other records.
+    buf.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid_name(name));
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    buf.extend_from_slice(&qtype.to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(buf)
+}
+
+pub(super) fn decode_answer(msg: &[u8], qtype: u16) -> io::Result<Answer> {
+    let rcode = flags(msg) & RCODE_MASK;
+    match rcode {
+        0 => (),
+        RCODE_NXDOMAIN => return Ok(Answer::NotFound(negative_ttl(msg)?)),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("dns server error: {}", rcode_str(rcode)),
+            ))
+        }
+    }
+
+    let questions = read_u16(msg, 4)?;
+    let answers = read_u16(msg, 6)?;
+    let mut pos = HEADER_LEN;
+    for _ in 0..questions {
+        pos = skip_name(msg, pos)? + 4;
+    }
+
+    let mut addrs = Vec::new();
+    let mut min_ttl = u32::MAX;
+    for _ in 0..answers {
+        let record = read_record(msg, pos)?;
+        pos = record.end;
+        if record.class != CLASS_IN || record.rtype != qtype {
+            // Such as the CNAME records leading to the addresses.
+            continue;
+        }
+        let ip = match (record.rtype, record.data.len()) {
+            (TYPE_A, 4) => {
+                let mut octets = [0; 4];
+                octets.copy_from_slice(record.data);
+                IpAddr::from(octets)
+            }
+            (TYPE_AAAA, 16) => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(record.data);
+                IpAddr::from(octets)
+            }
+            _ => return Err(malformed()),
+        };
+        addrs.push(ip);
+        min_ttl = min_ttl.min(record.ttl);
+    }
+
+    if addrs.is_empty() {
+        Ok(Answer::NotFound(negative_ttl(msg)?))
+    } else {
+        Ok(Answer::Addrs(addrs, min_ttl))
+    }
+}
+
+/// Find how long a negative answer may be cached, from the `SOA` record in
+/// its authority section (RFC 2308).
+fn negative_ttl(msg: &[u8]) -> io::Result<Option<u32>> {
+    let questions = read_u16(msg, 4)?;
+    let answers = read_u16(msg, 6)?;
+    let authorities = read_u16(msg, 8)?;
+    let mut pos = HEADER_LEN;
+    for _ in 0..questions {
+        pos = skip_name(msg, pos)? + 4;
+    }
+    for _ in 0..answers {
+        pos = read_record(msg, pos)?.end;
+    }
+    for _ in 0..authorities {
+        let record = read_record(msg, pos)?;
+        pos = record.end;
+        if record.rtype == TYPE_SOA {
+            // The MINIMUM field ends the record, after two names and four
+            // other numbers.
+            let data_pos = record.end - record.data.len();
+            let names_end = skip_name(msg, skip_name(msg, data_pos)?)?;
+            if names_end + 20 != record.end {
+                return Err(malformed());
+            }
+            let minimum = read_u32(msg, names_end + 16)?;
+            return Ok(Some(record.ttl.min(minimum)));
+        }
+    }
+    Ok(None)
+}
+
+struct Record<'a> {
+    rtype: u16,
+    class: u16,
+    ttl: u32,
+    data: &'a [u8],
+    end: usize,
+}
+
+fn read_record(msg: &[u8], pos: usize) -> io::Result<Record<'_>> {
+    let pos = skip_name(msg, pos)?;
+    let rtype = read_u16(msg, pos)?;
+    let class = read_u16(msg, pos + 2)?;
+    let ttl = read_u32(msg, pos + 4)?;
+    let len = read_u16(msg, pos + 8)? as usize;
+    let start = pos + 10;
+    let data = msg.get(start..start + len).ok_or_else(malformed)?;
+    Ok(Record {
+        rtype,
+        class,
+        // RFC 2181 says to treat TTLs with the top bit set as zero.
+        ttl: if ttl > i32::MAX as u32 { 0 } else { ttl },
+        data,
+        end: start + len,
+    })
+}
+
+/// Returns the position after the name at `pos`.
+fn skip_name(msg: &[u8], mut pos: usize) -> io::Result<usize> {
+    loop {
+        let len = *msg.get(pos).ok_or_else(malformed)?;
+        match len & 0xc0 {
+            // The end of the name.
+            0x00 if len == 0 => return Ok(pos + 1),
+            0x00 => pos += 1 + len as usize,
+            // A pointer to the rest of the name elsewhere ends it here.
+            0xc0 if pos + 1 < msg.len() => return Ok(pos + 2),
+            _ => return Err(malformed()),
+        }
+    }
+}
+
+fn is_response(msg: &[u8], id: u16) -> bool {
+    msg.len() >= HEADER_LEN && read_u16(msg, 0).ok() == Some(id) && flags(msg) & FLAG_QR != 0
+}
+
+fn flags(msg: &[u8]) -> u16 {
+    read_u16(msg, 2).unwrap_or(0)
+}
+
+fn read_u16(msg: &[u8], pos: usize) -> io::Result<u16> {
+    match msg.get(pos..pos + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+        None => Err(malformed()),
+    }
+}
+
+fn read_u32(msg: &[u8], pos: usize) -> io::Result<u32> {
+    match msg.get(pos..pos + 4) {
+        Some(bytes) => Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(malformed()),
+    }
+}
+
+fn rcode_str(rcode: u16) -> &'static str {
+    match rcode {
+        1 => "format error",
+        2 => "server failure",
+        4 => "not implemented",
+        5 => "refused",
+        _ => "unknown response code",
+    }
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed dns response")
+}
+
+fn invalid_name(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid dns name: {:?}", name),
+    )
+}
+
+async fn write_all<T>(io: &mut T, mut buf: &[u8]) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin,
+{
+    while !buf.is_empty() {
+        let n = poll_fn(|cx| Pin::new(&mut *io).poll_write(cx, buf)).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        buf = &buf[n..];
+    }
+    poll_fn(|cx| Pin::new(&mut *io).poll_flush(cx)).await
+}
+
+async fn read_exact<T>(io: &mut T, buf: &mut [u8]) -> io::Result<()>
+where
+    T: AsyncRead + Unpin,
+{
+    let mut read_buf = ReadBuf::new(buf);
+    while read_buf.remaining() > 0 {
+        let filled = read_buf.filled().len();
+        poll_fn(|cx| Pin::new(&mut *io).poll_read(cx, &mut read_buf)).await?;
+        if read_buf.filled().len() == filled {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_query_labels() {
+        let msg = encode_query(0x1234, "example.com.", TYPE_AAAA).expect("encode");
+        assert_eq!(
+            msg,
+            b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+              \x07example\x03com\x00\x00\x1c\x00\x01"
+        );
+
+        encode_query(1, "a..b", TYPE_A).unwrap_err();
+        encode_query(1, &"a".repeat(64), TYPE_A).unwrap_err();
+    }
+
+    #[test]
+    fn decode_answer_with_cname_and_soa() {
+        let mut msg = encode_query(7, "www.example", TYPE_A).unwrap();
+        msg[2] = 0x81;
+        msg[7] = 2;
+        // www.example CNAME example, TTL 300
+        msg.extend_from_slice(b"\xc0\x0c\x00\x05\x00\x01\x00\x00\x01\x2c\x00\x02\xc0\x10");
+        // example A 10.0.0.1, TTL 60
+        msg.extend_from_slice(b"\xc0\x10\x00\x01\x00\x01\x00\x00\x00\x3c\x00\x04\x0a\x00\x00\x01");
+        assert_eq!(
+            decode_answer(&msg, TYPE_A).expect("decode"),
+            Answer::Addrs(vec![IpAddr::from([10, 0, 0, 1])], 60)
+        );
+
+        let mut msg = encode_query(7, "example", TYPE_AAAA).unwrap();
+        msg[2] = 0x81;
+        msg[3] = 0x83;
+        msg[9] = 1;
+        // example SOA ns. admin. 1 2 3 4 30, TTL 600
+        msg.extend_from_slice(b"\xc0\x0c\x00\x06\x00\x01\x00\x00\x02\x58\x00\x1f");
+        msg.extend_from_slice(b"\x02ns\x00\x05admin\x00");
+        msg.extend_from_slice(b"\x00\x00\x00\x01\x00\x00\x00\x02\x00\x00\x00\x03");
+        msg.extend_from_slice(b"\x00\x00\x00\x04\x00\x00\x00\x1e");
+        assert_eq!(
+            decode_answer(&msg, TYPE_AAAA).expect("decode"),
+            Answer::NotFound(Some(30))
+        );
+
+        msg.truncate(msg.len() - 1);
+        decode_answer(&msg, TYPE_AAAA).unwrap_err();
+    }
+}
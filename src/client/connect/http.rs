@@ -2,20 +2,20 @@ use std::error::Error as StdError;
 use std::fmt;
 use std::future::Future;
 use std::io;
+use std::iter::Peekable;
 use std::marker::PhantomData;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{self, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use futures_util::future::Either;
 use http::uri::{Scheme, Uri};
 use pin_project::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::{TcpSocket, TcpStream};
-use tokio::time::Sleep;
 
-use super::dns::{self, resolve, GaiResolver, Resolve};
+use super::dns::{self, resolve, GaiResolver, Resolve, ResolvedAddr};
 use super::{Connected, Connection};
 //#[cfg(feature = "runtime")] use super::dns::TokioThreadpoolGaiResolver;
 
@@ -64,6 +64,51 @@ pub struct HttpConnector<R = GaiResolver> {
 #[derive(Clone, Debug)]
 pub struct HttpInfo {
     remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    connect_duration: Option<Duration>,
+    dns_duration: Option<Duration>,
+    attempts: Option<usize>,
+    is_reused: bool,
+}
+
+/// A connector for the `http` scheme, whose streams know how they were
+/// connected.
+///
+/// This connects with the [`HttpConnector`](HttpConnector) it wraps, but
+/// returns an [`HttpStream`](HttpStream) instead of a `TcpStream`. Its
+/// [`HttpInfo`](HttpInfo) also has how long it took to resolve the hostname
+/// and to connect, and how many addresses were attempted.
+///
+/// # Example
+///
+/// ```
+/// use hyper::client::connect::{HttpConnector, HttpInfoConnector};
+/// use hyper::Client;
+///
+/// let mut http = HttpConnector::new();
+/// http.set_nodelay(true);
+/// let client = Client::builder().build::<_, hyper::Body>(HttpInfoConnector::new(http));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpInfoConnector<R = GaiResolver> {
+    http: HttpConnector<R>,
+}
+
+/// A TCP stream connected by an [`HttpInfoConnector`](HttpInfoConnector).
+///
+/// Besides the `TcpStream`, this has the [`HttpInfo`](HttpInfo) describing
+/// how it was connected.
+#[derive(Debug)]
+pub struct HttpStream {
+    tcp: TcpStream,
+    info: Option<HttpInfo>,
+}
+
+/// How a `TcpStream` was connected by `HttpConnector::call_async`.
+struct ConnectDetails {
+    dns_duration: Option<Duration>,
+    connect_duration: Duration,
+    attempts: usize,
 }
 
 #[derive(Clone)]
@@ -71,6 +116,7 @@ struct Config {
     connect_timeout: Option<Duration>,
     enforce_http: bool,
     happy_eyeballs_timeout: Option<Duration>,
+    happy_eyeballs_attempt_delay: Duration,
     keep_alive_timeout: Option<Duration>,
     local_address_ipv4: Option<Ipv4Addr>,
     local_address_ipv6: Option<Ipv6Addr>,
@@ -78,6 +124,14 @@ struct Config {
     reuse_address: bool,
     send_buffer_size: Option<usize>,
     recv_buffer_size: Option<usize>,
+    #[cfg(target_os = "linux")]
+    interface: Option<String>,
+    #[cfg(target_os = "linux")]
+    tcp_user_timeout: Option<Duration>,
+    #[cfg(target_os = "linux")]
+    tcp_fastopen: bool,
+    #[cfg(target_os = "linux")]
+    tos: Option<u8>,
 }
 
 // ===== impl HttpConnector =====
@@ -111,6 +165,7 @@ impl<R> HttpConnector<R> {
                 connect_timeout: None,
                 enforce_http: true,
                 happy_eyeballs_timeout: Some(Duration::from_millis(300)),
+                happy_eyeballs_attempt_delay: Duration::from_millis(250),
                 keep_alive_timeout: None,
                 local_address_ipv4: None,
                 local_address_ipv6: None,
@@ -118,6 +173,14 @@ impl<R> HttpConnector<R> {
                 reuse_address: false,
                 send_buffer_size: None,
                 recv_buffer_size: None,
+                #[cfg(target_os = "linux")]
+                interface: None,
+                #[cfg(target_os = "linux")]
+                tcp_user_timeout: None,
+                #[cfg(target_os = "linux")]
+                tcp_fastopen: false,
+                #[cfg(target_os = "linux")]
+                tos: None,
             }),
             resolver,
         }
@@ -190,6 +253,23 @@ impl<R> HttpConnector<R> {
         cfg.local_address_ipv6 = Some(addr_ipv6);
     }
 
+    /// Set that all sockets are bound to the network interface named
+    /// `interface`, such as `"eth0"`, with `SO_BINDTODEVICE`.
+    ///
+    /// Unlike a local address, this pins the traffic to the interface, even
+    /// if the routing table would send it through another one.
+    ///
+    /// If `None`, the sockets will not be bound to an interface.
+    ///
+    /// Default is `None`.
+    ///
+    /// This option is only available on Linux.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn set_interface<S: Into<String>>(&mut self, interface: Option<S>) {
+        self.config_mut().interface = interface.map(Into::into);
+    }
+
     /// Set the connect timeout.
     ///
     /// If a domain resolves to multiple IP addresses, the timeout will be
@@ -201,23 +281,40 @@ impl<R> HttpConnector<R> {
         self.config_mut().connect_timeout = dur;
     }
 
-    /// Set timeout for [RFC 6555 (Happy Eyeballs)][RFC 6555] algorithm.
+    /// Set timeout for [RFC 8305 (Happy Eyeballs)][RFC 8305] algorithm.
     ///
-    /// If hostname resolves to both IPv4 and IPv6 addresses and connection
-    /// cannot be established using preferred address family before timeout
-    /// elapses, then connector will in parallel attempt connection using other
-    /// address family.
+    /// If hostname resolves to both IPv4 and IPv6 addresses, they are
+    /// attempted alternating between the address families, starting with
+    /// the family of the first address. If connection cannot be established
+    /// using preferred address family before timeout elapses, then connector
+    /// will in parallel attempt connection using other address family.
     ///
-    /// If `None`, parallel connection attempts are disabled.
+    /// If `None`, parallel connection attempts are disabled, and addresses
+    /// are attempted one after another in the order they were resolved.
     ///
     /// Default is 300 milliseconds.
     ///
-    /// [RFC 6555]: https://tools.ietf.org/html/rfc6555
+    /// [RFC 8305]: https://tools.ietf.org/html/rfc8305
     #[inline]
     pub fn set_happy_eyeballs_timeout(&mut self, dur: Option<Duration>) {
         self.config_mut().happy_eyeballs_timeout = dur;
     }
 
+    /// Set the delay between starting connection attempts to the addresses
+    /// of a hostname.
+    ///
+    /// Each address is attempted in parallel with the previous ones if they
+    /// are still connecting once the delay elapses, and right away if they
+    /// have all failed. The first connection established is used, and the
+    /// others are cancelled. Has no effect if the Happy Eyeballs timeout is
+    /// `None`.
+    ///
+    /// Default is 250 milliseconds.
+    #[inline]
+    pub fn set_happy_eyeballs_attempt_delay(&mut self, dur: Duration) {
+        self.config_mut().happy_eyeballs_attempt_delay = dur;
+    }
+
     /// Set that all socket have `SO_REUSEADDR` set to the supplied value `reuse_address`.
     ///
     /// Default is `false`.
@@ -227,6 +324,59 @@ impl<R> HttpConnector<R> {
         self
     }
 
+    /// Set that all sockets have `TCP_USER_TIMEOUT` set with the supplied
+    /// duration.
+    ///
+    /// This is how long sent data may remain unacknowledged before the
+    /// connection is closed with an error.
+    ///
+    /// If `None`, the option will not be set.
+    ///
+    /// Default is `None`.
+    ///
+    /// This option is only available on Linux.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn set_tcp_user_timeout(&mut self, dur: Option<Duration>) {
+        self.config_mut().tcp_user_timeout = dur;
+    }
+
+    /// Set that all sockets have `TCP_FASTOPEN_CONNECT` set to the supplied
+    /// value `fastopen`.
+    ///
+    /// With TCP Fast Open, the first request is sent with the SYN of
+    /// connections to servers that the system has a Fast Open cookie for.
+    ///
+    /// Default is `false`.
+    ///
+    /// This option is only available on Linux.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn set_tcp_fastopen(&mut self, fastopen: bool) {
+        self.config_mut().tcp_fastopen = fastopen;
+    }
+
+    /// Set that all sockets have `IP_TOS` (or `IPV6_TCLASS` for IPv6) set to
+    /// the supplied value `tos`.
+    ///
+    /// To mark packets with a DSCP value, pass it shifted left by two bits,
+    /// such as `46 << 2` for Expedited Forwarding.
+    ///
+    /// If `None`, the option will not be set.
+    ///
+    /// Default is `None`.
+    ///
+    /// This option is only available on Linux.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn set_tos(&mut self, tos: Option<u8>) {
+        self.config_mut().tos = tos;
+    }
+
+    pub(super) fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
     // private
 
     fn config_mut(&mut self) -> &mut Config {
@@ -265,7 +415,7 @@ where
     fn call(&mut self, dst: Uri) -> Self::Future {
         let mut self_ = self.clone();
         HttpConnecting {
-            fut: Box::pin(async move { self_.call_async(dst).await }),
+            fut: Box::pin(async move { Ok(self_.call_async(dst).await?.0) }),
             _marker: PhantomData,
         }
     }
@@ -275,7 +425,7 @@ impl<R> HttpConnector<R>
 where
     R: Resolve,
 {
-    async fn call_async(&mut self, dst: Uri) -> Result<TcpStream, ConnectError> {
+    async fn call_async(&mut self, dst: Uri) -> Result<(TcpStream, ConnectDetails), ConnectError> {
         trace!(
             "Http::connect; scheme={:?}, host={:?}, port={:?}",
             dst.scheme(),
@@ -321,32 +471,40 @@ where
 
         // If the host is already an IP addr (v4 or v6),
         // skip resolving the dns and start connecting right away.
-        let addrs = if let Some(addrs) = dns::IpAddrs::try_parse(host, port) {
-            addrs
+        let (addrs, dns_duration) = if let Some(addrs) = dns::IpAddrs::try_parse(host, port) {
+            (addrs, None)
         } else {
+            let start = Instant::now();
             let addrs = resolve(&mut self.resolver, dns::Name::new(host.into()))
                 .await
                 .map_err(ConnectError::dns)?;
-            let addrs = addrs.map(|addr| SocketAddr::new(addr, port)).collect();
-            dns::IpAddrs::new(addrs)
+            let addrs = addrs.map(|addr| addr.into_socket_addr(port)).collect();
+            (dns::IpAddrs::new(addrs), Some(start.elapsed()))
         };
 
         let c = ConnectingTcp::new(addrs, config);
 
-        let sock = c.connect().await?;
+        let start = Instant::now();
+        let (sock, attempts) = c.connect().await?;
+        let connect_duration = start.elapsed();
 
         sock.set_nodelay(config.nodelay)
             .map_err(ConnectError::m("tcp set_nodelay error"))?;
 
-        Ok(sock)
+        let details = ConnectDetails {
+            dns_duration,
+            connect_duration,
+            attempts,
+        };
+        Ok((sock, details))
     }
 }
 
 impl Connection for TcpStream {
     fn connected(&self) -> Connected {
         let connected = Connected::new();
-        if let Ok(remote_addr) = self.peer_addr() {
-            connected.extra(HttpInfo { remote_addr })
+        if let Some(info) = HttpInfo::from_tcp(self) {
+            connected.extra(info)
         } else {
             connected
         }
@@ -358,6 +516,203 @@ impl HttpInfo {
     pub fn remote_addr(&self) -> SocketAddr {
         self.remote_addr
     }
+
+    /// Get the local address of the transport used.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns how long it took to connect to the remote address.
+    ///
+    /// This is measured from the first connection attempt, after the
+    /// hostname was resolved. It is `None` if the transport wasn't connected
+    /// by an `HttpInfoConnector`.
+    pub fn connect_duration(&self) -> Option<Duration> {
+        self.connect_duration
+    }
+
+    /// Returns how long it took to resolve the hostname.
+    ///
+    /// It is `None` if the host was an IP address, or the transport wasn't
+    /// connected by an `HttpInfoConnector`.
+    pub fn dns_duration(&self) -> Option<Duration> {
+        self.dns_duration
+    }
+
+    /// Returns the number of addresses a connection was attempted to,
+    /// including the remote address.
+    ///
+    /// It is `None` if the transport wasn't connected by an
+    /// `HttpInfoConnector`.
+    pub fn attempts(&self) -> Option<usize> {
+        self.attempts
+    }
+
+    /// Returns whether the response was received on a connection that was
+    /// used for earlier requests.
+    pub fn is_reused(&self) -> bool {
+        self.is_reused
+    }
+
+    /// Returns whether the transport is over IPv4.
+    ///
+    /// If the hostname resolved to both IPv4 and IPv6 addresses, this is
+    /// the address family that won the Happy Eyeballs race.
+    pub fn is_ipv4(&self) -> bool {
+        self.remote_addr.is_ipv4()
+    }
+
+    /// Returns whether the transport is over IPv6.
+    ///
+    /// If the hostname resolved to both IPv4 and IPv6 addresses, this is
+    /// the address family that won the Happy Eyeballs race.
+    pub fn is_ipv6(&self) -> bool {
+        self.remote_addr.is_ipv6()
+    }
+
+    pub(crate) fn set_reused(&mut self, is_reused: bool) {
+        self.is_reused = is_reused;
+    }
+
+    fn from_tcp(tcp: &TcpStream) -> Option<HttpInfo> {
+        Some(HttpInfo {
+            remote_addr: tcp.peer_addr().ok()?,
+            local_addr: tcp.local_addr().ok()?,
+            connect_duration: None,
+            dns_duration: None,
+            attempts: None,
+            is_reused: false,
+        })
+    }
+}
+
+// ===== impl HttpInfoConnector =====
+
+impl<R> HttpInfoConnector<R> {
+    /// Construct a new `HttpInfoConnector`, connecting with `http`.
+    pub fn new(http: HttpConnector<R>) -> HttpInfoConnector<R> {
+        HttpInfoConnector { http }
+    }
+
+    /// Get a reference to the wrapped `HttpConnector`.
+    pub fn get_ref(&self) -> &HttpConnector<R> {
+        &self.http
+    }
+
+    /// Get a mutable reference to the wrapped `HttpConnector`.
+    pub fn get_mut(&mut self) -> &mut HttpConnector<R> {
+        &mut self.http
+    }
+}
+
+impl<R> tower_service::Service<Uri> for HttpInfoConnector<R>
+where
+    R: Resolve + Clone + Send + Sync + 'static,
+    R::Future: Send,
+{
+    type Response = HttpStream;
+    type Error = ConnectError;
+    type Future = HttpConnecting<R, HttpStream>;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.http.poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let mut http = self.http.clone();
+        HttpConnecting {
+            fut: Box::pin(async move {
+                let (tcp, details) = http.call_async(dst).await?;
+                let info = HttpInfo::from_tcp(&tcp).map(|info| HttpInfo {
+                    connect_duration: Some(details.connect_duration),
+                    dns_duration: details.dns_duration,
+                    attempts: Some(details.attempts),
+                    ..info
+                });
+                Ok(HttpStream { tcp, info })
+            }),
+            _marker: PhantomData,
+        }
+    }
+}
+
+// ===== impl HttpStream =====
+
+impl HttpStream {
+    /// Get the information about how the stream was connected.
+    ///
+    /// This is the `HttpInfo` put in the extensions of responses received
+    /// on the stream, and is `None` if the addresses of the stream couldn't
+    /// be read.
+    pub fn info(&self) -> Option<&HttpInfo> {
+        self.info.as_ref()
+    }
+
+    /// Get a reference to the `TcpStream`.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.tcp
+    }
+
+    /// Get a mutable reference to the `TcpStream`.
+    pub fn get_mut(&mut self) -> &mut TcpStream {
+        &mut self.tcp
+    }
+
+    /// Consumes the `HttpStream`, returning the `TcpStream`.
+    pub fn into_inner(self) -> TcpStream {
+        self.tcp
+    }
+}
+
+impl AsyncRead for HttpStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.tcp).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for HttpStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.tcp).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.tcp).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.tcp.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.tcp).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.tcp).poll_shutdown(cx)
+    }
+}
+
+impl Connection for HttpStream {
+    fn connected(&self) -> Connected {
+        let connected = Connected::new();
+        if let Some(ref info) = self.info {
+            connected.extra(info.clone())
+        } else {
+            connected
+        }
+    }
 }
 
 // Not publicly exported (so missing_docs doesn't trigger).
@@ -368,17 +723,17 @@ impl HttpInfo {
 #[must_use = "futures do nothing unless polled"]
 #[pin_project]
 #[allow(missing_debug_implementations)]
-pub struct HttpConnecting<R> {
+pub struct HttpConnecting<R, T = TcpStream> {
     #[pin]
-    fut: BoxConnecting,
+    fut: BoxConnecting<T>,
     _marker: PhantomData<R>,
 }
 
-type ConnectResult = Result<TcpStream, ConnectError>;
-type BoxConnecting = Pin<Box<dyn Future<Output = ConnectResult> + Send>>;
+type ConnectResult<T = TcpStream> = Result<T, ConnectError>;
+type BoxConnecting<T = TcpStream> = Pin<Box<dyn Future<Output = ConnectResult<T>> + Send>>;
 
-impl<R: Resolve> Future for HttpConnecting<R> {
-    type Output = ConnectResult;
+impl<R: Resolve, T> Future for HttpConnecting<R, T> {
+    type Output = ConnectResult<T>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
         self.project().fut.poll(cx)
@@ -392,7 +747,7 @@ pub struct ConnectError {
 }
 
 impl ConnectError {
-    fn new<S, E>(msg: S, cause: E) -> ConnectError
+    pub(super) fn new<S, E>(msg: S, cause: E) -> ConnectError
     where
         S: Into<Box<str>>,
         E: Into<Box<dyn StdError + Send + Sync>>,
@@ -403,20 +758,30 @@ impl ConnectError {
         }
     }
 
-    fn dns<E>(cause: E) -> ConnectError
+    pub(super) fn dns<E>(cause: E) -> ConnectError
     where
         E: Into<Box<dyn StdError + Send + Sync>>,
     {
         ConnectError::new("dns error", cause)
     }
 
-    fn m<S, E>(msg: S) -> impl FnOnce(E) -> ConnectError
+    pub(super) fn m<S, E>(msg: S) -> impl FnOnce(E) -> ConnectError
     where
         S: Into<Box<str>>,
         E: Into<Box<dyn StdError + Send + Sync>>,
     {
         move |cause| ConnectError::new(msg, cause)
     }
+
+    pub(super) fn msg<S>(msg: S) -> ConnectError
+    where
+        S: Into<Box<str>>,
+    {
+        ConnectError {
+            msg: msg.into(),
+            cause: None,
+        }
+    }
 }
 
 impl fmt::Debug for ConnectError {
@@ -450,89 +815,94 @@ impl StdError for ConnectError {
     }
 }
 
-struct ConnectingTcp<'a> {
-    preferred: ConnectingTcpRemote,
-    fallback: Option<ConnectingTcpFallback>,
-    config: &'a Config,
-}
+/// The errors of all the addresses a connection was attempted to.
+struct AttemptErrors(Vec<(SocketAddr, ConnectError)>);
 
-impl<'a> ConnectingTcp<'a> {
-    fn new(remote_addrs: dns::IpAddrs, config: &'a Config) -> Self {
-        if let Some(fallback_timeout) = config.happy_eyeballs_timeout {
-            let (preferred_addrs, fallback_addrs) = remote_addrs
-                .split_by_preference(config.local_address_ipv4, config.local_address_ipv6);
-            if fallback_addrs.is_empty() {
-                return ConnectingTcp {
-                    preferred: ConnectingTcpRemote::new(preferred_addrs, config.connect_timeout),
-                    fallback: None,
-                    config,
-                };
-            }
+impl fmt::Debug for AttemptErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(addr, err)| (addr, err)))
+            .finish()
+    }
+}
 
-            ConnectingTcp {
-                preferred: ConnectingTcpRemote::new(preferred_addrs, config.connect_timeout),
-                fallback: Some(ConnectingTcpFallback {
-                    delay: tokio::time::sleep(fallback_timeout),
-                    remote: ConnectingTcpRemote::new(fallback_addrs, config.connect_timeout),
-                }),
-                config,
-            }
-        } else {
-            ConnectingTcp {
-                preferred: ConnectingTcpRemote::new(remote_addrs, config.connect_timeout),
-                fallback: None,
-                config,
-            }
+impl fmt::Display for AttemptErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} attempts failed", self.0.len())?;
+        for (i, (addr, err)) in self.0.iter().enumerate() {
+            f.write_str(if i == 0 { ": " } else { ", " })?;
+            write!(f, "{} ({})", addr, err)?;
         }
+        Ok(())
     }
 }
 
-struct ConnectingTcpFallback {
-    delay: Sleep,
-    remote: ConnectingTcpRemote,
+impl StdError for AttemptErrors {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.last().map(|(_, err)| err as _)
+    }
 }
 
-struct ConnectingTcpRemote {
-    addrs: dns::IpAddrs,
+struct ConnectingTcp<'a> {
+    addrs: Peekable<dns::IpAddrs>,
+    preferring_v6: bool,
+    fallback_started: bool,
     connect_timeout: Option<Duration>,
+    config: &'a Config,
 }
 
-impl ConnectingTcpRemote {
-    fn new(addrs: dns::IpAddrs, connect_timeout: Option<Duration>) -> Self {
-        let connect_timeout = connect_timeout.map(|t| t / (addrs.len() as u32));
+impl<'a> ConnectingTcp<'a> {
+    fn new(remote_addrs: dns::IpAddrs, config: &'a Config) -> Self {
+        let remote_addrs = if config.happy_eyeballs_timeout.is_some() {
+            remote_addrs.interleave(config.local_address_ipv4, config.local_address_ipv6)
+        } else {
+            remote_addrs
+        };
+        let connect_timeout = config
+            .connect_timeout
+            .map(|t| t / (remote_addrs.len().max(1) as u32));
 
-        Self {
+        let mut addrs = remote_addrs.peekable();
+        let preferring_v6 = addrs.peek().map(SocketAddr::is_ipv6).unwrap_or(false);
+
+        ConnectingTcp {
             addrs,
+            preferring_v6,
+            fallback_started: false,
             connect_timeout,
+            config,
         }
     }
-}
 
-impl ConnectingTcpRemote {
-    async fn connect(&mut self, config: &Config) -> Result<TcpStream, ConnectError> {
-        let mut err = None;
-        for addr in &mut self.addrs {
-            debug!("connecting to {}", addr);
-            match connect(&addr, config, self.connect_timeout)?.await {
-                Ok(tcp) => {
-                    debug!("connected to {}", addr);
-                    return Ok(tcp);
-                }
-                Err(e) => {
-                    trace!("connect error for {}: {:?}", addr, e);
-                    err = Some(e);
-                }
-            }
+    /// How long to wait for the started attempts before starting the next
+    /// one, if there is one and attempts are made in parallel.
+    fn next_delay(&mut self) -> Option<Duration> {
+        let fallback_timeout = self.config.happy_eyeballs_timeout?;
+        let next = self.addrs.peek()?;
+        if !self.fallback_started && next.is_ipv6() != self.preferring_v6 {
+            Some(fallback_timeout)
+        } else {
+            Some(self.config.happy_eyeballs_attempt_delay)
         }
+    }
+}
 
-        match err {
-            Some(e) => Err(e),
-            None => Err(ConnectError::new(
-                "tcp connect error",
-                std::io::Error::new(std::io::ErrorKind::NotConnected, "Network unreachable"),
-            )),
+struct Attempt {
+    addr: SocketAddr,
+    fut: BoxConnecting,
+}
+
+fn poll_attempts(
+    attempts: &mut Vec<Attempt>,
+    cx: &mut task::Context<'_>,
+) -> Poll<(SocketAddr, ConnectResult)> {
+    for i in 0..attempts.len() {
+        if let Poll::Ready(result) = attempts[i].fut.as_mut().poll(cx) {
+            let attempt = attempts.swap_remove(i);
+            return Poll::Ready((attempt.addr, result));
         }
     }
+    Poll::Pending
 }
 
 fn bind_local_address(
@@ -618,6 +988,9 @@ fn connect(
             .map_err(ConnectError::m("tcp set_recv_buffer_size error"))?;
     }
 
+    #[cfg(target_os = "linux")]
+    set_linux_options(&socket, addr, config)?;
+
     #[cfg(unix)]
     let socket = unsafe {
         // Safety: `from_raw_fd` is only safe to call if ownership of the raw
@@ -650,39 +1023,145 @@ fn connect(
     })
 }
 
+#[cfg(target_os = "linux")]
+fn set_linux_options(
+    socket: &socket2::Socket,
+    addr: &SocketAddr,
+    config: &Config,
+) -> Result<(), ConnectError> {
+    use std::ffi::CString;
+
+    if let Some(ref interface) = config.interface {
+        let interface = CString::new(interface.as_str())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+            .map_err(ConnectError::m("tcp bind_device error"))?;
+        socket
+            .bind_device(Some(&interface))
+            .map_err(ConnectError::m("tcp bind_device error"))?;
+    }
+
+    if let Some(dur) = config.tcp_user_timeout {
+        let millis = dur.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        setsockopt(socket, libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT, millis)
+            .map_err(ConnectError::m("tcp set_tcp_user_timeout error"))?;
+    }
+
+    if config.tcp_fastopen {
+        setsockopt(socket, libc::IPPROTO_TCP, libc::TCP_FASTOPEN_CONNECT, 1)
+            .map_err(ConnectError::m("tcp set_tcp_fastopen error"))?;
+    }
+
+    if let Some(tos) = config.tos {
+        let (level, name) = match *addr {
+            SocketAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_TOS),
+            SocketAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_TCLASS),
+        };
+        setsockopt(socket, level, name, libc::c_int::from(tos))
+            .map_err(ConnectError::m("tcp set_tos error"))?;
+    }
+
+    Ok(())
+}
+
+/// Sets an integer socket option that socket2 has no method for.
+#[cfg(target_os = "linux")]
+fn setsockopt(
+    socket: &socket2::Socket,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // Safety: the pointer and length describe `value`, which outlives the
+    // call, and the fd is owned by `socket`.
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
 impl ConnectingTcp<'_> {
-    async fn connect(mut self) -> Result<TcpStream, ConnectError> {
-        match self.fallback {
-            None => self.preferred.connect(self.config).await,
-            Some(mut fallback) => {
-                let preferred_fut = self.preferred.connect(self.config);
-                futures_util::pin_mut!(preferred_fut);
-
-                let fallback_fut = fallback.remote.connect(self.config);
-                futures_util::pin_mut!(fallback_fut);
-
-                let (result, future) =
-                    match futures_util::future::select(preferred_fut, fallback.delay).await {
-                        Either::Left((result, _fallback_delay)) => {
-                            (result, Either::Right(fallback_fut))
-                        }
-                        Either::Right(((), preferred_fut)) => {
-                            // Delay is done, start polling both the preferred and the fallback
-                            futures_util::future::select(preferred_fut, fallback_fut)
-                                .await
-                                .factor_first()
-                        }
-                    };
+    /// Connects to one of the addresses, returning the stream and the number
+    /// of addresses that were attempted.
+    async fn connect(mut self) -> Result<(TcpStream, usize), ConnectError> {
+        let mut attempts = Vec::new();
+        let mut started = 0;
+        let mut errors = Vec::new();
+
+        loop {
+            // Another attempt is started when the previous one failed, or
+            // took longer than the delay.
+            if let Some(addr) = self.addrs.next() {
+                debug!("connecting to {}", addr);
+                started += 1;
+                if addr.is_ipv6() != self.preferring_v6 {
+                    self.fallback_started = true;
+                }
+                match connect(&addr, self.config, self.connect_timeout) {
+                    Ok(fut) => attempts.push(Attempt {
+                        addr,
+                        fut: Box::pin(fut),
+                    }),
+                    Err(e) => {
+                        trace!("connect error for {}: {:?}", addr, e);
+                        errors.push((addr, e));
+                        continue;
+                    }
+                }
+            }
 
-                if result.is_err() {
-                    // Fallback to the remaining future (could be preferred or fallback)
-                    // if we get an error
-                    future.await
-                } else {
-                    result
+            if attempts.is_empty() {
+                break;
+            }
+
+            let mut delay = self.next_delay().map(tokio::time::sleep);
+            let finished = futures_util::future::poll_fn(|cx| {
+                if let Poll::Ready(finished) = poll_attempts(&mut attempts, cx) {
+                    return Poll::Ready(Some(finished));
+                }
+                match delay {
+                    Some(ref mut delay) => Pin::new(delay).poll(cx).map(|()| None),
+                    None => Poll::Pending,
+                }
+            })
+            .await;
+
+            match finished {
+                Some((addr, Ok(tcp))) => {
+                    debug!("connected to {}", addr);
+                    return Ok((tcp, started));
+                }
+                Some((addr, Err(e))) => {
+                    trace!("connect error for {}: {:?}", addr, e);
+                    errors.push((addr, e));
                 }
+                None => trace!("connect attempt delay elapsed"),
             }
         }
+
+        if errors.len() > 1 {
+            return Err(ConnectError::new(
+                "tcp connect error",
+                AttemptErrors(errors),
+            ));
+        }
+        match errors.pop() {
+            Some((_, e)) => Err(e),
+            None => Err(ConnectError::new(
+                "tcp connect error",
+                std::io::Error::new(std::io::ErrorKind::NotConnected, "Network unreachable"),
+            )),
+        }
     }
 }
 
@@ -693,7 +1172,7 @@ mod tests {
     use ::http::Uri;
 
     use super::super::sealed::{Connect, ConnectSvc};
-    use super::{Config, ConnectError, HttpConnector};
+    use super::{Config, ConnectError, HttpConnector, HttpInfoConnector};
 
     async fn connect<C>(
         connector: C,
@@ -750,6 +1229,89 @@ mod tests {
         assert_eq!(&*err.msg, super::INVALID_MISSING_SCHEME);
     }
 
+    #[tokio::test]
+    async fn resolver_overrides_port() {
+        use super::super::dns::{GaiResolver, OverrideResolver};
+
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut resolver = OverrideResolver::new(GaiResolver::new());
+        resolver.set_override("example.domain", vec![server.local_addr().unwrap()]);
+        let connector = HttpConnector::new_with_resolver(resolver);
+
+        let dst = "http://example.domain".parse().unwrap();
+        let stream = connect(connector, dst).await.expect("connect");
+        assert_eq!(stream.peer_addr().unwrap(), server.local_addr().unwrap());
+    }
+
+    #[tokio::test]
+    async fn connect_errors_of_all_attempts() {
+        use super::super::dns::{GaiResolver, OverrideResolver};
+
+        // The ports of closed listeners refuse connections.
+        let closed = || {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let addrs = vec![closed(), closed()];
+        let mut resolver = OverrideResolver::new(GaiResolver::new());
+        resolver.set_override("example.domain", addrs.clone());
+        let connector = HttpConnector::new_with_resolver(resolver);
+
+        let dst = "http://example.domain".parse().unwrap();
+        let err = connect(connector, dst).await.unwrap_err().to_string();
+        assert!(
+            err.starts_with("tcp connect error: 2 attempts failed"),
+            "{}",
+            err
+        );
+        assert!(err.contains(&addrs[0].to_string()), "{}", err);
+        assert!(err.contains(&addrs[1].to_string()), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn http_info() {
+        use super::super::dns::{GaiResolver, OverrideResolver};
+        use super::super::Connection;
+        use super::HttpInfo;
+
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut resolver = OverrideResolver::new(GaiResolver::new());
+        resolver.set_override("example.domain", vec![closed, addr]);
+        let connector = HttpInfoConnector::new(HttpConnector::new_with_resolver(resolver));
+
+        let dst = "http://example.domain".parse().unwrap();
+        let stream = connect(connector.clone(), dst).await.expect("connect");
+        let info = stream.info().expect("info");
+        assert_eq!(info.remote_addr(), addr);
+        assert_eq!(info.local_addr(), stream.get_ref().local_addr().unwrap());
+        assert_eq!(info.attempts(), Some(2));
+        assert!(info.dns_duration().is_some());
+        assert!(info.connect_duration().is_some());
+        assert!(!info.is_reused());
+
+        let dst = format!("http://{}", addr).parse().unwrap();
+        let stream = connect(connector, dst).await.expect("connect");
+        let info = stream.info().expect("info");
+        assert_eq!(info.attempts(), Some(1));
+        assert!(info.dns_duration().is_none());
+
+        // A plain `HttpConnector` only knows the addresses.
+        let dst = format!("http://{}", addr).parse().unwrap();
+        let stream = connect(HttpConnector::new(), dst).await.expect("connect");
+        let mut extensions = ::http::Extensions::new();
+        let extra = stream.connected().extra.expect("extra");
+        extra.set(&mut extensions);
+        let info = extensions.get::<HttpInfo>().expect("info");
+        assert_eq!(info.local_addr(), stream.local_addr().unwrap());
+        assert_eq!(info.attempts(), None);
+        assert!(info.connect_duration().is_none());
+    }
+
     // NOTE: pnet crate that we use in this test doesn't compile on Windows
     #[cfg(any(target_os = "linux", target_os = "macos"))]
     #[tokio::test]
@@ -788,6 +1350,59 @@ mod tests {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn linux_socket_options() {
+        use std::os::unix::io::AsRawFd;
+        use std::time::Duration;
+
+        fn getsockopt(fd: libc::c_int, level: libc::c_int, name: libc::c_int) -> Vec<u8> {
+            let mut buf = [0u8; 64];
+            let mut len = buf.len() as libc::socklen_t;
+            let ret =
+                unsafe { libc::getsockopt(fd, level, name, buf.as_mut_ptr() as *mut _, &mut len) };
+            assert_eq!(ret, 0, "getsockopt: {}", io::Error::last_os_error());
+            buf[..len as usize].to_vec()
+        }
+        fn getsockopt_int(fd: libc::c_int, level: libc::c_int, name: libc::c_int) -> libc::c_int {
+            let buf = getsockopt(fd, level, name);
+            let mut int = [0u8; 4];
+            int.copy_from_slice(&buf[..4]);
+            libc::c_int::from_ne_bytes(int)
+        }
+
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dst: Uri = format!("http://{}", server.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let mut connector = HttpConnector::new();
+        connector.set_interface(Some("lo"));
+        connector.set_tcp_user_timeout(Some(Duration::from_secs(5)));
+        connector.set_tcp_fastopen(true);
+        connector.set_tos(Some(46 << 2));
+
+        let stream = connect(connector.clone(), dst.clone())
+            .await
+            .expect("connect");
+        let fd = stream.as_raw_fd();
+        let device = getsockopt(fd, libc::SOL_SOCKET, libc::SO_BINDTODEVICE);
+        assert_eq!(device.split(|&b| b == 0).next().unwrap(), b"lo");
+        assert_eq!(
+            getsockopt_int(fd, libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT),
+            5000
+        );
+        assert_eq!(
+            getsockopt_int(fd, libc::IPPROTO_TCP, libc::TCP_FASTOPEN_CONNECT),
+            1
+        );
+        assert_eq!(getsockopt_int(fd, libc::IPPROTO_IP, libc::IP_TOS), 46 << 2);
+
+        connector.set_interface(Some("no-such-interface"));
+        let err = connect(connector, dst).await.unwrap_err();
+        assert_eq!(&*err.msg, "tcp bind_device error");
+    }
+
     #[test]
     #[cfg_attr(not(feature = "__internal_happy_eyeballs_tests"), ignore)]
     fn client_happy_eyeballs() {
@@ -842,14 +1457,15 @@ mod tests {
                 unreachable_v6_timeout,
                 false,
             ),
-            // Unreachable + fast primary, with (unused) fallback.
+            // Unreachable primary, with fallback interleaved before the
+            // next primary.
             (
                 &[
                     unreachable_ipv4_addr(),
                     local_ipv4_addr(),
                     local_ipv6_addr(),
                 ][..],
-                4,
+                6,
                 unreachable_v4_timeout,
                 false,
             ),
@@ -859,7 +1475,7 @@ mod tests {
                     local_ipv6_addr(),
                     local_ipv4_addr(),
                 ][..],
-                6,
+                4,
                 unreachable_v6_timeout,
                 true,
             ),
@@ -912,15 +1528,25 @@ mod tests {
                         connect_timeout: None,
                         keep_alive_timeout: None,
                         happy_eyeballs_timeout: Some(fallback_timeout),
+                        happy_eyeballs_attempt_delay: fallback_timeout,
                         nodelay: false,
                         reuse_address: false,
                         enforce_http: false,
                         send_buffer_size: None,
                         recv_buffer_size: None,
+                        #[cfg(target_os = "linux")]
+                        interface: None,
+                        #[cfg(target_os = "linux")]
+                        tcp_user_timeout: None,
+                        #[cfg(target_os = "linux")]
+                        tcp_fastopen: false,
+                        #[cfg(target_os = "linux")]
+                        tos: None,
                     };
                     let connecting_tcp = ConnectingTcp::new(dns::IpAddrs::new(addrs), &cfg);
                     let start = Instant::now();
-                    Ok::<_, ConnectError>((start, ConnectingTcp::connect(connecting_tcp).await?))
+                    let (stream, _) = ConnectingTcp::connect(connecting_tcp).await?;
+                    Ok::<_, ConnectError>((start, stream))
                 })
                 .unwrap();
             let res = if stream.peer_addr().unwrap().is_ipv4() {
@@ -4,6 +4,10 @@
 //!
 //! - A default [`HttpConnector`][] that does DNS resolution and establishes
 //!   connections over TCP.
+//! - A [`ProxyConnector`][] that routes connections through HTTP proxies.
+//! - A [`SocksConnector`](socks::SocksConnector) that routes connections
+//!   through SOCKS5 proxies.
+//! - A [`UnixConnector`][] that connects to Unix domain sockets.
 //! - Types to build custom connectors.
 //!
 //! # Connectors
@@ -74,6 +78,8 @@
 //!
 //!
 //! [`HttpConnector`]: HttpConnector
+//! [`ProxyConnector`]: ProxyConnector
+//! [`UnixConnector`]: UnixConnector
 //! [`Service`]: crate::service::Service
 //! [`Uri`]: ::http::Uri
 //! [`AsyncRead`]: tokio::io::AsyncRead
@@ -81,15 +87,23 @@
 //! [`Connection`]: Connection
 use std::fmt;
 
-use ::http::Extensions;
+use ::http::{Extensions, HeaderValue};
 
 #[cfg(feature = "tcp")]
 pub mod dns;
 #[cfg(feature = "tcp")]
 mod http;
+mod proxy;
 #[cfg(feature = "tcp")]
-pub use self::http::{HttpConnector, HttpInfo};
+pub mod socks;
+#[cfg(all(unix, feature = "unix"))]
+mod unix;
+#[cfg(feature = "tcp")]
+pub use self::http::{HttpConnector, HttpInfo, HttpInfoConnector, HttpStream};
+pub use self::proxy::{Proxy, ProxyConnector, ProxyStream};
 pub use self::sealed::Connect;
+#[cfg(all(unix, feature = "unix"))]
+pub use self::unix::{UnixConnector, UnixInfo};
 
 /// Describes a type returned by a connector.
 pub trait Connection {
@@ -105,6 +119,7 @@ pub trait Connection {
 pub struct Connected {
     pub(super) alpn: Alpn,
     pub(super) is_proxied: bool,
+    pub(super) proxy_authorization: Option<HeaderValue>,
     pub(super) extra: Option<Extra>,
 }
 
@@ -122,6 +137,7 @@ impl Connected {
         Connected {
             alpn: Alpn::None,
             is_proxied: false,
+            proxy_authorization: None,
             extra: None,
         }
     }
@@ -154,6 +170,13 @@ impl Connected {
         self.is_proxied
     }
 
+    /// Set the `Proxy-Authorization` added to requests written in
+    /// absolute-form to the proxy.
+    pub(super) fn proxy_authorization(mut self, value: Option<HeaderValue>) -> Connected {
+        self.proxy_authorization = value;
+        self
+    }
+
     /// Set extra connection information to be set in the extensions of every `Response`.
     pub fn extra<T: Clone + Send + Sync + 'static>(mut self, extra: T) -> Connected {
         if let Some(prev) = self.extra {
@@ -184,11 +207,12 @@ impl Connected {
 
     // Don't public expose that `Connected` is `Clone`, unsure if we want to
     // keep that contract...
-    #[cfg(feature = "http2")]
-    pub(super) fn clone(&self) -> Connected {
+    #[cfg(any(feature = "http2", feature = "tls"))]
+    pub(crate) fn clone(&self) -> Connected {
         Connected {
             alpn: self.alpn.clone(),
             is_proxied: self.is_proxied,
+            proxy_authorization: self.proxy_authorization.clone(),
             extra: self.extra.clone(),
         }
     }
@@ -0,0 +1,605 @@
+//! SOCKS5 proxy support for the `HttpConnector`.
+//!
+//! This module contains a [`SocksConnector`](SocksConnector), which connects
+//! to a SOCKS5 proxy with an `HttpConnector`, and then asks the proxy to
+//! connect to the destination.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(feature = "runtime")]
+//! # fn doc() -> Result<(), Box<dyn std::error::Error>> {
+//! use hyper::client::{Client, HttpConnector};
+//! use hyper::client::connect::socks::SocksConnector;
+//!
+//! let mut connector = SocksConnector::new(
+//!     "socks5h://127.0.0.1:1080".parse()?,
+//!     HttpConnector::new(),
+//! );
+//! connector.set_auth("user", "pass");
+//!
+//! let client = Client::builder().build::<_, hyper::Body>(connector);
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{self, Poll};
+
+use futures_util::future::poll_fn;
+use http::uri::{Scheme, Uri};
+use pin_project::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+use super::dns::{self, resolve, GaiResolver, Resolve, ResolvedAddr};
+use super::http::{ConnectError, HttpConnector};
+
+/// A connector that reaches destinations through a SOCKS5 proxy.
+///
+/// The connection to the proxy is made with the wrapped `HttpConnector`,
+/// so its options, such as timeouts and `SO_NODELAY`, apply to it.
+///
+/// # Note
+///
+/// A SOCKS5 proxy relays the connection without looking at it, so the
+/// returned connections are not reported as [proxied][], and requests are
+/// written as they would be to the destination itself.
+///
+/// [proxied]: super::Connected::proxy
+#[derive(Clone)]
+pub struct SocksConnector<R = GaiResolver> {
+    http: HttpConnector<R>,
+    config: Arc<Config>,
+}
+
+#[derive(Clone)]
+struct Config {
+    proxy: Uri,
+    auth: Option<(String, String)>,
+    remote_dns: bool,
+}
+
+/// The address sent to the proxy in a `CONNECT` request.
+enum Addr<'a> {
+    Ip(SocketAddr),
+    Domain(&'a str, u16),
+}
+
+// SOCKS5 constants, from RFC 1928 and RFC 1929.
+const VERSION: u8 = 0x05;
+const AUTH_VERSION: u8 = 0x01;
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_PASSWORD: u8 = 0x02;
+const METHOD_NONE_ACCEPTABLE: u8 = 0xff;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+const DEFAULT_PORT: u16 = 1080;
+
+// ===== impl SocksConnector =====
+
+impl<R> SocksConnector<R> {
+    /// Construct a new SocksConnector, for the proxy at `proxy`.
+    ///
+    /// The proxy URI should look like `socks5://host:port`. If the scheme is
+    /// `socks5h`, destination host names are resolved by the proxy, instead
+    /// of locally with the `HttpConnector`'s resolver. The port defaults
+    /// to 1080.
+    pub fn new(proxy: Uri, http: HttpConnector<R>) -> SocksConnector<R> {
+        let remote_dns = proxy.scheme_str() == Some("socks5h");
+        SocksConnector {
+            http,
+            config: Arc::new(Config {
+                proxy,
+                auth: None,
+                remote_dns,
+            }),
+        }
+    }
+
+    /// Set the username and password to authenticate with the proxy.
+    ///
+    /// If not set, only the "no authentication" method is offered.
+    pub fn set_auth(&mut self, username: &str, password: &str) {
+        self.config_mut().auth = Some((username.to_owned(), password.to_owned()));
+    }
+
+    /// Set whether destination host names are resolved by the proxy.
+    ///
+    /// If `false`, host names are resolved locally, and the proxy is sent
+    /// an IP address.
+    ///
+    /// Default is `true` if the proxy URI scheme is `socks5h`, and `false`
+    /// otherwise.
+    #[inline]
+    pub fn set_remote_dns(&mut self, remote_dns: bool) {
+        self.config_mut().remote_dns = remote_dns;
+    }
+
+    /// Get a mutable reference to the wrapped `HttpConnector`.
+    pub fn http_connector_mut(&mut self) -> &mut HttpConnector<R> {
+        &mut self.http
+    }
+
+    fn config_mut(&mut self) -> &mut Config {
+        Arc::make_mut(&mut self.config)
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for SocksConnector<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocksConnector")
+            .field("proxy", &self.config.proxy)
+            .field("remote_dns", &self.config.remote_dns)
+            .finish()
+    }
+}
+
+impl<R> tower_service::Service<Uri> for SocksConnector<R>
+where
+    R: Resolve + Clone + Send + Sync + 'static,
+    R::Future: Send,
+{
+    type Response = TcpStream;
+    type Error = ConnectError;
+    type Future = SocksConnecting;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        tower_service::Service::poll_ready(&mut self.http, cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let mut self_ = self.clone();
+        SocksConnecting {
+            fut: Box::pin(async move { self_.call_async(dst).await }),
+        }
+    }
+}
+
+impl<R> SocksConnector<R>
+where
+    R: Resolve + Clone + Send + Sync + 'static,
+    R::Future: Send,
+{
+    async fn call_async(&mut self, dst: Uri) -> Result<TcpStream, ConnectError> {
+        trace!(
+            "Socks::connect; proxy={:?}, host={:?}, port={:?}",
+            self.config.proxy,
+            dst.host(),
+            dst.port(),
+        );
+
+        let host = dst
+            .host()
+            .ok_or_else(|| ConnectError::msg(INVALID_MISSING_HOST))?;
+        let port = dst.port_u16().unwrap_or_else(|| {
+            if dst.scheme() == Some(&Scheme::HTTPS) {
+                443
+            } else {
+                80
+            }
+        });
+
+        let proxy_host = self
+            .config
+            .proxy
+            .host()
+            .ok_or_else(|| ConnectError::msg(INVALID_PROXY_MISSING_HOST))?;
+        let proxy_port = self.config.proxy.port_u16().unwrap_or(DEFAULT_PORT);
+        let proxy_dst = format!("http://{}:{}", proxy_host, proxy_port)
+            .parse()
+            .map_err(ConnectError::m("invalid proxy URL"))?;
+
+        let addr = if let Ok(ip) = unbracket(host).parse::<IpAddr>() {
+            Addr::Ip(SocketAddr::new(ip, port))
+        } else if self.config.remote_dns {
+            Addr::Domain(host, port)
+        } else {
+            let mut resolver = self.http.resolver().clone();
+            let addr = resolve(&mut resolver, dns::Name::new(host.into()))
+                .await
+                .map_err(ConnectError::dns)?
+                .next()
+                .ok_or_else(|| ConnectError::msg("dns error: no addresses found"))?;
+            Addr::Ip(addr.into_socket_addr(port))
+        };
+
+        let mut sock = tower_service::Service::call(&mut self.http, proxy_dst).await?;
+        handshake(&mut sock, self.config.auth.as_ref(), addr).await?;
+        Ok(sock)
+    }
+}
+
+fn unbracket(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+static INVALID_MISSING_HOST: &str = "invalid URL, host is missing";
+static INVALID_PROXY_MISSING_HOST: &str = "invalid proxy URL, host is missing";
+
+/// A future for a connection made by a [`SocksConnector`](SocksConnector).
+#[must_use = "futures do nothing unless polled"]
+#[pin_project]
+#[allow(missing_debug_implementations)]
+pub struct SocksConnecting {
+    #[pin]
+    fut: BoxConnecting,
+}
+
+type ConnectResult = Result<TcpStream, ConnectError>;
+type BoxConnecting = Pin<Box<dyn Future<Output = ConnectResult> + Send>>;
+
+impl Future for SocksConnecting {
+    type Output = ConnectResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.project().fut.poll(cx)
+    }
+}
+
+// ===== handshake =====
+
+async fn handshake<T>(
+    io: &mut T,
+    auth: Option<&(String, String)>,
+    addr: Addr<'_>,
+) -> Result<(), ConnectError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    // Method selection
+    if auth.is_some() {
+        write_all(io, &[VERSION, 2, METHOD_NO_AUTH, METHOD_PASSWORD]).await?;
+    } else {
+        write_all(io, &[VERSION, 1, METHOD_NO_AUTH]).await?;
+    }
+
+    let mut reply = [0; 2];
+    read_exact(io, &mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(ConnectError::msg("socks error: invalid version in reply"));
+    }
+    match (reply[1], auth) {
+        (METHOD_NO_AUTH, _) => (),
+        (METHOD_PASSWORD, Some((username, password))) => {
+            if username.len() > 255 || password.len() > 255 {
+                return Err(ConnectError::msg(
+                    "socks error: username or password is too long",
+                ));
+            }
+            let mut req = Vec::with_capacity(3 + username.len() + password.len());
+            req.push(AUTH_VERSION);
+            req.push(username.len() as u8);
+            req.extend_from_slice(username.as_bytes());
+            req.push(password.len() as u8);
+            req.extend_from_slice(password.as_bytes());
+            write_all(io, &req).await?;
+
+            read_exact(io, &mut reply).await?;
+            if reply[0] != AUTH_VERSION {
+                return Err(ConnectError::msg(
+                    "socks error: invalid version in authentication reply",
+                ));
+            }
+            if reply[1] != 0 {
+                return Err(ConnectError::msg("socks error: authentication failed"));
+            }
+        }
+        (METHOD_NONE_ACCEPTABLE, _) => {
+            return Err(ConnectError::msg(
+                "socks error: no acceptable authentication methods",
+            ));
+        }
+        _ => {
+            return Err(ConnectError::msg(
+                "socks error: unexpected authentication method",
+            ));
+        }
+    }
+
+    // Connect request
+    let mut req = vec![VERSION, CMD_CONNECT, 0x00];
+    let port = match addr {
+        Addr::Ip(SocketAddr::V4(addr)) => {
+            req.push(ATYP_IPV4);
+            req.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        Addr::Ip(SocketAddr::V6(addr)) => {
+            req.push(ATYP_IPV6);
+            req.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        Addr::Domain(host, port) => {
+            if host.len() > 255 {
+                return Err(ConnectError::msg("socks error: host name is too long"));
+            }
+            req.push(ATYP_DOMAIN);
+            req.push(host.len() as u8);
+            req.extend_from_slice(host.as_bytes());
+            port
+        }
+    };
+    req.extend_from_slice(&port.to_be_bytes());
+    write_all(io, &req).await?;
+
+    let mut reply = [0; 4];
+    read_exact(io, &mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(ConnectError::msg("socks error: invalid version in reply"));
+    }
+    if reply[1] != 0 {
+        return Err(ConnectError::msg(format!(
+            "socks error: {}",
+            reply_message(reply[1])
+        )));
+    }
+
+    // The bound address isn't needed, but must be read off the stream.
+    let len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => {
+            let mut len = [0; 1];
+            read_exact(io, &mut len).await?;
+            len[0] as usize
+        }
+        _ => {
+            return Err(ConnectError::msg(
+                "socks error: invalid address type in reply",
+            ))
+        }
+    };
+    let mut bound = vec![0; len + 2];
+    read_exact(io, &mut bound).await?;
+
+    Ok(())
+}
+
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "general SOCKS server failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown reply code",
+    }
+}
+
+async fn write_all<T>(io: &mut T, mut buf: &[u8]) -> Result<(), ConnectError>
+where
+    T: AsyncWrite + Unpin,
+{
+    while !buf.is_empty() {
+        let n = poll_fn(|cx| Pin::new(&mut *io).poll_write(cx, buf))
+            .await
+            .map_err(ConnectError::m("socks write error"))?;
+        if n == 0 {
+            return Err(ConnectError::new(
+                "socks write error",
+                io::Error::from(io::ErrorKind::WriteZero),
+            ));
+        }
+        buf = &buf[n..];
+    }
+    poll_fn(|cx| Pin::new(&mut *io).poll_flush(cx))
+        .await
+        .map_err(ConnectError::m("socks write error"))
+}
+
+async fn read_exact<T>(io: &mut T, buf: &mut [u8]) -> Result<(), ConnectError>
+where
+    T: AsyncRead + Unpin,
+{
+    let mut read_buf = ReadBuf::new(buf);
+    while read_buf.remaining() > 0 {
+        let filled = read_buf.filled().len();
+        poll_fn(|cx| Pin::new(&mut *io).poll_read(cx, &mut read_buf))
+            .await
+            .map_err(ConnectError::m("socks read error"))?;
+        if read_buf.filled().len() == filled {
+            return Err(ConnectError::new(
+                "socks read error",
+                io::Error::from(io::ErrorKind::UnexpectedEof),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::future::Future;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::pin::Pin;
+    use std::task::{self, Poll};
+
+    use ::http::Uri;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::super::dns::Name;
+    use super::super::sealed::{Connect, ConnectSvc};
+    use super::super::HttpConnector;
+    use super::SocksConnector;
+
+    async fn connect<C>(
+        connector: C,
+        dst: Uri,
+    ) -> Result<<C::_Svc as ConnectSvc>::Connection, <C::_Svc as ConnectSvc>::Error>
+    where
+        C: Connect,
+    {
+        connector.connect(super::super::sealed::Internal, dst).await
+    }
+
+    /// An in-process SOCKS5 stand-in, which expects to read each request
+    /// and then writes its reply, in order.
+    async fn socks_server(script: Vec<(&'static [u8], &'static [u8])>) -> SocketAddr {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            for (expected, reply) in script {
+                let mut buf = vec![0; expected.len()];
+                sock.read_exact(&mut buf).await.expect("read");
+                assert_eq!(buf, expected);
+                sock.write_all(reply).await.expect("write");
+            }
+        });
+        addr
+    }
+
+    fn proxy_uri(scheme: &str, addr: SocketAddr) -> Uri {
+        format!("{}://{}", scheme, addr).parse().unwrap()
+    }
+
+    #[derive(Clone)]
+    struct StaticResolver(IpAddr);
+
+    impl tower_service::Service<Name> for StaticResolver {
+        type Response = std::vec::IntoIter<IpAddr>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Name) -> Self::Future {
+            let ip = self.0;
+            Box::pin(async move { Ok(vec![ip].into_iter()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn no_auth_remote_dns() {
+        let addr = socks_server(vec![
+            (&[5, 1, 0], &[5, 0]),
+            (
+                b"\x05\x01\x00\x03\x0eexample.domain\x00\x50",
+                &[5, 0, 0, 1, 10, 0, 0, 1, 0x1f, 0x90],
+            ),
+            (b"ping", b"pong"),
+        ])
+        .await;
+
+        let connector = SocksConnector::new(proxy_uri("socks5h", addr), HttpConnector::new());
+        let mut sock = connect(connector, "http://example.domain".parse().unwrap())
+            .await
+            .expect("connect");
+
+        sock.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        sock.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    }
+
+    #[tokio::test]
+    async fn password_auth_local_dns() {
+        let addr = socks_server(vec![
+            (&[5, 2, 0, 2], &[5, 2]),
+            (b"\x01\x03foo\x03bar", &[1, 0]),
+            (
+                &[5, 1, 0, 1, 10, 0, 0, 2, 0x01, 0xbb],
+                // bound to a domain name, which must be skipped
+                b"\x05\x00\x00\x03\x05proxy\x1f\x90",
+            ),
+        ])
+        .await;
+
+        let http =
+            HttpConnector::new_with_resolver(StaticResolver(Ipv4Addr::new(10, 0, 0, 2).into()));
+        let mut connector = SocksConnector::new(proxy_uri("socks5", addr), http);
+        connector.set_auth("foo", "bar");
+        connect(connector, "https://example.domain".parse().unwrap())
+            .await
+            .expect("connect");
+    }
+
+    #[tokio::test]
+    async fn ipv6_destination() {
+        let addr = socks_server(vec![
+            (&[5, 1, 0], &[5, 0]),
+            (
+                &[
+                    5, 1, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0x1f, 0x90,
+                ],
+                &[5, 0, 0, 1, 0, 0, 0, 0, 0, 0],
+            ),
+        ])
+        .await;
+
+        let connector = SocksConnector::new(proxy_uri("socks5h", addr), HttpConnector::new());
+        connect(connector, "http://[::1]:8080".parse().unwrap())
+            .await
+            .expect("connect");
+    }
+
+    #[tokio::test]
+    async fn connect_refused() {
+        let addr = socks_server(vec![
+            (&[5, 1, 0], &[5, 0]),
+            (
+                b"\x05\x01\x00\x03\x0eexample.domain\x00\x50",
+                &[5, 5, 0, 1, 0, 0, 0, 0, 0, 0],
+            ),
+        ])
+        .await;
+
+        let connector = SocksConnector::new(proxy_uri("socks5h", addr), HttpConnector::new());
+        let err = connect(connector, "http://example.domain".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "socks error: connection refused");
+    }
+
+    #[tokio::test]
+    async fn auth_failed() {
+        let addr = socks_server(vec![
+            (&[5, 2, 0, 2], &[5, 2]),
+            (b"\x01\x03foo\x05wrong", &[1, 1]),
+        ])
+        .await;
+
+        let mut connector = SocksConnector::new(proxy_uri("socks5h", addr), HttpConnector::new());
+        connector.set_auth("foo", "wrong");
+        let err = connect(connector, "http://example.domain".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "socks error: authentication failed");
+    }
+
+    #[tokio::test]
+    async fn auth_reply_invalid_version() {
+        let addr = socks_server(vec![
+            (&[5, 2, 0, 2], &[5, 2]),
+            (b"\x01\x03foo\x03bar", &[5, 0]),
+        ])
+        .await;
+
+        let mut connector = SocksConnector::new(proxy_uri("socks5h", addr), HttpConnector::new());
+        connector.set_auth("foo", "bar");
+        let err = connect(connector, "http://example.domain".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "socks error: invalid version in authentication reply"
+        );
+    }
+}
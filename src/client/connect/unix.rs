@@ -0,0 +1,226 @@
+use std::ffi::OsStr;
+use std::future::Future;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{self, Poll};
+
+use http::uri::Uri;
+use tokio::net::unix::UCred;
+use tokio::net::UnixStream;
+
+use super::{Connected, Connection};
+
+/// A connector for the `unix` scheme.
+///
+/// Connects to a Unix domain socket, whose path is hex-encoded in the host
+/// of the `Uri`, such as `unix://2f746d702f68797065722e736f636b/index.html`
+/// for `/tmp/hyper.sock`. Use [`UnixConnector::uri`](UnixConnector::uri) to
+/// create such a `Uri`.
+///
+/// # Note
+///
+/// Sets the [`UnixInfo`](UnixInfo) value on responses, which includes the
+/// credentials of the process on the other end of the socket.
+///
+/// # Example
+///
+/// ```
+/// # async fn doc() -> Result<(), Box<dyn std::error::Error>> {
+/// use hyper::client::{Client, connect::UnixConnector};
+///
+/// let client = Client::builder().build::<_, hyper::Body>(UnixConnector::new());
+/// let uri = UnixConnector::uri("/var/run/docker.sock", "/version")?;
+/// let res = client.get(uri).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UnixConnector {
+    _priv: (),
+}
+
+/// Extra information about the transport when a UnixConnector is used.
+#[derive(Clone, Debug)]
+pub struct UnixInfo {
+    peer_cred: Option<UCred>,
+    peer_path: Option<PathBuf>,
+}
+
+// ===== impl UnixConnector =====
+
+impl UnixConnector {
+    /// Construct a new UnixConnector.
+    pub fn new() -> UnixConnector {
+        UnixConnector { _priv: () }
+    }
+
+    /// Create a `Uri` for a request to `path_and_query` on the socket at
+    /// `socket_path`.
+    pub fn uri<P: AsRef<Path>>(socket_path: P, path_and_query: &str) -> Result<Uri, http::Error> {
+        let mut host = String::new();
+        for b in socket_path.as_ref().as_os_str().as_bytes() {
+            host.push_str(&format!("{:02x}", b));
+        }
+        Uri::builder()
+            .scheme("unix")
+            .authority(&*host)
+            .path_and_query(path_and_query)
+            .build()
+    }
+}
+
+impl tower_service::Service<Uri> for UnixConnector {
+    type Response = UnixStream;
+    type Error = io::Error;
+    type Future = UnixConnecting;
+
+    fn poll_ready(&mut self, _: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        UnixConnecting {
+            fut: Box::pin(async move {
+                trace!("Unix::connect; dst={:?}", dst);
+                let path = socket_path(&dst)?;
+                UnixStream::connect(path).await
+            }),
+        }
+    }
+}
+
+fn socket_path(dst: &Uri) -> io::Result<PathBuf> {
+    if dst.scheme_str() != Some("unix") {
+        return Err(invalid(INVALID_NOT_UNIX));
+    }
+    let host = dst.host().ok_or_else(|| invalid(INVALID_MISSING_HOST))?;
+    let path = hex_decode(host).ok_or_else(|| invalid(INVALID_NOT_HEX))?;
+    Ok(PathBuf::from(OsStr::from_bytes(&path)))
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    let pairs = s.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| {
+            let hi = (pair[0] as char).to_digit(16)?;
+            let lo = (pair[1] as char).to_digit(16)?;
+            Some((hi << 4 | lo) as u8)
+        })
+        .collect()
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+static INVALID_NOT_UNIX: &str = "invalid URL, scheme is not unix";
+static INVALID_MISSING_HOST: &str = "invalid URL, host is missing";
+static INVALID_NOT_HEX: &str = "invalid URL, host is not a hex-encoded path";
+
+impl Connection for UnixStream {
+    fn connected(&self) -> Connected {
+        Connected::new().extra(UnixInfo {
+            peer_cred: self.peer_cred().ok(),
+            peer_path: self
+                .peer_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(Path::to_path_buf)),
+        })
+    }
+}
+
+// ===== impl UnixInfo =====
+
+impl UnixInfo {
+    /// Get the credentials of the process on the other end of the socket.
+    ///
+    /// Returns `None` if the platform doesn't support getting them.
+    pub fn peer_cred(&self) -> Option<UCred> {
+        self.peer_cred
+    }
+
+    /// Get the path of the socket that was connected to.
+    pub fn peer_path(&self) -> Option<&Path> {
+        self.peer_path.as_deref()
+    }
+}
+
+// Not publicly exported (so missing_docs doesn't trigger).
+#[must_use = "futures do nothing unless polled"]
+#[allow(missing_debug_implementations)]
+pub struct UnixConnecting {
+    fut: Pin<Box<dyn Future<Output = io::Result<UnixStream>> + Send>>,
+}
+
+impl Future for UnixConnecting {
+    type Output = io::Result<UnixStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.fut.as_mut().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tokio::net::UnixListener;
+
+    use super::super::sealed::Connect;
+    use super::super::Connection;
+    use super::{socket_path, UnixConnector, UnixInfo};
+
+    #[test]
+    fn test_uri_roundtrip() {
+        let uri = UnixConnector::uri("/tmp/hyper.sock", "/index.html?q=1").unwrap();
+        assert_eq!(uri, "unix://2f746d702f68797065722e736f636b/index.html?q=1");
+        assert_eq!(socket_path(&uri).unwrap(), Path::new("/tmp/hyper.sock"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = socket_path(&"http://2f746d70/".parse().unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), super::INVALID_NOT_UNIX);
+
+        let err = socket_path(&"unix://2f746d7/".parse().unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), super::INVALID_NOT_HEX);
+
+        let err = socket_path(&"unix://zz/".parse().unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), super::INVALID_NOT_HEX);
+    }
+
+    #[tokio::test]
+    async fn test_connect_peer_cred() {
+        let path = std::env::temp_dir().join(format!(
+            "hyper-test-unix-connector-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let dst = UnixConnector::uri(&path, "/").unwrap();
+        let (stream, _) = futures_util::future::join(
+            UnixConnector::new().connect(super::super::sealed::Internal, dst),
+            listener.accept(),
+        )
+        .await;
+        let stream = stream.expect("connect");
+
+        let mut extensions = http::Extensions::new();
+        stream.connected().get_extras(&mut extensions);
+        let info = extensions.get::<UnixInfo>().expect("UnixInfo");
+        assert_eq!(info.peer_path(), Some(&*path));
+        if let Some(cred) = info.peer_cred() {
+            if let Some(pid) = cred.pid() {
+                assert_eq!(pid as u32, std::process::id());
+            }
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
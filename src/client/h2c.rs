@@ -0,0 +1,222 @@
+//! Upgrading cleartext HTTP/1.1 connections to HTTP/2 (RFC 7540, section 3.2).
+//!
+//! The request that asks for the upgrade is sent with HTTP/1.1, and the
+//! server sends its response as stream 1 of the new HTTP/2 connection. The
+//! `h2` client can't adopt a stream it didn't open, so after the switch a
+//! placeholder request is sent as stream 1, and `H2cIo` keeps its frames off
+//! the wire. The server's response to the original request is then
+//! received as the response to the placeholder.
+
+use std::error::Error as StdError;
+use std::io;
+
+use futures_util::future::{FutureExt as _, TryFutureExt as _};
+use http::header::{HeaderValue, CONNECTION, UPGRADE};
+use http::{HeaderMap, Method, Request, Response, StatusCode, Version};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::connect::Connected;
+use super::pool::{Key as PoolKey, Pool};
+use super::{conn, ClientError, PoolClient, PoolTx, Ver};
+use crate::body::{Body, HttpBody};
+use crate::common::{task, Pin, Poll};
+use crate::proto::h2::h2c::{FrameFilter, CONTINUATION, DATA, HEADERS};
+
+/// Add the headers asking to upgrade to `h2c` to a request.
+pub(super) fn set_upgrade_headers(headers: &mut HeaderMap) {
+    headers.append(
+        CONNECTION,
+        HeaderValue::from_static("Upgrade, HTTP2-Settings"),
+    );
+    headers.insert(UPGRADE, HeaderValue::from_static("h2c"));
+    // A SETTINGS payload of SETTINGS_ENABLE_PUSH = 0, base64url encoded.
+    // The real settings are sent in the connection preface anyway.
+    headers.insert("http2-settings", HeaderValue::from_static("AAIAAAAA"));
+}
+
+/// Returns whether a request can ask to upgrade to `h2c`.
+pub(super) fn can_upgrade<B>(req: &Request<B>) -> bool {
+    req.version() == Version::HTTP_11
+        && req.method() != Method::CONNECT
+        && !req.headers().contains_key(UPGRADE)
+}
+
+/// Returns whether the server agreed to upgrade to `h2c`.
+pub(super) fn is_switching<B>(res: &Response<B>) -> bool {
+    res.status() == StatusCode::SWITCHING_PROTOCOLS
+        && res
+            .headers()
+            .get(UPGRADE)
+            .map_or(false, |v| v.as_bytes().eq_ignore_ascii_case(b"h2c"))
+}
+
+/// The placeholder request sent as stream 1 after the switch.
+///
+/// Its pseudo-headers are all in the HPACK static table, so dropping its
+/// `HEADERS` frame doesn't leave the server's dynamic table out of sync.
+pub(super) fn placeholder<B>(body: B) -> Request<B> {
+    let mut req = Request::new(body);
+    *req.uri_mut() = http::Uri::from_static("/");
+    req
+}
+
+/// A request that asked to upgrade a new connection to `h2c`.
+pub(super) struct Upgrade<B> {
+    pub(super) conn_builder: conn::Builder,
+    pub(super) pool: Pool<PoolClient<B>>,
+    pub(super) pool_key: PoolKey,
+    pub(super) conn_info: Connected,
+    /// The body of the placeholder request.
+    pub(super) body: B,
+}
+
+impl<B> Upgrade<B>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    /// If the server switched protocols, start HTTP/2 on the connection and
+    /// return the response to the upgrade request.
+    ///
+    /// Otherwise, the response is returned as it is.
+    pub(super) async fn switch(
+        self,
+        res: Response<Body>,
+    ) -> Result<Response<Body>, ClientError<B>> {
+        if !is_switching(&res) {
+            trace!("server declined h2c upgrade");
+            return Ok(res);
+        }
+        self.handshake(res).await.map_err(ClientError::Normal)
+    }
+
+    async fn handshake(mut self, res: Response<Body>) -> crate::Result<Response<Body>> {
+        trace!("server switched to h2c, starting HTTP/2 handshake");
+        let upgraded = res.into_body().on_upgrade().await?;
+
+        self.conn_builder.http2_only(true);
+        let (tx, conn) = self.conn_builder.handshake(H2cIo::new(upgraded)).await?;
+        self.conn_builder.exec.execute(
+            conn.map_err(|e| debug!("client connection error: {}", e))
+                .map(|_| ()),
+        );
+        let tx = tx.when_ready().await?;
+
+        let mut client = PoolClient {
+            conn_info: self.conn_info,
+            tx: PoolTx::Http2(tx.into_http2()),
+        };
+        // This must be the first request on the connection, to be stream 1.
+        let fut = client.send_request_retryable(placeholder(self.body));
+
+        // Make the connection available to other requests. If another HTTP/2
+        // connection is already being made, this one just finishes the
+        // response.
+        if let Some(connecting) = self.pool.connecting(&self.pool_key, Ver::Http2) {
+            drop(self.pool.pooled(connecting, client));
+        }
+
+        fut.await.map_err(|(err, _)| err)
+    }
+}
+
+/// An IO of an upgraded connection, that drops the frames the client writes
+/// on stream 1.
+#[derive(Debug)]
+pub(super) struct H2cIo<T> {
+    io: T,
+    frames: FrameFilter,
+}
+
+impl<T> H2cIo<T> {
+    pub(super) fn new(io: T) -> H2cIo<T> {
+        H2cIo {
+            io,
+            frames: FrameFilter::client(),
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for H2cIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for H2cIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = &mut *self;
+        me.frames.poll_write(&mut me.io, cx, buf, |head| {
+            head.stream_id == 1
+                && (head.kind == DATA || head.kind == HEADERS || head.kind == CONTINUATION)
+        })
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        let me = &mut *self;
+        ready!(me.frames.poll_write_pending(&mut me.io, cx))?;
+        Pin::new(&mut me.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        let me = &mut *self;
+        ready!(me.frames.poll_write_pending(&mut me.io, cx))?;
+        Pin::new(&mut me.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::H2cIo;
+    use tokio::io::AsyncWriteExt;
+
+    fn frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let len = payload.len() as u32;
+        let mut frame = len.to_be_bytes()[1..].to_vec();
+        frame.push(kind);
+        frame.push(flags);
+        frame.extend_from_slice(&stream_id.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[tokio::test]
+    async fn drops_client_frames_on_stream_1() {
+        let preface = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+        let settings = frame(0x4, 0, 0, &[0, 2, 0, 0, 0, 0]);
+        let headers = frame(0x1, 0x5, 1, &[0x82, 0x86, 0x84]);
+        let window_update = frame(0x8, 0, 1, &[0, 0, 1, 0]);
+        let data = frame(0x0, 0, 3, b"hello");
+
+        let mut written = Vec::new();
+        written.extend_from_slice(preface);
+        written.extend_from_slice(&settings);
+        written.extend_from_slice(&headers);
+        written.extend_from_slice(&window_update);
+        written.extend_from_slice(&data);
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(preface);
+        expected.extend_from_slice(&settings);
+        expected.extend_from_slice(&window_update);
+        expected.extend_from_slice(&data);
+
+        // Write in small, uneven chunks, so frame headers are split up.
+        let mut io = H2cIo::new(Vec::new());
+        for chunk in written.chunks(5) {
+            io.write_all(chunk).await.unwrap();
+        }
+        io.flush().await.unwrap();
+
+        assert_eq!(io.io, expected);
+    }
+}
@@ -17,6 +17,8 @@
 //! - Automatic setting of the `Host` header, based on the request `Uri`.
 //! - Automatic request **retries** when a pooled connection is closed by the
 //!   server before any bytes have been written.
+//! - Optionally following **redirects**, configured with a
+//!   [`redirect::Policy`](redirect::Policy).
 //!
 //! Many of these features can configured, by making use of
 //! [`Client::builder`](Client::builder).
@@ -51,26 +53,33 @@
 use std::error::Error as StdError;
 use std::fmt;
 use std::mem;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures_channel::oneshot;
 use futures_util::future::{self, Either, FutureExt as _, TryFutureExt as _};
-use http::header::{HeaderValue, HOST};
+use http::header::{HeaderValue, HOST, PROXY_AUTHORIZATION};
 use http::uri::Scheme;
 use http::{Method, Request, Response, Uri, Version};
 
 use self::connect::{sealed::Connect, Alpn, Connected, Connection};
 use self::pool::{Key as PoolKey, Pool, Poolable, Pooled, Reservation};
 use crate::body::{Body, HttpBody};
+#[cfg(feature = "compression")]
+use crate::common::compression;
 use crate::common::{lazy as hyper_lazy, task, BoxSendFuture, Executor, Future, Lazy, Pin, Poll};
 
 #[cfg(feature = "tcp")]
 pub use self::connect::HttpConnector;
+pub use self::pool::{PoolEvent, PoolHostStats, PoolStats};
 
 pub mod conn;
 pub mod connect;
 pub(crate) mod dispatch;
+#[cfg(feature = "http2")]
+mod h2c;
 mod pool;
+pub mod redirect;
 pub mod service;
 #[cfg(test)]
 #[cfg(feature = "runtime")]
@@ -84,11 +93,20 @@ pub struct Client<C, B = Body> {
     pool: Pool<PoolClient<B>>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 struct Config {
     retry_canceled_requests: bool,
     set_host: bool,
     ver: Ver,
+    #[cfg(feature = "http2")]
+    h2c_upgrade: bool,
+    redirect_policy: Option<redirect::Policy>,
+    #[cfg(feature = "compression")]
+    decompress: bool,
+    #[cfg(feature = "runtime")]
+    response_head_timeout: Option<Duration>,
+    #[cfg(feature = "runtime")]
+    request_timeout: Option<Duration>,
 }
 
 /// A `Future` that will resolve to an HTTP Response.
@@ -212,46 +230,107 @@ where
     /// # }
     /// # fn main() {}
     /// ```
+    #[cfg_attr(not(feature = "compression"), allow(unused_mut))]
     pub fn request(&self, mut req: Request<B>) -> ResponseFuture {
+        #[cfg(feature = "compression")]
+        let decompress = self.config.decompress
+            && req.method() != Method::CONNECT
+            && compression::set_accept_encoding(req.headers_mut());
+
+        #[cfg(feature = "runtime")]
+        let request_deadline = self.config.request_timeout.map(|dur| Instant::now() + dur);
+        #[cfg(not(feature = "runtime"))]
+        let request_deadline = None;
+
+        let fut = match self.config.redirect_policy {
+            Some(ref policy) if req.method() != Method::CONNECT => Either::Left(
+                self.clone()
+                    .send_following_redirects(req, policy.clone(), request_deadline),
+            ),
+            _ => Either::Right(self.send_single_request(req, request_deadline)),
+        };
+        #[cfg(feature = "runtime")]
+        let fut = request_timeout(fut, request_deadline);
+        #[cfg(feature = "compression")]
+        let fut = fut.map_ok(move |res| {
+            if decompress {
+                compression::decode_response(res)
+            } else {
+                res
+            }
+        });
+        ResponseFuture::new(Box::new(fut))
+    }
+
+    async fn send_following_redirects(
+        self,
+        mut req: Request<B>,
+        policy: redirect::Policy,
+        request_deadline: Option<Instant>,
+    ) -> crate::Result<Response<Body>> {
+        let mut previous = Vec::new();
+        loop {
+            let uri = req.uri().clone();
+            let replay = redirect::Replay::new(&req);
+            let mut res = self.send_single_request(req, request_deadline).await?;
+            previous.push(uri);
+            match policy.redirect(&res, replay, &previous)? {
+                Some(next) => req = next,
+                None => {
+                    let uri = previous.pop().expect("current uri");
+                    res.extensions_mut()
+                        .insert(redirect::History::new(uri, previous));
+                    return Ok(res);
+                }
+            }
+        }
+    }
+
+    fn send_single_request(
+        &self,
+        mut req: Request<B>,
+        request_deadline: Option<Instant>,
+    ) -> impl Future<Output = crate::Result<Response<Body>>> {
         let is_http_connect = req.method() == Method::CONNECT;
         match req.version() {
             Version::HTTP_11 => (),
             Version::HTTP_10 => {
                 if is_http_connect {
                     warn!("CONNECT is not allowed for HTTP/1.0");
-                    return ResponseFuture::new(Box::new(future::err(
+                    return Either::Left(future::err(
                         crate::Error::new_user_unsupported_request_method(),
-                    )));
+                    ));
                 }
             }
             other_h2 @ Version::HTTP_2 => {
                 if self.config.ver != Ver::Http2 {
-                    return ResponseFuture::error_version(other_h2);
+                    return Either::Left(future::err(error_version(other_h2)));
                 }
             }
             // completely unsupported HTTP version (like HTTP/0.9)!
-            other => return ResponseFuture::error_version(other),
+            other => return Either::Left(future::err(error_version(other))),
         };
 
         let pool_key = match extract_domain(req.uri_mut(), is_http_connect) {
             Ok(s) => s,
             Err(err) => {
-                return ResponseFuture::new(Box::new(future::err(err)));
+                return Either::Left(future::err(err));
             }
         };
 
-        ResponseFuture::new(Box::new(self.retryably_send_request(req, pool_key)))
+        Either::Right(self.retryably_send_request(req, pool_key, request_deadline))
     }
 
     fn retryably_send_request(
         &self,
         req: Request<B>,
         pool_key: PoolKey,
+        request_deadline: Option<Instant>,
     ) -> impl Future<Output = crate::Result<Response<Body>>> {
         let client = self.clone();
         let uri = req.uri().clone();
 
-        let mut send_fut = client.send_request(req, pool_key.clone());
+        let mut send_fut = client.send_request(req, pool_key.clone(), request_deadline);
         future::poll_fn(move |cx| loop {
             match ready!(Pin::new(&mut send_fut).poll(cx)) {
                 Ok(resp) => return Poll::Ready(Ok(resp)),
@@ -272,22 +351,42 @@ where
                         reason
                     );
                     *req.uri_mut() = uri.clone();
-                    send_fut = client.send_request(req, pool_key.clone());
+                    send_fut = client.send_request(req, pool_key.clone(), request_deadline);
                 }
             }
         })
     }
 
+    /// Returns a snapshot of the connection pool of this `Client`.
+    ///
+    /// The snapshot is empty if the pool is disabled.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    #[cfg_attr(not(feature = "runtime"), allow(unused_variables))]
     fn send_request(
         &self,
         mut req: Request<B>,
         pool_key: PoolKey,
+        request_deadline: Option<Instant>,
     ) -> impl Future<Output = Result<Response<Body>, ClientError<B>>> + Unpin {
+        #[cfg(feature = "http2")]
+        let h2c_key = pool_key.clone();
         let conn = self.connection_for(pool_key);
 
         let set_host = self.config.set_host;
+        #[cfg(feature = "http2")]
+        let h2c_upgrade = self.config.h2c_upgrade;
+        #[cfg(feature = "http2")]
+        let conn_builder = self.conn_builder.clone();
+        let pool = self.pool.clone();
+        #[cfg(feature = "runtime")]
+        let response_head_timeout = self.config.response_head_timeout;
         let executor = self.conn_builder.exec.clone();
         conn.and_then(move |mut pooled| {
+            pool.checked_out(&pooled);
+
             if pooled.is_http1() {
                 if set_host {
                     let uri = req.uri().clone();
@@ -308,6 +407,11 @@ where
                     authority_form(req.uri_mut());
                 } else if pooled.conn_info.is_proxied {
                     absolute_form(req.uri_mut());
+                    if let Some(ref auth) = pooled.conn_info.proxy_authorization {
+                        req.headers_mut()
+                            .entry(PROXY_AUTHORIZATION)
+                            .or_insert_with(|| auth.clone());
+                    }
                 } else {
                     origin_form(req.uri_mut());
                 };
@@ -318,16 +422,54 @@ where
                 )));
             }
 
+            // A new HTTP/1 connection without TLS may be upgraded to HTTP/2
+            // by the first request sent on it.
+            #[cfg(feature = "http2")]
+            let h2c = if h2c_upgrade
+                && pooled.is_http1()
+                && !pooled.is_reused()
+                && !pooled.conn_info.is_proxied
+                && h2c_key.0 == Scheme::HTTP
+                && h2c::can_upgrade(&req)
+            {
+                // The placeholder request after the switch needs an empty
+                // body, which can only be made for a `Body`.
+                redirect::empty_body::<B>().map(|body| {
+                    h2c::set_upgrade_headers(req.headers_mut());
+                    h2c::Upgrade {
+                        conn_builder,
+                        pool,
+                        pool_key: h2c_key,
+                        conn_info: pooled.conn_info.clone(),
+                        body,
+                    }
+                })
+            } else {
+                None
+            };
+
             let fut = pooled
                 .send_request_retryable(req)
                 .map_err(ClientError::map_with_reused(pooled.is_reused()));
 
+            #[cfg(feature = "runtime")]
+            let fut =
+                response_head_timeout_or_deadline(fut, response_head_timeout, request_deadline);
+
             // If the Connector included 'extra' info, add to Response...
             let extra_info = pooled.conn_info.extra.clone();
+            #[cfg(feature = "tcp")]
+            let is_reused = pooled.is_reused();
             let fut = fut.map_ok(move |mut res| {
                 if let Some(extra) = extra_info {
                     extra.set(res.extensions_mut());
                 }
+                #[cfg(feature = "tcp")]
+                {
+                    if let Some(info) = res.extensions_mut().get_mut::<connect::HttpInfo>() {
+                        info.set_reused(is_reused);
+                    }
+                }
                 res
             });
 
@@ -342,7 +484,21 @@ where
                 return Either::Right(Either::Left(fut));
             }
 
-            Either::Right(Either::Right(fut.map_ok(move |mut res| {
+            let fut = fut.map(move |result| {
+                let mut res = match result {
+                    Ok(res) => res,
+                    Err(err) => {
+                        // The response was abandoned, so the connection
+                        // can't be used for another request.
+                        if let ClientError::Normal(ref err) = err {
+                            if err.is_timeout() {
+                                pooled.discard();
+                            }
+                        }
+                        return Err(err);
+                    }
+                };
+
                 // If pooled is HTTP/2, we can toss this reference immediately.
                 //
                 // when pooled is dropped, it will try to insert back into the
@@ -372,8 +528,18 @@ where
 
                     executor.execute(on_idle);
                 }
-                res
-            })))
+                Ok(res)
+            });
+
+            #[cfg(feature = "http2")]
+            let fut = match h2c {
+                Some(upgrade) => {
+                    Either::Left(Box::pin(fut.and_then(move |res| upgrade.switch(res))))
+                }
+                None => Either::Right(fut),
+            };
+
+            Either::Right(Either::Right(fut))
         })
     }
 
@@ -393,9 +559,12 @@ where
         // - If a new connection is started, but the Checkout wins after
         //   (an idle connection became available first), the started
         //   connection future is spawned into the runtime to complete,
-        //   and then be inserted into the pool as an idle connection.
+        //   and then be inserted into the pool as an idle connection. If it
+        //   is still waiting for the connection limits of the pool, it is
+        //   canceled instead.
         let checkout = self.pool.checkout(pool_key.clone());
-        let connect = self.connect_to(pool_key);
+        let (cancel_acquire, acquire_canceled) = oneshot::channel();
+        let connect = self.connect_to(pool_key, acquire_canceled);
 
         let executor = self.conn_builder.exec.clone();
         // The order of the `select` is depended on below...
@@ -413,6 +582,7 @@ where
                 // If it *wasn't* ready yet, then the connect future will
                 // have been started...
                 if connecting.started() {
+                    let _ = cancel_acquire.send(());
                     let bg = connecting
                         .map_err(|err| {
                             trace!("background connect error: {}", err);
@@ -457,6 +627,7 @@ where
     fn connect_to(
         &self,
         pool_key: PoolKey,
+        acquire_canceled: oneshot::Receiver<()>,
     ) -> impl Lazy<Output = crate::Result<Pooled<PoolClient<B>>>> + Unpin {
         let executor = self.conn_builder.exec.clone();
         let pool = self.pool.clone();
@@ -482,7 +653,10 @@ where
                     return Either::Right(future::err(canceled));
                 }
             };
-            Either::Left(
+            // Wait for the connection limits of the pool, if any. The
+            // permit is held by the connection task, until it closes.
+            let acquire = pool.acquire(&pool_key).cancel_on(acquire_canceled);
+            Either::Left(acquire.and_then(move |permit| {
                 connector
                     .connect(connect::sealed::Internal, dst)
                     .map_err(crate::Error::new_connect)
@@ -525,7 +699,7 @@ where
                                     );
                                     executor.execute(
                                         conn.map_err(|e| debug!("client connection error: {}", e))
-                                            .map(|_| ()),
+                                            .map(move |_| drop(permit)),
                                     );
 
                                     // Wait for 'conn' to ready up before we
@@ -554,8 +728,8 @@ where
                                     )
                                 }),
                         ))
-                    }),
-            )
+                    })
+            }))
         })
     }
 }
@@ -623,13 +797,6 @@ impl ResponseFuture {
     fn new(fut: Box<dyn Future<Output = crate::Result<Response<Body>>> + Send>) -> Self {
         Self { inner: fut.into() }
     }
-
-    fn error_version(ver: Version) -> Self {
-        warn!("Request has unsupported version \"{:?}\"", ver);
-        ResponseFuture::new(Box::new(future::err(
-            crate::Error::new_user_unsupported_version(),
-        )))
-    }
 }
 
 impl fmt::Debug for ResponseFuture {
@@ -785,6 +952,71 @@ impl<B> ClientError<B> {
     }
 }
 
+/// Fails the request if the whole request takes longer than the
+/// `request_timeout`, including reading the response body.
+#[cfg(feature = "runtime")]
+fn request_timeout<F>(
+    fut: F,
+    deadline: Option<Instant>,
+) -> impl Future<Output = crate::Result<Response<Body>>>
+where
+    F: Future<Output = crate::Result<Response<Body>>>,
+{
+    match deadline {
+        Some(deadline) => Either::Left(tokio::time::timeout_at(deadline.into(), fut).map(
+            move |result| match result {
+                Ok(Ok(mut res)) => {
+                    if !res.body().is_end_stream() {
+                        res.body_mut().set_deadline(deadline);
+                    }
+                    Ok(res)
+                }
+                Ok(Err(err)) => Err(err),
+                Err(_elapsed) => Err(crate::Error::new_request_timeout()),
+            },
+        )),
+        None => Either::Right(fut),
+    }
+}
+
+/// Fails a request sent on a connection if the response head doesn't arrive
+/// within the `response_head_timeout`, or before the request's deadline.
+///
+/// This applies to the connection's response future, so that the connection
+/// can be discarded instead of returned to the pool.
+#[cfg(feature = "runtime")]
+fn response_head_timeout_or_deadline<F, B>(
+    fut: F,
+    head_timeout: Option<Duration>,
+    request_deadline: Option<Instant>,
+) -> impl Future<Output = Result<Response<Body>, ClientError<B>>> + Unpin
+where
+    F: Future<Output = Result<Response<Body>, ClientError<B>>> + Unpin,
+{
+    let head_deadline = head_timeout.map(|dur| Instant::now() + dur);
+    let (deadline, new_error): (Instant, fn() -> crate::Error) =
+        match (head_deadline, request_deadline) {
+            (Some(head), Some(request)) if request < head => {
+                (request, crate::Error::new_request_timeout)
+            }
+            (Some(head), _) => (head, crate::Error::new_response_head_timeout),
+            (None, Some(request)) => (request, crate::Error::new_request_timeout),
+            (None, None) => return Either::Right(fut),
+        };
+
+    Either::Left(
+        tokio::time::timeout_at(deadline.into(), fut).map(move |result| match result {
+            Ok(result) => result,
+            Err(_elapsed) => Err(ClientError::Normal(new_error())),
+        }),
+    )
+}
+
+fn error_version(ver: Version) -> crate::Error {
+    warn!("Request has unsupported version \"{:?}\"", ver);
+    crate::Error::new_user_unsupported_version()
+}
+
 /// A marker to identify what version a pooled connection is.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum Ver {
@@ -919,11 +1151,25 @@ impl Default for Builder {
                 retry_canceled_requests: true,
                 set_host: true,
                 ver: Ver::Auto,
+                #[cfg(feature = "http2")]
+                h2c_upgrade: false,
+                redirect_policy: None,
+                #[cfg(feature = "compression")]
+                decompress: false,
+                #[cfg(feature = "runtime")]
+                response_head_timeout: None,
+                #[cfg(feature = "runtime")]
+                request_timeout: None,
             },
             conn_builder: conn::Builder::new(),
             pool_config: pool::Config {
                 idle_timeout: Some(Duration::from_secs(90)),
                 max_idle_per_host: std::usize::MAX,
+                max_per_host: None,
+                max_total: None,
+                #[cfg(feature = "runtime")]
+                queue_timeout: None,
+                event_hook: None,
             },
         }
     }
@@ -984,6 +1230,74 @@ impl Builder {
         self
     }
 
+    /// Sets the maximum number of connections open at once to a single host.
+    ///
+    /// Connections still being established count towards the limit. When it
+    /// is reached, new requests to the host wait for one of its connections
+    /// to become idle or to close.
+    ///
+    /// This has no effect if the pool is disabled.
+    ///
+    /// Default is `None` (no limit).
+    pub fn pool_max_connections_per_host(&mut self, max: impl Into<Option<usize>>) -> &mut Self {
+        self.pool_config.max_per_host = max.into();
+        self
+    }
+
+    /// Sets the maximum number of connections open at once, to all hosts.
+    ///
+    /// Connections still being established count towards the limit. When it
+    /// is reached, new requests wait for a connection to close, and are
+    /// served in the order they started waiting. An idle connection to
+    /// another host is closed to make room.
+    ///
+    /// This has no effect if the pool is disabled.
+    ///
+    /// Default is `None` (no limit).
+    pub fn pool_max_connections(&mut self, max: impl Into<Option<usize>>) -> &mut Self {
+        self.pool_config.max_total = max.into();
+        self
+    }
+
+    /// Set a timeout for requests waiting on the connection limits of the
+    /// pool.
+    ///
+    /// If the timeout elapses, the `ResponseFuture` resolves to an error for
+    /// which `Error::is_timeout()` and `Error::is_pool_timeout()` are true.
+    ///
+    /// Pass `None` to wait indefinitely.
+    ///
+    /// Default is `None`.
+    ///
+    /// # Cargo Feature
+    ///
+    /// Requires the `runtime` cargo feature to be enabled.
+    #[cfg(feature = "runtime")]
+    pub fn pool_queue_timeout<D>(&mut self, val: D) -> &mut Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.pool_config.queue_timeout = val.into();
+        self
+    }
+
+    /// Set a function to call on the events of the connection pool.
+    ///
+    /// The function is called with a [`PoolEvent`](PoolEvent) when a
+    /// connection is established, given to a request, reused, or removed
+    /// from the pool. It is called after the pool is unlocked, so it may
+    /// call [`Client::pool_stats`](Client::pool_stats), but it runs on the
+    /// task that touched the pool, so it should return quickly.
+    ///
+    /// This has no effect if the pool is disabled.
+    pub fn pool_event_hook<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn(&PoolEvent) + Send + Sync + 'static,
+    {
+        self.pool_config.event_hook = Some(pool::EventHook(Arc::new(hook)));
+        self
+    }
+
     // HTTP/1 options
 
     /// Set whether HTTP/1 connections should try to use vectored writes,
@@ -1027,6 +1341,23 @@ impl Builder {
         self
     }
 
+    /// Set the maximum number of headers allowed in a response.
+    ///
+    /// If a server sends more headers than this, the response is
+    /// rejected with a parse error.
+    ///
+    /// Up to 100 headers are parsed using storage on the stack. A larger
+    /// maximum means the storage is allocated on the heap for every
+    /// response instead, which is somewhat slower.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is 100.
+    pub fn http1_max_headers(&mut self, val: usize) -> &mut Self {
+        self.conn_builder.http1_max_headers(val);
+        self
+    }
+
     /// Set whether HTTP/1 connections will write header names as title case at
     /// the socket level.
     ///
@@ -1038,6 +1369,35 @@ impl Builder {
         self
     }
 
+    /// Set whether to record the original case of response header names.
+    ///
+    /// The original names are stored in the extensions of the `Response`.
+    /// If those extensions are moved to a `Request`, such as when proxying,
+    /// its headers are written with the recorded case, taking precedence
+    /// over `http1_title_case_headers`.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is false.
+    pub fn http1_preserve_header_case(&mut self, val: bool) -> &mut Self {
+        self.conn_builder.http1_preserve_header_case(val);
+        self
+    }
+
+    /// Set whether to record the original order of response headers.
+    ///
+    /// The original order is stored in the extensions of the `Response`.
+    /// If those extensions are moved to a `Request`, such as when proxying,
+    /// its headers are written in the recorded order.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is false.
+    pub fn http1_preserve_header_order(&mut self, val: bool) -> &mut Self {
+        self.conn_builder.http1_preserve_header_order(val);
+        self
+    }
+
     /// Set whether the connection **must** use HTTP/2.
     ///
     /// The destination must either allow HTTP2 Prior Knowledge, or the
@@ -1055,6 +1415,25 @@ impl Builder {
         self
     }
 
+    /// Set whether new HTTP/1 connections should try to upgrade to HTTP/2.
+    ///
+    /// If enabled, the first request on a new `http` connection is sent with
+    /// HTTP/1.1, asking the server to upgrade to cleartext HTTP/2 with
+    /// `Upgrade: h2c`. If the server agrees, the connection is pooled as an
+    /// HTTP/2 connection, and later requests share it. Otherwise, the
+    /// connection keeps using HTTP/1.
+    ///
+    /// Connections using TLS, or to a proxy, are never upgraded. The upgrade
+    /// is only tried if the request body type is `Body`.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_h2c_upgrade(&mut self, val: bool) -> &mut Self {
+        self.client_config.h2c_upgrade = val;
+        self
+    }
+
     /// Sets the [`SETTINGS_INITIAL_WINDOW_SIZE`][spec] option for HTTP2
     /// stream-level flow control.
     ///
@@ -1170,6 +1549,97 @@ impl Builder {
         self
     }
 
+    /// Set a timeout for receiving the response head, once the request has
+    /// been given to a connection.
+    ///
+    /// If the timeout elapses, the `ResponseFuture` resolves to an error for
+    /// which `Error::is_timeout()` is true, and the connection is closed
+    /// instead of being returned to the pool.
+    ///
+    /// Pass `None` to disable the timeout.
+    ///
+    /// Default is `None`.
+    ///
+    /// # Cargo Feature
+    ///
+    /// Requires the `runtime` cargo feature to be enabled.
+    #[cfg(feature = "runtime")]
+    pub fn response_head_timeout<D>(&mut self, val: D) -> &mut Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.client_config.response_head_timeout = val.into();
+        self
+    }
+
+    /// Set a timeout for the whole request, from calling `Client::request`
+    /// until the response body has been read.
+    ///
+    /// This includes getting a connection, either from the pool or by
+    /// connecting, any retries of canceled requests, and any redirects that
+    /// are followed.
+    ///
+    /// If the timeout elapses before the response head is received, the
+    /// `ResponseFuture` resolves to an error for which `Error::is_timeout()`
+    /// is true. If the request had been given to a connection, the
+    /// connection is closed instead of being returned to the pool. If it
+    /// elapses while the body is being read, the body yields such an error
+    /// instead, and dropping the body closes the connection.
+    ///
+    /// Pass `None` to disable the timeout.
+    ///
+    /// Default is `None`.
+    ///
+    /// # Cargo Feature
+    ///
+    /// Requires the `runtime` cargo feature to be enabled.
+    #[cfg(feature = "runtime")]
+    pub fn request_timeout<D>(&mut self, val: D) -> &mut Self
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.client_config.request_timeout = val.into();
+        self
+    }
+
+    /// Set a policy for following redirects.
+    ///
+    /// When set, `3xx` responses with a `Location` are followed as allowed by
+    /// the policy, and responses include a
+    /// [`redirect::History`](redirect::History) extension. See the
+    /// [`redirect`](redirect) module for details.
+    ///
+    /// `CONNECT` requests never follow redirects.
+    ///
+    /// Default is `None`, which returns redirect responses to the caller.
+    pub fn redirect_policy<P>(&mut self, policy: P) -> &mut Self
+    where
+        P: Into<Option<redirect::Policy>>,
+    {
+        self.client_config.redirect_policy = policy.into();
+        self
+    }
+
+    /// Set whether to decode compressed response bodies.
+    ///
+    /// When enabled, requests that don't have an `Accept-Encoding` header are
+    /// sent with `Accept-Encoding: gzip, deflate, br`, and response bodies
+    /// with one of those `Content-Encoding`s are decoded as they are read.
+    /// The `Content-Encoding` and `Content-Length` headers are removed from
+    /// decoded responses.
+    ///
+    /// Default is `false`.
+    ///
+    /// # Cargo Feature
+    ///
+    /// Requires the `compression` cargo feature to be enabled.
+    #[cfg(feature = "compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+    pub fn decompress(&mut self, val: bool) -> &mut Self {
+        self.client_config.decompress = val;
+        self
+    }
+
     /// Set whether to retry requests that get disrupted before ever starting
     /// to write.
     ///
@@ -1230,10 +1700,10 @@ impl Builder {
         B::Data: Send,
     {
         Client {
-            config: self.client_config,
+            config: self.client_config.clone(),
             conn_builder: self.conn_builder.clone(),
             connector,
-            pool: Pool::new(self.pool_config, &self.conn_builder.exec),
+            pool: Pool::new(self.pool_config.clone(), &self.conn_builder.exec),
         }
     }
 }
@@ -1,12 +1,14 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::mem;
 use std::ops::{Deref, DerefMut};
-use std::sync::{Arc, Mutex, Weak};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
 
 #[cfg(not(feature = "runtime"))]
 use std::time::{Duration, Instant};
 
 use futures_channel::oneshot;
+use http::uri::{Authority, Scheme};
 #[cfg(feature = "runtime")]
 use tokio::time::{Duration, Instant, Interval};
 
@@ -18,6 +20,8 @@ use crate::common::{task, Exec, Future, Pin, Poll, Unpin};
 pub(super) struct Pool<T> {
     // If the pool is disabled, this is None.
     inner: Option<Arc<Mutex<PoolInner<T>>>>,
+    #[cfg(feature = "runtime")]
+    queue_timeout: Option<Duration>,
 }
 
 // Before using a pooled connection, make sure the sender is not dead.
@@ -53,7 +57,7 @@ pub(super) enum Reservation<T> {
 }
 
 /// Simple type alias in case the key type needs to be adjusted.
-pub(super) type Key = (http::uri::Scheme, http::uri::Authority); //Arc<String>;
+pub(super) type Key = (Scheme, Authority); //Arc<String>;
 
 struct PoolInner<T> {
     // A flag that a connection is being established, and the connection
@@ -74,6 +78,24 @@ struct PoolInner<T> {
     // them that the Conn could be used instead of waiting for a brand new
     // connection.
     waiters: HashMap<Key, VecDeque<oneshot::Sender<T>>>,
+    // Limits on the number of connections open at once, per host and in
+    // total, counting the ones still connecting.
+    max_per_host: Option<usize>,
+    max_total: Option<usize>,
+    // The number of open connections, only counted if there are limits.
+    open: HashMap<Key, usize>,
+    open_total: usize,
+    // New connections waiting for the pool to be under its limits, in the
+    // order they started waiting.
+    connect_waiters: VecDeque<(Key, oneshot::Sender<Permit<T>>)>,
+    // The connections being established and checked out, for `PoolStats`.
+    counts: HashMap<Key, Counts>,
+    checkouts: u64,
+    reused: u64,
+    event_hook: Option<EventHook>,
+    // The events recorded while the pool is locked, passed to the hook
+    // once `Locked` unlocks it.
+    events: Vec<PoolEvent>,
     // A oneshot channel is used to allow the interval to be notified when
     // the Pool completely drops. That way, the interval can cancel immediately.
     #[cfg(feature = "runtime")]
@@ -87,10 +109,64 @@ struct PoolInner<T> {
 // doesn't need it!
 struct WeakOpt<T>(Option<Weak<T>>);
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub(super) struct Config {
     pub(super) idle_timeout: Option<Duration>,
     pub(super) max_idle_per_host: usize,
+    pub(super) max_per_host: Option<usize>,
+    pub(super) max_total: Option<usize>,
+    #[cfg(feature = "runtime")]
+    pub(super) queue_timeout: Option<Duration>,
+    pub(super) event_hook: Option<EventHook>,
+}
+
+/// A function called with the events of a pool.
+#[derive(Clone)]
+pub(super) struct EventHook(pub(super) Arc<dyn Fn(&PoolEvent) + Send + Sync>);
+
+#[derive(Default)]
+struct Counts {
+    connecting: usize,
+    in_use: usize,
+}
+
+/// A snapshot of the connection pool of a `Client`.
+///
+/// Returned by [`Client::pool_stats`](super::Client::pool_stats).
+#[derive(Clone, Debug, Default)]
+pub struct PoolStats {
+    hosts: Vec<PoolHostStats>,
+    checkouts: u64,
+    reused: u64,
+}
+
+/// The connections of a pool to a single host.
+#[derive(Clone, Debug)]
+pub struct PoolHostStats {
+    key: Key,
+    idle: usize,
+    in_use: usize,
+    connecting: usize,
+    waiting: usize,
+}
+
+/// An event in the connection pool of a `Client`.
+///
+/// Passed to the hook set with
+/// [`Builder::pool_event_hook`](super::Builder::pool_event_hook).
+#[derive(Clone, Debug)]
+pub struct PoolEvent {
+    kind: EventKind,
+    key: Key,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EventKind {
+    Connect,
+    Checkout,
+    Reuse,
+    IdleExpired,
+    Evict,
 }
 
 impl Config {
@@ -109,6 +185,16 @@ impl<T> Pool<T> {
                 idle_interval_ref: None,
                 max_idle_per_host: config.max_idle_per_host,
                 waiters: HashMap::new(),
+                max_per_host: config.max_per_host,
+                max_total: config.max_total,
+                open: HashMap::new(),
+                open_total: 0,
+                connect_waiters: VecDeque::new(),
+                counts: HashMap::new(),
+                checkouts: 0,
+                reused: 0,
+                event_hook: config.event_hook,
+                events: Vec::new(),
                 #[cfg(feature = "runtime")]
                 exec: __exec.clone(),
                 timeout: config.idle_timeout,
@@ -117,19 +203,59 @@ impl<T> Pool<T> {
             None
         };
 
-        Pool { inner }
+        Pool {
+            inner,
+            #[cfg(feature = "runtime")]
+            queue_timeout: config.queue_timeout,
+        }
     }
 
     fn is_enabled(&self) -> bool {
         self.inner.is_some()
     }
 
+    /// Returns a snapshot of the connections in the pool.
+    pub(super) fn stats(&self) -> PoolStats {
+        let inner = match self.inner {
+            Some(ref enabled) => Locked::lock(enabled).unwrap(),
+            None => return PoolStats::default(),
+        };
+
+        let keys = inner
+            .idle
+            .keys()
+            .chain(inner.counts.keys())
+            .chain(inner.waiters.keys())
+            .collect::<HashSet<_>>();
+        let hosts = keys
+            .into_iter()
+            .map(|key| {
+                let counts = inner.counts.get(key);
+                PoolHostStats {
+                    key: key.clone(),
+                    idle: inner.idle.get(key).map_or(0, Vec::len),
+                    in_use: counts.map_or(0, |counts| counts.in_use),
+                    connecting: counts.map_or(0, |counts| counts.connecting),
+                    waiting: inner.waiters.get(key).map_or(0, |waiters| {
+                        waiters.iter().filter(|tx| !tx.is_canceled()).count()
+                    }),
+                }
+            })
+            .collect();
+
+        PoolStats {
+            hosts,
+            checkouts: inner.checkouts,
+            reused: inner.reused,
+        }
+    }
+
     #[cfg(test)]
     pub(super) fn no_timer(&self) {
         // Prevent an actual interval from being created for this pool...
         #[cfg(feature = "runtime")]
         {
-            let mut inner = self.inner.as_ref().unwrap().lock().unwrap();
+            let mut inner = Locked::lock(self.inner.as_ref().unwrap()).unwrap();
             assert!(inner.idle_interval_ref.is_none(), "timer already spawned");
             let (tx, _) = oneshot::channel();
             inner.idle_interval_ref = Some(tx);
@@ -151,31 +277,86 @@ impl<T: Poolable> Pool<T> {
     /// Ensure that there is only ever 1 connecting task for HTTP/2
     /// connections. This does nothing for HTTP/1.
     pub(super) fn connecting(&self, key: &Key, ver: Ver) -> Option<Connecting<T>> {
-        if ver == Ver::Http2 {
-            if let Some(ref enabled) = self.inner {
-                let mut inner = enabled.lock().unwrap();
-                return if inner.connecting.insert(key.clone()) {
-                    let connecting = Connecting {
-                        key: key.clone(),
-                        pool: WeakOpt::downgrade(enabled),
-                    };
-                    Some(connecting)
-                } else {
-                    trace!("HTTP/2 connecting already in progress for {:?}", key);
-                    None
-                };
+        let enabled = match self.inner {
+            Some(ref enabled) => enabled,
+            None => {
+                return Some(Connecting {
+                    key: key.clone(),
+                    pool: WeakOpt::none(),
+                    counted: WeakOpt::none(),
+                })
             }
-        }
+        };
+
+        let mut inner = Locked::lock(enabled).unwrap();
+        let pool = if ver == Ver::Http2 {
+            if !inner.connecting.insert(key.clone()) {
+                trace!("HTTP/2 connecting already in progress for {:?}", key);
+                return None;
+            }
+            WeakOpt::downgrade(enabled)
+        } else {
+            // in HTTP/1's case, there is never a lock, so we don't
+            // need to release it in Drop.
+            WeakOpt::none()
+        };
+        inner.count(key, |counts| counts.connecting += 1);
 
-        // else
         Some(Connecting {
             key: key.clone(),
-            // in HTTP/1's case, there is never a lock, so we don't
-            // need to do anything in Drop.
-            pool: WeakOpt::none(),
+            pool,
+            counted: WeakOpt::downgrade(enabled),
         })
     }
 
+    /// Returns an `Acquire`, a future that resolves once a new connection
+    /// can be opened for `key` within the limits of the pool.
+    ///
+    /// The connection is counted as open until the `Permit` is dropped.
+    pub(super) fn acquire(&self, key: &Key) -> Acquire<T> {
+        let state = match self.inner {
+            Some(ref enabled) => {
+                let mut inner = Locked::lock(enabled).unwrap();
+                if !inner.is_limited() {
+                    AcquireState::Ready(None)
+                } else if inner.can_open(key) {
+                    inner.open(key);
+                    AcquireState::Ready(Some(Permit {
+                        key: key.clone(),
+                        pool: WeakOpt::downgrade(enabled),
+                    }))
+                } else {
+                    trace!("connection limit reached, waiting to connect {:?}", key);
+                    // If the pool as a whole is at its limit, closing an
+                    // idle connection to another host makes room.
+                    if !inner.can_open_total() {
+                        inner.evict_idle(key);
+                    }
+                    let (tx, rx) = oneshot::channel();
+                    inner.connect_waiters.retain(|(_, tx)| !tx.is_canceled());
+                    inner.connect_waiters.push_back((key.clone(), tx));
+                    AcquireState::Waiting(rx)
+                }
+            }
+            None => AcquireState::Ready(None),
+        };
+
+        #[cfg(feature = "runtime")]
+        let timeout = match state {
+            AcquireState::Waiting(_) => self
+                .queue_timeout
+                .map(|dur| Box::pin(tokio::time::sleep(dur))),
+            AcquireState::Ready(_) => None,
+        };
+
+        Acquire {
+            state,
+            canceled: None,
+            #[cfg(feature = "runtime")]
+            timeout,
+        }
+    }
+
     #[cfg(test)]
     fn locked(&self) -> std::sync::MutexGuard<'_, PoolInner<T>> {
         self.inner.as_ref().expect("enabled").lock().expect("lock")
@@ -200,16 +381,28 @@ impl<T: Poolable> Pool<T> {
     }
     */
 
-    pub(super) fn pooled(
-        &self,
-        #[cfg_attr(not(feature = "http2"), allow(unused_mut))] mut connecting: Connecting<T>,
-        value: T,
-    ) -> Pooled<T> {
-        let (value, pool_ref) = if let Some(ref enabled) = self.inner {
-            match value.reserve() {
+    pub(super) fn pooled(&self, mut connecting: Connecting<T>, value: T) -> Pooled<T> {
+        let (value, pool_ref, counted) = if let Some(ref enabled) = self.inner {
+            let mut inner = Locked::lock(enabled).unwrap();
+            // A unique connection is counted as in use from here on, instead
+            // of connecting. A shared one is idle, even while it is used.
+            // Do this here instead of Drop for Connecting because we already
+            // have a lock.
+            let was_connecting = connecting.counted.0.take().is_some();
+            let is_unique = !value.can_share();
+            inner.count(&connecting.key, |counts| {
+                if was_connecting {
+                    counts.connecting -= 1;
+                }
+                if is_unique {
+                    counts.in_use += 1;
+                }
+            });
+            inner.emit(&connecting.key, EventKind::Connect);
+
+            let (value, pool_ref) = match value.reserve() {
                 #[cfg(feature = "http2")]
                 Reservation::Shared(to_insert, to_return) => {
-                    let mut inner = enabled.lock().unwrap();
                     inner.put(connecting.key.clone(), to_insert, enabled);
                     // Do this here instead of Drop for Connecting because we
                     // already have a lock, no need to lock the mutex twice.
@@ -227,19 +420,26 @@ impl<T: Poolable> Pool<T> {
                     // completed
                     (value, WeakOpt::downgrade(enabled))
                 }
-            }
+            };
+            let counted = if is_unique {
+                WeakOpt::downgrade(enabled)
+            } else {
+                WeakOpt::none()
+            };
+            (value, pool_ref, counted)
         } else {
             // If pool is not enabled, skip all the things...
 
             // The Connecting should have had no pool ref
             debug_assert!(connecting.pool.upgrade().is_none());
 
-            (value, WeakOpt::none())
+            (value, WeakOpt::none(), WeakOpt::none())
         };
         Pooled {
             key: connecting.key.clone(),
             is_reused: false,
             pool: pool_ref,
+            counted,
             value: Some(value),
         }
     }
@@ -255,9 +455,14 @@ impl<T: Poolable> Pool<T> {
         // unique or shared. So, the hack is to just assume Ver::Http2 means
         // shared... :(
         let mut pool_ref = WeakOpt::none();
-        if !value.can_share() {
-            if let Some(ref enabled) = self.inner {
+        let mut counted = WeakOpt::none();
+        if let Some(ref enabled) = self.inner {
+            // A shared connection isn't counted as in use for each request.
+            if !value.can_share() {
                 pool_ref = WeakOpt::downgrade(enabled);
+                let mut inner = Locked::lock(enabled).unwrap();
+                inner.count(key, |counts| counts.in_use += 1);
+                counted = WeakOpt::downgrade(enabled);
             }
         }
 
@@ -265,15 +470,31 @@ impl<T: Poolable> Pool<T> {
             is_reused: true,
             key: key.clone(),
             pool: pool_ref,
+            counted,
             value: Some(value),
         }
     }
+
+    /// Records that `pooled` was given to a request.
+    pub(super) fn checked_out(&self, pooled: &Pooled<T>) {
+        if let Some(ref enabled) = self.inner {
+            let mut inner = Locked::lock(enabled).unwrap();
+            inner.checkouts += 1;
+            inner.emit(&pooled.key, EventKind::Checkout);
+            if pooled.is_reused() {
+                inner.reused += 1;
+                inner.emit(&pooled.key, EventKind::Reuse);
+            }
+        }
+    }
 }
 
 /// Pop off this list, looking for a usable connection that hasn't expired.
 struct IdlePopper<'a, T> {
     key: &'a Key,
     list: &'a mut Vec<Idle<T>>,
+    event_hook: Option<&'a EventHook>,
+    events: &'a mut Vec<PoolEvent>,
 }
 
 impl<'a, T: Poolable + 'a> IdlePopper<'a, T> {
@@ -283,6 +504,7 @@ impl<'a, T: Poolable + 'a> IdlePopper<'a, T> {
             // timeout, simply drop it and keep looking...
             if !entry.value.is_open() {
                 trace!("removing closed connection for {:?}", self.key);
+                emit(self.event_hook, self.events, self.key, EventKind::Evict);
                 continue;
             }
             // TODO: Actually, since the `idle` list is pushed to the end always,
@@ -293,6 +515,12 @@ impl<'a, T: Poolable + 'a> IdlePopper<'a, T> {
             // whole list...
             if expiration.expires(entry.idle_at) {
                 trace!("removing expired connection for {:?}", self.key);
+                emit(
+                    self.event_hook,
+                    self.events,
+                    self.key,
+                    EventKind::IdleExpired,
+                );
                 continue;
             }
 
@@ -368,6 +596,7 @@ impl<T: Poolable> PoolInner<T> {
                     let idle_list = self.idle.entry(key.clone()).or_insert_with(Vec::new);
                     if self.max_idle_per_host <= idle_list.len() {
                         trace!("max idle per host for {:?}, dropping connection", key);
+                        self.emit(&key, EventKind::Evict);
                         return;
                     }
 
@@ -398,6 +627,38 @@ impl<T: Poolable> PoolInner<T> {
         self.waiters.remove(key);
     }
 
+    /// Closes the oldest idle HTTP/1 connection to a host other than `key`,
+    /// to make room for a connection to `key`.
+    fn evict_idle(&mut self, key: &Key) {
+        let oldest = self
+            .idle
+            .iter()
+            .filter(|(idle_key, _)| *idle_key != key)
+            .filter_map(|(idle_key, list)| {
+                list.first()
+                    .filter(|entry| !entry.value.can_share())
+                    .map(|entry| (idle_key.clone(), entry.idle_at))
+            })
+            .min_by_key(|&(_, idle_at)| idle_at);
+
+        if let Some((idle_key, _)) = oldest {
+            trace!(
+                "evicting idle connection for {:?} to connect {:?}",
+                idle_key,
+                key
+            );
+            let mut remove_idle = false;
+            if let Some(list) = self.idle.get_mut(&idle_key) {
+                list.remove(0);
+                remove_idle = list.is_empty();
+            }
+            if remove_idle {
+                self.idle.remove(&idle_key);
+            }
+            self.emit(&idle_key, EventKind::Evict);
+        }
+    }
+
     #[cfg(feature = "runtime")]
     fn spawn_idle_interval(&mut self, pool_ref: &Arc<Mutex<PoolInner<T>>>) {
         let (dur, rx) = {
@@ -425,6 +686,91 @@ impl<T: Poolable> PoolInner<T> {
 }
 
 impl<T> PoolInner<T> {
+    /// Updates the `Counts` of `key`, removing them once they are all zero.
+    fn count(&mut self, key: &Key, f: impl FnOnce(&mut Counts)) {
+        let counts = self.counts.entry(key.clone()).or_default();
+        f(counts);
+        if counts.connecting == 0 && counts.in_use == 0 {
+            self.counts.remove(key);
+        }
+    }
+
+    fn emit(&mut self, key: &Key, kind: EventKind) {
+        emit(self.event_hook.as_ref(), &mut self.events, key, kind);
+    }
+
+    fn is_limited(&self) -> bool {
+        self.max_per_host.is_some() || self.max_total.is_some()
+    }
+
+    /// Returns whether a new connection to `key` would be within the limits.
+    fn can_open(&self, key: &Key) -> bool {
+        let can_open_host = match self.max_per_host {
+            Some(max) => self.open.get(key).copied().unwrap_or(0) < max,
+            None => true,
+        };
+        can_open_host && self.can_open_total()
+    }
+
+    fn can_open_total(&self) -> bool {
+        match self.max_total {
+            Some(max) => self.open_total < max,
+            None => true,
+        }
+    }
+
+    /// Counts a new connection to `key` as open.
+    fn open(&mut self, key: &Key) {
+        *self.open.entry(key.clone()).or_insert(0) += 1;
+        self.open_total += 1;
+    }
+
+    /// Stops counting a connection to `key` as open.
+    fn forget(&mut self, key: &Key) {
+        if let Some(open) = self.open.get_mut(key) {
+            *open -= 1;
+            if *open == 0 {
+                self.open.remove(key);
+            }
+        }
+        self.open_total -= 1;
+    }
+
+    /// A connection counted by a `Permit` has closed, so the next waiting
+    /// connections within the limits can be opened.
+    fn close(&mut self, key: &Key, pool_ref: &Arc<Mutex<PoolInner<T>>>) {
+        self.forget(key);
+
+        let mut i = 0;
+        while i < self.connect_waiters.len() {
+            if self.connect_waiters[i].1.is_canceled() {
+                self.connect_waiters.remove(i);
+                continue;
+            }
+            if !self.can_open_total() {
+                break;
+            }
+            if !self.can_open(&self.connect_waiters[i].0) {
+                i += 1;
+                continue;
+            }
+
+            let (key, tx) = self.connect_waiters.remove(i).expect("index in bounds");
+            trace!("connection limit freed, waking connect to {:?}", key);
+            self.open(&key);
+            let permit = Permit {
+                key,
+                pool: WeakOpt::downgrade(pool_ref),
+            };
+            if let Err(mut permit) = tx.send(permit) {
+                // The waiter is gone. Dropping the permit would lock the pool
+                // again, so it is undone here instead.
+                permit.pool = WeakOpt::none();
+                self.forget(&permit.key);
+            }
+        }
+    }
+
     /// Any `FutureResponse`s that were created will have made a `Checkout`,
     /// and possibly inserted into the pool that it is waiting for an idle
     /// connection. If a user ever dropped that future, we need to clean out
@@ -450,14 +796,18 @@ impl<T: Poolable> PoolInner<T> {
         let now = Instant::now();
         //self.last_idle_check_at = now;
 
+        let event_hook = self.event_hook.as_ref();
+        let events = &mut self.events;
         self.idle.retain(|key, values| {
             values.retain(|entry| {
                 if !entry.value.is_open() {
                     trace!("idle interval evicting closed for {:?}", key);
+                    emit(event_hook, events, key, EventKind::Evict);
                     return false;
                 }
                 if now - entry.idle_at > dur {
                     trace!("idle interval evicting expired for {:?}", key);
+                    emit(event_hook, events, key, EventKind::IdleExpired);
                     return false;
                 }
 
@@ -475,6 +825,8 @@ impl<T> Clone for Pool<T> {
     fn clone(&self) -> Pool<T> {
         Pool {
             inner: self.inner.clone(),
+            #[cfg(feature = "runtime")]
+            queue_timeout: self.queue_timeout,
         }
     }
 }
@@ -486,6 +838,8 @@ pub(super) struct Pooled<T: Poolable> {
     is_reused: bool,
     key: Key,
     pool: WeakOpt<Mutex<PoolInner<T>>>,
+    // Counts this connection as in use until dropped.
+    counted: WeakOpt<Mutex<PoolInner<T>>>,
 }
 
 impl<T: Poolable> Pooled<T> {
@@ -497,6 +851,14 @@ impl<T: Poolable> Pooled<T> {
         self.pool.0.is_some()
     }
 
+    /// Drop the connection without returning it to the pool.
+    ///
+    /// Used when the state of the connection is unknown, such as when a
+    /// response was abandoned halfway.
+    pub fn discard(mut self) {
+        self.value.take();
+    }
+
     fn as_ref(&self) -> &T {
         self.value.as_ref().expect("not dropped")
     }
@@ -521,6 +883,12 @@ impl<T: Poolable> DerefMut for Pooled<T> {
 
 impl<T: Poolable> Drop for Pooled<T> {
     fn drop(&mut self) {
+        if let Some(pool) = self.counted.upgrade() {
+            if let Ok(mut inner) = Locked::lock(&pool) {
+                inner.count(&self.key, |counts| counts.in_use -= 1);
+            }
+        }
+
         if let Some(value) = self.value.take() {
             if !value.is_open() {
                 // If we *already* know the connection is done here,
@@ -529,7 +897,7 @@ impl<T: Poolable> Drop for Pooled<T> {
             }
 
             if let Some(pool) = self.pool.upgrade() {
-                if let Ok(mut inner) = pool.lock() {
+                if let Ok(mut inner) = Locked::lock(&pool) {
                     inner.put(self.key.clone(), value, &pool);
                 }
             } else if !value.can_share() {
@@ -590,8 +958,11 @@ impl<T: Poolable> Checkout<T> {
 
     fn checkout(&mut self, cx: &mut task::Context<'_>) -> Option<Pooled<T>> {
         let entry = {
-            let mut inner = self.pool.inner.as_ref()?.lock().unwrap();
+            let mut locked = Locked::lock(self.pool.inner.as_ref()?).unwrap();
+            let inner = &mut *locked;
             let expiration = Expiration::new(inner.timeout);
+            let event_hook = inner.event_hook.as_ref();
+            let events = &mut inner.events;
             let maybe_entry = inner.idle.get_mut(&self.key).and_then(|list| {
                 trace!("take? {:?}: expiration = {:?}", self.key, expiration.0);
                 // A block to end the mutable borrow on list,
@@ -600,6 +971,8 @@ impl<T: Poolable> Checkout<T> {
                     let popper = IdlePopper {
                         key: &self.key,
                         list,
+                        event_hook,
+                        events,
                     };
                     popper.pop(&expiration)
                 }
@@ -662,7 +1035,7 @@ impl<T> Drop for Checkout<T> {
     fn drop(&mut self) {
         if self.waiter.take().is_some() {
             trace!("checkout dropped for {:?}", self.key);
-            if let Some(Ok(mut inner)) = self.pool.inner.as_ref().map(|i| i.lock()) {
+            if let Some(Ok(mut inner)) = self.pool.inner.as_ref().map(|i| Locked::lock(i)) {
                 inner.clean_waiters(&self.key);
             }
         }
@@ -674,6 +1047,8 @@ impl<T> Drop for Checkout<T> {
 pub(super) struct Connecting<T: Poolable> {
     key: Key,
     pool: WeakOpt<Mutex<PoolInner<T>>>,
+    // Counts this connection as connecting until dropped.
+    counted: WeakOpt<Mutex<PoolInner<T>>>,
 }
 
 impl<T: Poolable> Connecting<T> {
@@ -688,16 +1063,111 @@ impl<T: Poolable> Connecting<T> {
 }
 
 impl<T: Poolable> Drop for Connecting<T> {
+    fn drop(&mut self) {
+        if let Some(pool) = self.counted.upgrade() {
+            // No need to panic on drop, that could abort!
+            if let Ok(mut inner) = Locked::lock(&pool) {
+                inner.count(&self.key, |counts| counts.connecting -= 1);
+                if self.pool.0.is_some() {
+                    inner.connected(&self.key);
+                }
+            }
+        }
+    }
+}
+
+/// Counts a connection as open in a pool with connection limits, until
+/// dropped along with the connection.
+// FIXME: allow() required due to `impl Trait` leaking types to this lint
+#[allow(missing_debug_implementations)]
+pub(super) struct Permit<T> {
+    key: Key,
+    pool: WeakOpt<Mutex<PoolInner<T>>>,
+}
+
+impl<T> Drop for Permit<T> {
     fn drop(&mut self) {
         if let Some(pool) = self.pool.upgrade() {
             // No need to panic on drop, that could abort!
-            if let Ok(mut inner) = pool.lock() {
-                inner.connected(&self.key);
+            if let Ok(mut inner) = Locked::lock(&pool) {
+                inner.close(&self.key, &pool);
             }
         }
     }
 }
 
+// FIXME: allow() required due to `impl Trait` leaking types to this lint
+#[allow(missing_debug_implementations)]
+pub(super) struct Acquire<T> {
+    state: AcquireState<T>,
+    // Once sent on, the connection is no longer wanted, and waiting for the
+    // connection limits stops.
+    canceled: Option<oneshot::Receiver<()>>,
+    #[cfg(feature = "runtime")]
+    timeout: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+enum AcquireState<T> {
+    // `None` if the pool has no connection limits.
+    Ready(Option<Permit<T>>),
+    Waiting(oneshot::Receiver<Permit<T>>),
+}
+
+impl<T> Acquire<T> {
+    /// Stop waiting for the connection limits, releasing the place in the
+    /// queue, once `canceled` receives a value.
+    ///
+    /// If `canceled` is dropped instead, the `Acquire` keeps waiting.
+    pub(super) fn cancel_on(mut self, canceled: oneshot::Receiver<()>) -> Acquire<T> {
+        self.canceled = Some(canceled);
+        self
+    }
+}
+
+impl<T> Future for Acquire<T> {
+    type Output = crate::Result<Option<Permit<T>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let me = &mut *self;
+        let rx = match me.state {
+            AcquireState::Ready(ref mut permit) => return Poll::Ready(Ok(permit.take())),
+            AcquireState::Waiting(ref mut rx) => rx,
+        };
+
+        if let Poll::Ready(result) = Pin::new(rx).poll(cx) {
+            return Poll::Ready(
+                result
+                    .map(Some)
+                    .map_err(|_canceled| crate::Error::new_canceled().with("pool closed")),
+            );
+        }
+
+        if let Some(ref mut canceled) = me.canceled {
+            if let Poll::Ready(result) = Pin::new(canceled).poll(cx) {
+                me.canceled = None;
+                if result.is_ok() {
+                    trace!("connection no longer wanted, leaving the connection queue");
+                    // Dropping the receiver releases the place in the queue.
+                    me.state = AcquireState::Ready(None);
+                    return Poll::Ready(Err(
+                        crate::Error::new_canceled().with("connection no longer wanted")
+                    ));
+                }
+            }
+        }
+
+        #[cfg(feature = "runtime")]
+        {
+            if let Some(ref mut timeout) = me.timeout {
+                ready!(timeout.as_mut().poll(cx));
+                return Poll::Ready(Err(crate::Error::new_pool_timeout()));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
 struct Expiration(Option<Duration>);
 
 impl Expiration {
@@ -746,7 +1216,7 @@ impl<T: Poolable + 'static> Future for IdleTask<T> {
             ready!(this.interval.as_mut().poll_next(cx));
 
             if let Some(inner) = this.pool.upgrade() {
-                if let Ok(mut inner) = inner.lock() {
+                if let Ok(mut inner) = Locked::lock(&inner) {
                     trace!("idle interval checking for expired");
                     inner.clear_expired();
                     continue;
@@ -757,6 +1227,183 @@ impl<T: Poolable + 'static> Future for IdleTask<T> {
     }
 }
 
+/// Records an event for the hook, if there is one.
+fn emit(event_hook: Option<&EventHook>, events: &mut Vec<PoolEvent>, key: &Key, kind: EventKind) {
+    if event_hook.is_some() {
+        events.push(PoolEvent {
+            kind,
+            key: key.clone(),
+        });
+    }
+}
+
+/// A locked `PoolInner`.
+///
+/// The events recorded while the pool is locked are passed to the hook
+/// once it is unlocked, so that the hook doesn't hold up the pool, and
+/// can use the pool itself.
+struct Locked<'a, T>(Option<MutexGuard<'a, PoolInner<T>>>);
+
+impl<'a, T> Locked<'a, T> {
+    fn lock(
+        pool: &'a Mutex<PoolInner<T>>,
+    ) -> Result<Locked<'a, T>, PoisonError<MutexGuard<'a, PoolInner<T>>>> {
+        pool.lock().map(|inner| Locked(Some(inner)))
+    }
+}
+
+impl<'a, T> Deref for Locked<'a, T> {
+    type Target = PoolInner<T>;
+    fn deref(&self) -> &PoolInner<T> {
+        self.0.as_ref().expect("locked")
+    }
+}
+
+impl<'a, T> DerefMut for Locked<'a, T> {
+    fn deref_mut(&mut self) -> &mut PoolInner<T> {
+        self.0.as_mut().expect("locked")
+    }
+}
+
+impl<'a, T> Drop for Locked<'a, T> {
+    fn drop(&mut self) {
+        let mut inner = match self.0.take() {
+            Some(inner) => inner,
+            None => return,
+        };
+        if inner.events.is_empty() {
+            return;
+        }
+        let events = mem::take(&mut inner.events);
+        let event_hook = inner.event_hook.clone();
+        drop(inner);
+
+        if let Some(hook) = event_hook {
+            for event in &events {
+                (hook.0)(event);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EventHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventHook").finish()
+    }
+}
+
+// ===== impl PoolStats =====
+
+impl PoolStats {
+    /// Returns the connections to each host in the pool, in no particular
+    /// order.
+    pub fn hosts(&self) -> &[PoolHostStats] {
+        &self.hosts
+    }
+
+    /// Returns the number of connections given to requests.
+    pub fn checkouts(&self) -> u64 {
+        self.checkouts
+    }
+
+    /// Returns the number of connections given to requests that had already
+    /// been used by an earlier request.
+    pub fn reused(&self) -> u64 {
+        self.reused
+    }
+
+    /// Returns the share of connections given to requests that were reused,
+    /// from `0.0` to `1.0`.
+    pub fn reuse_rate(&self) -> f64 {
+        if self.checkouts == 0 {
+            0.0
+        } else {
+            self.reused as f64 / self.checkouts as f64
+        }
+    }
+}
+
+impl PoolHostStats {
+    /// Returns the scheme of the connections.
+    pub fn scheme(&self) -> &Scheme {
+        &self.key.0
+    }
+
+    /// Returns the authority the connections are to.
+    pub fn authority(&self) -> &Authority {
+        &self.key.1
+    }
+
+    /// Returns the number of idle connections.
+    ///
+    /// An HTTP/2 connection, which can be shared by many requests, stays
+    /// idle while it is in use.
+    pub fn idle(&self) -> usize {
+        self.idle
+    }
+
+    /// Returns the number of HTTP/1 connections checked out by requests.
+    ///
+    /// HTTP/2 connections are counted as idle instead.
+    pub fn in_use(&self) -> usize {
+        self.in_use
+    }
+
+    /// Returns the number of connections being established.
+    pub fn connecting(&self) -> usize {
+        self.connecting
+    }
+
+    /// Returns the number of requests waiting for a connection.
+    pub fn waiting(&self) -> usize {
+        self.waiting
+    }
+}
+
+// ===== impl PoolEvent =====
+
+impl PoolEvent {
+    /// Returns the scheme of the connection.
+    pub fn scheme(&self) -> &Scheme {
+        &self.key.0
+    }
+
+    /// Returns the authority the connection is to.
+    pub fn authority(&self) -> &Authority {
+        &self.key.1
+    }
+
+    /// Returns true if a new connection was established.
+    pub fn is_connect(&self) -> bool {
+        self.kind == EventKind::Connect
+    }
+
+    /// Returns true if a connection was given to a request.
+    pub fn is_checkout(&self) -> bool {
+        self.kind == EventKind::Checkout
+    }
+
+    /// Returns true if a connection was given to a request after being used
+    /// by an earlier one.
+    ///
+    /// This follows the checkout event of the same request.
+    pub fn is_reuse(&self) -> bool {
+        self.kind == EventKind::Reuse
+    }
+
+    /// Returns true if an idle connection was closed after the idle timeout.
+    pub fn is_idle_expired(&self) -> bool {
+        self.kind == EventKind::IdleExpired
+    }
+
+    /// Returns true if an idle connection was removed from the pool, because
+    /// it was closed, there were too many idle connections, or to make room
+    /// under the connection limits.
+    pub fn is_evict(&self) -> bool {
+        self.kind == EventKind::Evict
+    }
+}
+
 impl<T> WeakOpt<T> {
     fn none() -> Self {
         WeakOpt(None)
@@ -776,8 +1423,14 @@ mod tests {
     use std::task::Poll;
     use std::time::Duration;
 
-    use super::{Connecting, Key, Pool, Poolable, Reservation, WeakOpt};
+    use std::sync::{Arc, Mutex};
+
+    use super::{
+        Connecting, EventHook, EventKind, Key, Pool, PoolEvent, Poolable, Reservation, WeakOpt,
+    };
+    use crate::client::Ver;
     use crate::common::{task, Exec, Future, Pin};
+    use futures_channel::oneshot;
 
     /// Test unique reservations.
     #[derive(Debug, PartialEq, Eq)]
@@ -801,6 +1454,7 @@ mod tests {
         Connecting {
             key,
             pool: WeakOpt::none(),
+            counted: WeakOpt::none(),
         }
     }
 
@@ -817,6 +1471,11 @@ mod tests {
             super::Config {
                 idle_timeout: Some(Duration::from_millis(100)),
                 max_idle_per_host: max_idle,
+                max_per_host: None,
+                max_total: None,
+                #[cfg(feature = "runtime")]
+                queue_timeout: None,
+                event_hook: None,
             },
             &Exec::Default,
         );
@@ -919,6 +1578,11 @@ mod tests {
             super::Config {
                 idle_timeout: Some(Duration::from_millis(10)),
                 max_idle_per_host: std::usize::MAX,
+                max_per_host: None,
+                max_total: None,
+                #[cfg(feature = "runtime")]
+                queue_timeout: None,
+                event_hook: None,
             },
             &Exec::Default,
         );
@@ -989,6 +1653,265 @@ mod tests {
         assert!(pool.locked().waiters.get(&key).is_none());
     }
 
+    fn pool_limited_no_timer<T>(max_per_host: Option<usize>, max_total: Option<usize>) -> Pool<T> {
+        let pool = Pool::new(
+            super::Config {
+                idle_timeout: Some(Duration::from_millis(100)),
+                max_idle_per_host: std::usize::MAX,
+                max_per_host,
+                max_total,
+                #[cfg(feature = "runtime")]
+                queue_timeout: None,
+                event_hook: None,
+            },
+            &Exec::Default,
+        );
+        pool.no_timer();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_pool_max_connections_per_host() {
+        use futures_util::FutureExt;
+
+        let pool = pool_limited_no_timer::<Uniq<i32>>(Some(1), None);
+        let foo = host_key("foo");
+
+        let permit = pool.acquire(&foo).await.unwrap();
+        assert!(permit.is_some());
+
+        let mut waiting = pool.acquire(&foo);
+        assert!((&mut waiting).now_or_never().is_none());
+        // other hosts are still free to connect
+        assert!(pool.acquire(&host_key("bar")).await.unwrap().is_some());
+
+        drop(permit);
+        assert!(waiting.await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pool_max_connections_wakes_in_order() {
+        use futures_util::FutureExt;
+
+        let pool = pool_limited_no_timer::<Uniq<i32>>(None, Some(1));
+        let permit = pool.acquire(&host_key("foo")).await.unwrap();
+
+        let mut first = pool.acquire(&host_key("bar"));
+        let mut second = pool.acquire(&host_key("baz"));
+        assert!((&mut first).now_or_never().is_none());
+        assert!((&mut second).now_or_never().is_none());
+
+        drop(permit);
+        let permit = (&mut first)
+            .now_or_never()
+            .expect("first waiter woken")
+            .unwrap();
+        assert!(permit.is_some());
+        assert!((&mut second).now_or_never().is_none());
+
+        drop(permit);
+        assert!(second
+            .now_or_never()
+            .expect("second waiter woken")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_pool_max_connections_evicts_idle() {
+        use futures_util::FutureExt;
+
+        let pool = pool_limited_no_timer(None, Some(1));
+        let foo = host_key("foo");
+
+        let _permit = pool.acquire(&foo).now_or_never().unwrap().unwrap();
+        pool.pooled(c(foo.clone()), Uniq(41));
+        assert!(pool.locked().idle.contains_key(&foo));
+
+        let _waiting = pool.acquire(&host_key("bar"));
+        assert!(!pool.locked().idle.contains_key(&foo));
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn test_pool_queue_timeout() {
+        let pool = Pool::<Uniq<i32>>::new(
+            super::Config {
+                idle_timeout: Some(Duration::from_millis(100)),
+                max_idle_per_host: std::usize::MAX,
+                max_per_host: None,
+                max_total: Some(1),
+                queue_timeout: Some(Duration::from_millis(10)),
+                event_hook: None,
+            },
+            &Exec::Default,
+        );
+        pool.no_timer();
+        let key = host_key("foo");
+
+        let _permit = pool.acquire(&key).await.unwrap();
+        let err = match pool.acquire(&key).await {
+            Ok(_) => panic!("acquire should time out"),
+            Err(err) => err,
+        };
+        assert!(err.is_timeout());
+        assert!(err.is_pool_timeout());
+    }
+
+    #[tokio::test]
+    async fn test_pool_acquire_cancel_releases_queue() {
+        use futures_util::FutureExt;
+
+        let pool = pool_limited_no_timer::<Uniq<i32>>(None, Some(1));
+        let permit = pool.acquire(&host_key("foo")).await.unwrap();
+
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let mut first = pool.acquire(&host_key("bar")).cancel_on(cancel_rx);
+        let mut second = pool.acquire(&host_key("baz"));
+        assert!((&mut first).now_or_never().is_none());
+        assert!((&mut second).now_or_never().is_none());
+
+        cancel_tx.send(()).unwrap();
+        let err = match (&mut first).now_or_never() {
+            Some(Err(err)) => err,
+            _ => panic!("first waiter should be canceled"),
+        };
+        assert!(err.is_canceled());
+        drop(first);
+
+        // the freed connection goes to the second waiter instead
+        drop(permit);
+        assert!(second
+            .now_or_never()
+            .expect("second waiter woken")
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pool_stats() {
+        let pool = pool_no_timer();
+        let key = host_key("foo");
+
+        let mut checkout = pool.checkout(key.clone());
+        assert!(PollOnce(&mut checkout).await.is_none());
+        let connecting = pool.connecting(&key, Ver::Auto).expect("connecting");
+
+        let stats = pool.stats();
+        assert_eq!(stats.hosts().len(), 1);
+        assert_eq!(stats.hosts()[0].connecting(), 1);
+        assert_eq!(stats.hosts()[0].waiting(), 1);
+
+        drop(checkout);
+        let pooled = pool.pooled(connecting, Uniq(41));
+        pool.checked_out(&pooled);
+
+        let stats = pool.stats();
+        assert_eq!(stats.hosts()[0].connecting(), 0);
+        assert_eq!(stats.hosts()[0].in_use(), 1);
+        assert_eq!(stats.hosts()[0].waiting(), 0);
+
+        drop(pooled);
+        let pooled = pool.checkout(key).await.expect("idle connection");
+        pool.checked_out(&pooled);
+        drop(pooled);
+
+        let stats = pool.stats();
+        assert_eq!(stats.hosts()[0].idle(), 1);
+        assert_eq!(stats.hosts()[0].in_use(), 0);
+        assert_eq!(stats.checkouts(), 2);
+        assert_eq!(stats.reused(), 1);
+        assert!((stats.reuse_rate() - 0.5).abs() < std::f64::EPSILON);
+    }
+
+    #[test]
+    fn test_pool_event_hook() {
+        use futures_util::FutureExt;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events2 = events.clone();
+        let pool = Pool::new(
+            super::Config {
+                idle_timeout: Some(Duration::from_millis(100)),
+                max_idle_per_host: 1,
+                max_per_host: None,
+                max_total: None,
+                #[cfg(feature = "runtime")]
+                queue_timeout: None,
+                event_hook: Some(EventHook(Arc::new(move |event: &PoolEvent| {
+                    events2.lock().unwrap().push(event.kind);
+                }))),
+            },
+            &Exec::Default,
+        );
+        pool.no_timer();
+        let key = host_key("foo");
+
+        let first = pool.pooled(c(key.clone()), Uniq(41));
+        pool.checked_out(&first);
+        let second = pool.pooled(c(key.clone()), Uniq(5));
+        drop(first);
+        // over the max idle
+        drop(second);
+
+        let reused = pool.checkout(key).now_or_never().unwrap().unwrap();
+        pool.checked_out(&reused);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                EventKind::Connect,
+                EventKind::Checkout,
+                EventKind::Connect,
+                EventKind::Evict,
+                EventKind::Checkout,
+                EventKind::Reuse,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pool_event_hook_called_unlocked() {
+        let pool_ref = Arc::new(Mutex::new(None::<Pool<Uniq<i32>>>));
+        let pool_ref2 = pool_ref.clone();
+        let idle = Arc::new(Mutex::new(Vec::new()));
+        let idle2 = idle.clone();
+        let pool = Pool::new(
+            super::Config {
+                idle_timeout: Some(Duration::from_millis(100)),
+                max_idle_per_host: 1,
+                max_per_host: None,
+                max_total: None,
+                #[cfg(feature = "runtime")]
+                queue_timeout: None,
+                event_hook: Some(EventHook(Arc::new(move |_event: &PoolEvent| {
+                    // the hook can use the pool, which would deadlock if it
+                    // were called with the pool locked
+                    if let Some(ref pool) = *pool_ref2.lock().unwrap() {
+                        let stats = pool.stats();
+                        idle2
+                            .lock()
+                            .unwrap()
+                            .push(stats.hosts().first().map_or(0, |host| host.idle()));
+                    }
+                }))),
+            },
+            &Exec::Default,
+        );
+        pool.no_timer();
+        *pool_ref.lock().unwrap() = Some(pool.clone());
+        let key = host_key("foo");
+
+        let first = pool.pooled(c(key.clone()), Uniq(41));
+        let second = pool.pooled(c(key), Uniq(5));
+        drop(first);
+        // over the max idle
+        drop(second);
+
+        assert_eq!(*idle.lock().unwrap(), vec![0, 0, 1]);
+        pool_ref.lock().unwrap().take();
+    }
+
     #[derive(Debug)]
     struct CanClose {
         val: i32,
@@ -0,0 +1,643 @@
+//! Following redirects with the `Client`.
+//!
+//! By default, the [`Client`](super::Client) returns `3xx` responses to the
+//! caller as they are. Setting a [`Policy`](Policy) with
+//! [`Builder::redirect_policy`](super::Builder::redirect_policy) makes it
+//! follow them instead.
+//!
+//! When a redirect is followed:
+//!
+//! - `301` and `302` responses change a `POST` into a `GET` without a body.
+//! - `303` responses change any method other than `HEAD` into a `GET`
+//!   without a body.
+//! - `307` and `308` responses keep the method and body. This requires the
+//!   body to be sent again, so these are only followed if the request body
+//!   is a [`Body`](crate::Body) created from a full buffer, such as with
+//!   `Body::from` or `Body::empty`.
+//! - Sensitive headers, such as `Authorization` and `Cookie`, are removed
+//!   when the redirect leads to a different origin.
+//! - The extensions of the request aren't sent again, since they can't be
+//!   cloned. Redirected requests are sent with empty extensions.
+//!
+//! The response that is returned includes a [`History`](History) in its
+//! extensions, with the final `Uri` and the ones that were redirected from.
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use http::header::{
+    HeaderMap, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HOST,
+    LOCATION, PROXY_AUTHORIZATION, TRANSFER_ENCODING,
+};
+use http::{Method, Request, Response, StatusCode, Uri, Version};
+
+use crate::body::Body;
+
+/// A policy describing which redirects the `Client` should follow.
+///
+/// # Example
+///
+/// ```
+/// use hyper::client::redirect::Policy;
+///
+/// let policy = Policy::limited(5)
+///     .same_origin(true)
+///     .filter(|attempt| attempt.uri().path() != "/logout");
+/// ```
+#[derive(Clone)]
+pub struct Policy {
+    max_redirects: usize,
+    same_origin: bool,
+    filter: Option<Filter>,
+}
+
+type Filter = Arc<dyn Fn(&Attempt<'_>) -> bool + Send + Sync>;
+
+/// A redirect the `Client` is about to follow.
+///
+/// Passed to the filter of a [`Policy`](Policy).
+#[derive(Debug)]
+pub struct Attempt<'a> {
+    status: StatusCode,
+    next: &'a Uri,
+    previous: &'a [Uri],
+}
+
+/// The redirects that were followed to get a response.
+///
+/// Added to the extensions of responses from a `Client` that has a redirect
+/// [`Policy`](Policy).
+#[derive(Clone, Debug)]
+pub struct History {
+    uri: Uri,
+    previous: Vec<Uri>,
+}
+
+/// The parts of a request that are needed to send it again.
+pub(super) struct Replay<B> {
+    method: Method,
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap,
+    body: Option<B>,
+}
+
+// ===== impl Policy =====
+
+impl Policy {
+    /// Create a `Policy` that follows up to `max` redirects.
+    ///
+    /// If a response would need another redirect after that, the request
+    /// fails with an error.
+    pub fn limited(max: usize) -> Policy {
+        Policy {
+            max_redirects: max,
+            same_origin: false,
+            filter: None,
+        }
+    }
+
+    /// Set whether to only follow redirects to the same origin.
+    ///
+    /// The origin is the scheme, host and port of the request `Uri`. A
+    /// redirect to a different origin is returned as the response.
+    ///
+    /// Default is `false`.
+    pub fn same_origin(mut self, val: bool) -> Policy {
+        self.same_origin = val;
+        self
+    }
+
+    /// Set a function to decide whether to follow a redirect.
+    ///
+    /// If the function returns `false`, the redirect is returned as the
+    /// response.
+    pub fn filter<F>(mut self, filter: F) -> Policy
+    where
+        F: Fn(&Attempt<'_>) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Check a response against this policy, returning the request to send
+    /// next if it should be followed.
+    pub(super) fn redirect<B: 'static>(
+        &self,
+        res: &Response<Body>,
+        replay: Replay<B>,
+        previous: &[Uri],
+    ) -> crate::Result<Option<Request<B>>> {
+        let status = res.status();
+        let method = match status {
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND if replay.method == Method::POST => {
+                Method::GET
+            }
+            StatusCode::SEE_OTHER if replay.method != Method::HEAD => Method::GET,
+            StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT => replay.method.clone(),
+            _ => return Ok(None),
+        };
+
+        let next = match res
+            .headers()
+            .get(LOCATION)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|loc| resolve(&replay.uri, loc))
+        {
+            Some(next) => next,
+            None => {
+                debug!("redirect {} has no valid location", status);
+                return Ok(None);
+            }
+        };
+
+        let cross_origin = !is_same_origin(&replay.uri, &next);
+        if cross_origin && self.same_origin {
+            debug!("not following redirect to other origin: {}", next);
+            return Ok(None);
+        }
+
+        if let Some(ref filter) = self.filter {
+            let attempt = Attempt {
+                status,
+                next: &next,
+                previous,
+            };
+            if !filter(&attempt) {
+                debug!("redirect policy refused redirect to {}", next);
+                return Ok(None);
+            }
+        }
+
+        // `previous` includes the `Uri` being redirected from.
+        if previous.len() > self.max_redirects {
+            debug!("too many redirects, max = {}", self.max_redirects);
+            return Err(crate::Error::new_too_many_redirects());
+        }
+
+        let mut headers = replay.headers;
+        let body = if method != replay.method {
+            for name in &[
+                CONTENT_LENGTH,
+                CONTENT_TYPE,
+                CONTENT_ENCODING,
+                TRANSFER_ENCODING,
+            ] {
+                headers.remove(name);
+            }
+            empty_body()
+        } else {
+            replay.body
+        };
+        let body = match body {
+            Some(body) => body,
+            None => {
+                debug!("not following redirect, request body can't be sent again");
+                return Ok(None);
+            }
+        };
+
+        if cross_origin {
+            for name in &[AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, HOST] {
+                headers.remove(name);
+            }
+        }
+
+        trace!("following redirect {} to {}", status, next);
+        let mut req = Request::new(body);
+        *req.method_mut() = method;
+        *req.uri_mut() = next;
+        *req.version_mut() = replay.version;
+        *req.headers_mut() = headers;
+        Ok(Some(req))
+    }
+}
+
+impl Default for Policy {
+    /// Follow up to 10 redirects.
+    fn default() -> Policy {
+        Policy::limited(10)
+    }
+}
+
+impl fmt::Debug for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Policy")
+            .field("max_redirects", &self.max_redirects)
+            .field("same_origin", &self.same_origin)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+// ===== impl Attempt =====
+
+impl Attempt<'_> {
+    /// Get the status code of the redirect response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the `Uri` the redirect leads to.
+    pub fn uri(&self) -> &Uri {
+        self.next
+    }
+
+    /// Get the `Uri`s that were already redirected from, oldest first.
+    ///
+    /// This includes the `Uri` of the redirect response itself.
+    pub fn previous(&self) -> &[Uri] {
+        self.previous
+    }
+}
+
+// ===== impl History =====
+
+impl History {
+    pub(super) fn new(uri: Uri, previous: Vec<Uri>) -> History {
+        History { uri, previous }
+    }
+
+    /// Get the `Uri` of the final request, which returned the response.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Get the `Uri`s that were redirected from, oldest first.
+    ///
+    /// This is empty if no redirects were followed.
+    pub fn previous(&self) -> &[Uri] {
+        &self.previous
+    }
+}
+
+// ===== impl Replay =====
+
+impl<B: 'static> Replay<B> {
+    pub(super) fn new(req: &Request<B>) -> Replay<B> {
+        Replay {
+            method: req.method().clone(),
+            uri: req.uri().clone(),
+            version: req.version(),
+            headers: req.headers().clone(),
+            body: try_clone_body(req.body()),
+        }
+    }
+}
+
+/// Only a `Body` can be cloned, and only if it was created from a full
+/// buffer. Other body types are never sent again.
+fn try_clone_body<B: 'static>(body: &B) -> Option<B> {
+    let body = (body as &dyn Any).downcast_ref::<Body>()?.try_clone();
+    cast_body(body)
+}
+
+pub(super) fn empty_body<B: 'static>() -> Option<B> {
+    cast_body(Some(Body::empty()))
+}
+
+fn cast_body<B: 'static>(mut body: Option<Body>) -> Option<B> {
+    (&mut body as &mut dyn Any)
+        .downcast_mut::<Option<B>>()?
+        .take()
+}
+
+/// Resolve the value of a `Location` header against the request `Uri`, as
+/// described in [RFC 3986 section 5.2](https://tools.ietf.org/html/rfc3986#section-5.2).
+fn resolve(base: &Uri, location: &str) -> Option<Uri> {
+    // The fragment isn't sent in requests.
+    let location = location.split('#').next()?;
+    let reference = Reference::parse(location);
+    let base_authority = base.authority()?.as_str();
+
+    let (scheme, authority, path, query) = match reference {
+        Reference {
+            scheme: Some(scheme),
+            authority,
+            path,
+            query,
+        } => (scheme, authority, remove_dot_segments(path), query),
+        Reference {
+            authority: Some(authority),
+            path,
+            query,
+            ..
+        } => (
+            base.scheme_str()?,
+            Some(authority),
+            remove_dot_segments(path),
+            query,
+        ),
+        Reference {
+            path: "", query, ..
+        } => (
+            base.scheme_str()?,
+            Some(base_authority),
+            base.path().to_owned(),
+            query.or_else(|| base.query()),
+        ),
+        Reference { path, query, .. } => {
+            let path = if path.starts_with('/') {
+                remove_dot_segments(path)
+            } else {
+                // Merge with the directory of the base path.
+                let base_path = base.path();
+                let dir = &base_path[..base_path.rfind('/').map_or(0, |i| i + 1)];
+                remove_dot_segments(&format!("{}{}", dir, path))
+            };
+            (base.scheme_str()?, Some(base_authority), path, query)
+        }
+    };
+
+    let mut uri = format!("{}://{}{}", scheme, authority?, path);
+    if let Some(query) = query {
+        uri.push('?');
+        uri.push_str(query);
+    }
+    let uri = uri.parse::<Uri>().ok()?;
+
+    match uri.scheme_str() {
+        Some("http") | Some("https") if uri.authority().is_some() => Some(uri),
+        _ => None,
+    }
+}
+
+/// The components of a URI reference, without its fragment.
+struct Reference<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+}
+
+impl<'a> Reference<'a> {
+    fn parse(mut s: &'a str) -> Reference<'a> {
+        let mut scheme = None;
+        if let Some(i) = s.find(&[':', '/', '?'][..]) {
+            let is_scheme = s.as_bytes()[i] == b':'
+                && s[..i].starts_with(|c: char| c.is_ascii_alphabetic())
+                && s[..i]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+            if is_scheme {
+                scheme = Some(&s[..i]);
+                s = &s[i + 1..];
+            }
+        }
+
+        let mut authority = None;
+        if let Some(rest) = s.strip_prefix("//") {
+            let end = rest.find(&['/', '?'][..]).unwrap_or(rest.len());
+            authority = Some(&rest[..end]);
+            s = &rest[end..];
+        }
+
+        let (path, query) = match s.find('?') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        Reference {
+            scheme,
+            authority,
+            path,
+            query,
+        }
+    }
+}
+
+/// Remove the `.` and `..` segments of an absolute path.
+fn remove_dot_segments(path: &str) -> String {
+    let segments = path.split('/').collect::<Vec<_>>();
+    let mut output = Vec::with_capacity(segments.len());
+    for (i, &segment) in segments.iter().enumerate() {
+        let is_last = i == segments.len() - 1;
+        match segment {
+            "." | ".." => {
+                // Never remove the empty segment before the leading `/`.
+                if segment == ".." && output.len() > 1 {
+                    output.pop();
+                }
+                // A trailing dot segment leaves a trailing `/`.
+                if is_last {
+                    output.push("");
+                }
+            }
+            _ => output.push(segment),
+        }
+    }
+    let path = output.join("/");
+    if path.starts_with('/') {
+        path
+    } else {
+        format!("/{}", path)
+    }
+}
+
+fn is_same_origin(a: &Uri, b: &Uri) -> bool {
+    a.scheme() == b.scheme()
+        && a.host().map(str::to_ascii_lowercase) == b.host().map(str::to_ascii_lowercase)
+        && port_or_default(a) == port_or_default(b)
+}
+
+fn port_or_default(uri: &Uri) -> Option<u16> {
+    uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some("http") => Some(80),
+        Some("https") => Some(443),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::{AUTHORIZATION, CONTENT_LENGTH, COOKIE, LOCATION};
+    use http::{Method, Request, Response, StatusCode, Uri};
+
+    use super::{is_same_origin, resolve, Policy, Replay};
+    use crate::body::{Body, HttpBody};
+
+    fn redirect_to(status: u16, location: &str) -> Response<Body> {
+        Response::builder()
+            .status(status)
+            .header(LOCATION, location)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn post(uri: &str) -> Request<Body> {
+        Request::post(uri)
+            .header(CONTENT_LENGTH, "5")
+            .header(AUTHORIZATION, "secret")
+            .header(COOKIE, "secret")
+            .body(Body::from("hello"))
+            .unwrap()
+    }
+
+    fn follow<B: 'static>(
+        policy: &Policy,
+        res: &Response<Body>,
+        req: &Request<B>,
+    ) -> Option<Request<B>> {
+        policy
+            .redirect(res, Replay::new(req), &[req.uri().clone()])
+            .expect("redirect")
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base: Uri = "http://example.com/a/b?q".parse().unwrap();
+        let cases = &[
+            ("https://other.com/x", "https://other.com/x"),
+            ("//other.com/x", "http://other.com/x"),
+            ("/x?y", "http://example.com/x?y"),
+            ("c", "http://example.com/a/c"),
+            ("c#frag", "http://example.com/a/c"),
+        ];
+        for &(location, expected) in cases {
+            assert_eq!(resolve(&base, location).unwrap(), expected, "{}", location);
+        }
+        assert!(resolve(&base, "ftp://example.com/").is_none());
+    }
+
+    #[test]
+    fn test_resolve_rfc3986_examples() {
+        // From RFC 3986 section 5.4, with http URIs.
+        let base: Uri = "http://a/b/c/d;p?q".parse().unwrap();
+        let cases = &[
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g/"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q"),
+            ("g;x?y#s", "http://a/b/c/g;x?y"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("../../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("./g/.", "http://a/b/c/g/"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+            ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+            ("g?y/./x", "http://a/b/c/g?y/./x"),
+            ("http://a/b/../c", "http://a/c"),
+            ("//other/./x/../y?z", "http://other/y?z"),
+        ];
+        for &(location, expected) in cases {
+            assert_eq!(resolve(&base, location).unwrap(), expected, "{}", location);
+        }
+    }
+
+    #[test]
+    fn test_same_origin() {
+        let a: Uri = "http://example.com/a".parse().unwrap();
+        assert!(is_same_origin(
+            &a,
+            &"http://EXAMPLE.com:80/b".parse().unwrap()
+        ));
+        assert!(!is_same_origin(
+            &a,
+            &"https://example.com/a".parse().unwrap()
+        ));
+        assert!(!is_same_origin(
+            &a,
+            &"http://example.com:8080/a".parse().unwrap()
+        ));
+        assert!(!is_same_origin(&a, &"http://other.com/a".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_method_rewriting() {
+        let policy = Policy::default();
+        for &status in &[301, 302, 303] {
+            let req = post("http://example.com/a");
+            let next = follow(&policy, &redirect_to(status, "/b"), &req).unwrap();
+            assert_eq!(next.method(), Method::GET, "{}", status);
+            assert_eq!(next.uri(), "http://example.com/b");
+            assert!(next.headers().get(CONTENT_LENGTH).is_none());
+            assert_eq!(next.headers()[AUTHORIZATION], "secret");
+        }
+
+        for &status in &[307, 308] {
+            let req = post("http://example.com/a");
+            let next = follow(&policy, &redirect_to(status, "/b"), &req).unwrap();
+            assert_eq!(next.method(), Method::POST, "{}", status);
+            assert_eq!(next.headers()[CONTENT_LENGTH], "5");
+            assert_eq!(next.body().size_hint().exact(), Some(5), "{}", status);
+        }
+
+        let req = Request::head("http://example.com/a")
+            .body(Body::empty())
+            .unwrap();
+        let next = follow(&policy, &redirect_to(303, "/b"), &req).unwrap();
+        assert_eq!(next.method(), Method::HEAD);
+    }
+
+    #[test]
+    fn test_not_followed() {
+        let policy = Policy::default();
+        let req = post("http://example.com/a");
+        assert!(follow(&policy, &redirect_to(300, "/b"), &req).is_none());
+        let res = Response::builder().status(302).body(Body::empty()).unwrap();
+        assert!(follow(&policy, &res, &req).is_none());
+
+        // a streaming body can't be sent again
+        let (_tx, body) = Body::channel();
+        let req = Request::post("http://example.com/a").body(body).unwrap();
+        assert!(follow(&policy, &redirect_to(307, "/b"), &req).is_none());
+
+        // bodies other than `Body` can't be replaced with an empty one
+        let req = Request::post("http://example.com/a").body(()).unwrap();
+        assert!(follow(&policy, &redirect_to(303, "/b"), &req).is_none());
+    }
+
+    #[test]
+    fn test_cross_origin() {
+        let req = post("http://example.com/a");
+        let res = redirect_to(307, "http://other.com/b");
+        let next = follow(&Policy::default(), &res, &req).unwrap();
+        assert!(next.headers().get(AUTHORIZATION).is_none());
+        assert!(next.headers().get(COOKIE).is_none());
+
+        let policy = Policy::default().same_origin(true);
+        assert!(follow(&policy, &res, &req).is_none());
+    }
+
+    #[test]
+    fn test_filter_and_limit() {
+        let req = post("http://example.com/a");
+        let res = redirect_to(302, "/logout");
+        let policy = Policy::default().filter(|attempt| {
+            assert_eq!(attempt.status(), StatusCode::FOUND);
+            assert_eq!(attempt.previous().len(), 1);
+            attempt.uri().path() != "/logout"
+        });
+        assert!(follow(&policy, &res, &req).is_none());
+
+        let err = Policy::limited(1)
+            .redirect(
+                &res,
+                Replay::new(&req),
+                &[req.uri().clone(), req.uri().clone()],
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "too many redirects");
+    }
+}
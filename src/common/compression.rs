@@ -0,0 +1,844 @@
+//! Streaming `Content-Encoding` support, for decoding responses in the
+//! `Client` and encoding responses with `service::compress`.
+use std::cmp;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+
+use brotli::{
+    BrotliDecompressStream, BrotliResult, BrotliState, CompressorWriter, HeapAlloc, HuffmanCode,
+};
+use bytes::{Buf, Bytes};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::{Compression, Crc, Decompress, FlushDecompress, Status};
+use http::header::{
+    HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY,
+};
+use http::{Response, StatusCode};
+use http_body::SizeHint;
+use pin_project::pin_project;
+
+use super::{task, Pin, Poll};
+use crate::body::{Body, HttpBody};
+
+/// The value of `Accept-Encoding` sent by the `Client`.
+pub(crate) const ACCEPT_ENCODING_VALUE: &str = "gzip, deflate, br";
+
+const BUFFER_SIZE: usize = 4096;
+// Lower than the maximum quality of 11, which is too slow to use for
+// responses that are compressed as they are sent.
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_LGWIN: u32 = 22;
+// The most decoded output returned from a single poll, however well the
+// input compresses.
+const DECODE_BUFFER_SIZE: usize = 16 * 1024;
+// Longer than the extra field of a gzip header can be, the file name and
+// comment are otherwise unbounded.
+const GZIP_MAX_HEADER: usize = 128 * 1024;
+
+const GZIP_FHCRC: u8 = 0x02;
+const GZIP_FEXTRA: u8 = 0x04;
+const GZIP_FNAME: u8 = 0x08;
+const GZIP_FCOMMENT: u8 = 0x10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+enum Codec {
+    Decoder(Decoder),
+    GzipEncoder(GzEncoder<Vec<u8>>),
+    DeflateEncoder(ZlibEncoder<Vec<u8>>),
+    BrotliEncoder(Box<CompressorWriter<Vec<u8>>>),
+}
+
+/// Decodes into a buffer of `DECODE_BUFFER_SIZE`, so that a small body
+/// can't be inflated into one huge chunk.
+struct Decoder {
+    kind: DecoderKind,
+    // Whether the last output filled the buffer, and there may be more.
+    full: bool,
+    // Whether the end of the encoded stream has been seen.
+    done: bool,
+}
+
+enum DecoderKind {
+    Gzip(Box<GzipDecoder>),
+    Deflate(Box<Decompress>),
+    Brotli(Box<BrotliState<HeapAlloc<u8>, HeapAlloc<u32>, HeapAlloc<HuffmanCode>>>),
+}
+
+struct GzipDecoder {
+    state: GzipState,
+    inflate: Decompress,
+    crc: Crc,
+    // The header or the trailer, until all of it has been received.
+    buf: Vec<u8>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum GzipState {
+    Header,
+    Body,
+    Trailer,
+}
+
+/// A body decoded or encoded with a `Codec`.
+#[pin_project]
+pub(crate) struct Coded<B> {
+    #[pin]
+    body: B,
+    // `None` once the codec has been finished, or has failed.
+    codec: Option<Codec>,
+    // Input the codec hasn't consumed yet.
+    input: Bytes,
+}
+
+/// A response body that is either encoded, or left as it is.
+#[pin_project(project = MaybeCodedProj)]
+pub(crate) enum MaybeCoded<B> {
+    Identity(#[pin] B),
+    Coded(Pin<Box<Coded<B>>>),
+}
+
+/// Add `Accept-Encoding` to a request, returning whether it was added.
+///
+/// If the request already has one, the caller asked for specific encodings,
+/// and the response is left as it is.
+pub(crate) fn set_accept_encoding(headers: &mut HeaderMap) -> bool {
+    if headers.contains_key(ACCEPT_ENCODING) {
+        return false;
+    }
+    headers.insert(
+        ACCEPT_ENCODING,
+        HeaderValue::from_static(ACCEPT_ENCODING_VALUE),
+    );
+    true
+}
+
+/// Decode the body of a response according to its `Content-Encoding`.
+pub(crate) fn decode_response(res: Response<Body>) -> Response<Body> {
+    let encoding = match res
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(Encoding::from_header)
+    {
+        Some(encoding) => encoding,
+        None => return res,
+    };
+    if res.body().is_end_stream() {
+        return res;
+    }
+
+    trace!("decoding response body with {:?}", encoding);
+    let (mut parts, body) = res.into_parts();
+    parts.headers.remove(CONTENT_ENCODING);
+    parts.headers.remove(CONTENT_LENGTH);
+    // Not `Body::wrap_stream`, which would drop the trailers.
+    let body = Body::boxed(Coded {
+        body,
+        codec: Some(Codec::decoder(encoding)),
+        input: Bytes::new(),
+    });
+    Response::from_parts(parts, body)
+}
+
+/// Pick the encoding to use for a response, from the `Accept-Encoding` of
+/// the request.
+pub(crate) fn preferred_encoding(headers: &HeaderMap) -> Option<Encoding> {
+    let mut preferred = None;
+    let mut preferred_q = 0.0;
+    let values = headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','));
+    for value in values {
+        let mut params = value.split(';');
+        let encoding = match params.next().map(str::trim) {
+            Some("*") => Encoding::Gzip,
+            Some(name) => match Encoding::from_name(name) {
+                Some(encoding) => encoding,
+                None => continue,
+            },
+            None => continue,
+        };
+        let q = params
+            .filter_map(|param| {
+                let param = param.trim();
+                let q = param
+                    .strip_prefix("q=")
+                    .or_else(|| param.strip_prefix("Q="))?;
+                q.parse::<f32>().ok()
+            })
+            .next()
+            .unwrap_or(1.0);
+        if q <= 0.0 {
+            continue;
+        }
+        let is_better = q > preferred_q
+            || (q == preferred_q && Some(encoding.rank()) > preferred.map(Encoding::rank));
+        if is_better {
+            preferred = Some(encoding);
+            preferred_q = q;
+        }
+    }
+    preferred
+}
+
+/// Encode the body of a response with `encoding`, if it should be.
+pub(crate) fn encode_response<B>(
+    res: Response<B>,
+    encoding: Option<Encoding>,
+) -> Response<MaybeCoded<B>>
+where
+    B: HttpBody,
+{
+    let (mut parts, body) = res.into_parts();
+    let skip = parts.status.is_informational()
+        || parts.status == StatusCode::NO_CONTENT
+        || parts.status == StatusCode::NOT_MODIFIED
+        || parts.status == StatusCode::PARTIAL_CONTENT
+        || parts.headers.contains_key(CONTENT_ENCODING)
+        || body.is_end_stream();
+
+    if !skip {
+        parts
+            .headers
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+        if let Some(encoding) = encoding {
+            trace!("encoding response body with {:?}", encoding);
+            parts.headers.remove(CONTENT_LENGTH);
+            parts.headers.insert(CONTENT_ENCODING, encoding.to_header());
+            let body = MaybeCoded::Coded(Box::pin(Coded {
+                body,
+                codec: Some(Codec::encoder(encoding)),
+                input: Bytes::new(),
+            }));
+            return Response::from_parts(parts, body);
+        }
+    }
+
+    // The body is passed through, so its length and trailers are kept.
+    Response::from_parts(parts, MaybeCoded::Identity(body))
+}
+
+// ===== impl Encoding =====
+
+impl Encoding {
+    fn from_name(name: &str) -> Option<Encoding> {
+        if name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip") {
+            Some(Encoding::Gzip)
+        } else if name.eq_ignore_ascii_case("deflate") {
+            Some(Encoding::Deflate)
+        } else if name.eq_ignore_ascii_case("br") {
+            Some(Encoding::Brotli)
+        } else {
+            None
+        }
+    }
+
+    /// Only a single encoding is decoded, `gzip, br` is left as it is.
+    fn from_header(val: &HeaderValue) -> Option<Encoding> {
+        Encoding::from_name(val.to_str().ok()?.trim())
+    }
+
+    fn to_header(self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+        })
+    }
+
+    /// When `Accept-Encoding` weights are equal, prefer the smaller output.
+    fn rank(self) -> u8 {
+        match self {
+            Encoding::Deflate => 0,
+            Encoding::Gzip => 1,
+            Encoding::Brotli => 2,
+        }
+    }
+}
+
+// ===== impl Codec =====
+
+impl Codec {
+    fn decoder(encoding: Encoding) -> Codec {
+        let kind = match encoding {
+            Encoding::Gzip => DecoderKind::Gzip(Box::new(GzipDecoder {
+                state: GzipState::Header,
+                inflate: Decompress::new(false),
+                crc: Crc::new(),
+                buf: Vec::new(),
+            })),
+            Encoding::Deflate => DecoderKind::Deflate(Box::new(Decompress::new(true))),
+            Encoding::Brotli => {
+                let mut state = BrotliState::new(
+                    HeapAlloc::new(0),
+                    HeapAlloc::new(0),
+                    HeapAlloc::new(HuffmanCode::default()),
+                );
+                // `br` is RFC 7932, with a window of at most 16MB.
+                state.large_window = false;
+                DecoderKind::Brotli(Box::new(state))
+            }
+        };
+        Codec::Decoder(Decoder {
+            kind,
+            full: false,
+            done: false,
+        })
+    }
+
+    fn encoder(encoding: Encoding) -> Codec {
+        match encoding {
+            Encoding::Gzip => {
+                Codec::GzipEncoder(GzEncoder::new(Vec::new(), Compression::default()))
+            }
+            Encoding::Deflate => {
+                Codec::DeflateEncoder(ZlibEncoder::new(Vec::new(), Compression::default()))
+            }
+            Encoding::Brotli => Codec::BrotliEncoder(Box::new(CompressorWriter::new(
+                Vec::new(),
+                BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_LGWIN,
+            ))),
+        }
+    }
+
+    /// Write some of `input`, returning any output that is ready.
+    ///
+    /// Decoders may leave some of `input` to be written again, once their
+    /// output has been taken. Encoders take all of it, and are flushed after
+    /// each chunk, so that streaming responses aren't held back waiting for
+    /// more input.
+    fn write(&mut self, input: &mut Bytes) -> io::Result<Bytes> {
+        if let Codec::Decoder(ref mut decoder) = *self {
+            return decoder.decode(input);
+        }
+        if input.is_empty() {
+            return Ok(Bytes::new());
+        }
+        let chunk = mem::take(input);
+        let out = match *self {
+            Codec::Decoder(_) => unreachable!("decoded above"),
+            Codec::GzipEncoder(ref mut w) => {
+                w.write_all(&chunk)?;
+                w.flush()?;
+                w.get_mut()
+            }
+            Codec::DeflateEncoder(ref mut w) => {
+                w.write_all(&chunk)?;
+                w.flush()?;
+                w.get_mut()
+            }
+            Codec::BrotliEncoder(ref mut w) => {
+                w.write_all(&chunk)?;
+                w.flush()?;
+                w.get_mut()
+            }
+        };
+        Ok(Bytes::from(mem::take(out)))
+    }
+
+    /// Finish the stream, returning the remaining output.
+    fn finish(self) -> io::Result<Bytes> {
+        let out = match self {
+            Codec::Decoder(decoder) => {
+                return if decoder.done {
+                    Ok(Bytes::new())
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "incomplete encoded stream",
+                    ))
+                };
+            }
+            Codec::GzipEncoder(w) => w.finish()?,
+            Codec::DeflateEncoder(w) => w.finish()?,
+            Codec::BrotliEncoder(w) => w.into_inner(),
+        };
+        Ok(Bytes::from(out))
+    }
+}
+
+// ===== impl Decoder =====
+
+impl Decoder {
+    fn decode(&mut self, input: &mut Bytes) -> io::Result<Bytes> {
+        if self.done {
+            // Anything after the end of the stream is ignored.
+            input.clear();
+            return Ok(Bytes::new());
+        }
+        if input.is_empty() && !self.full {
+            return Ok(Bytes::new());
+        }
+
+        let mut out = vec![0; DECODE_BUFFER_SIZE];
+        let remaining = input.len();
+        let (n, done) = match self.kind {
+            DecoderKind::Gzip(ref mut gzip) => gzip.decode(input, &mut out)?,
+            DecoderKind::Deflate(ref mut inflate) => inflate_into(inflate, input, &mut out)?,
+            DecoderKind::Brotli(ref mut state) => brotli_into(state, input, &mut out)?,
+        };
+        if n == 0 && !done && !input.is_empty() && input.len() == remaining {
+            return Err(invalid_data("encoded stream made no progress"));
+        }
+        self.full = n == out.len();
+        self.done = done;
+        out.truncate(n);
+        Ok(Bytes::from(out))
+    }
+}
+
+impl GzipDecoder {
+    fn decode(&mut self, input: &mut Bytes, out: &mut [u8]) -> io::Result<(usize, bool)> {
+        loop {
+            match self.state {
+                GzipState::Header => {
+                    self.buf.extend_from_slice(input);
+                    input.clear();
+                    match gzip_header_len(&self.buf)? {
+                        Some(len) => {
+                            *input = Bytes::from(self.buf.split_off(len));
+                            self.buf.clear();
+                            self.state = GzipState::Body;
+                        }
+                        None if self.buf.len() > GZIP_MAX_HEADER => {
+                            return Err(invalid_data("gzip header too long"));
+                        }
+                        None => return Ok((0, false)),
+                    }
+                }
+                GzipState::Body => {
+                    let (n, end) = inflate_into(&mut self.inflate, input, out)?;
+                    self.crc.update(&out[..n]);
+                    if end {
+                        self.state = GzipState::Trailer;
+                    }
+                    if n > 0 || !end {
+                        return Ok((n, false));
+                    }
+                }
+                GzipState::Trailer => {
+                    let len = cmp::min(8 - self.buf.len(), input.len());
+                    self.buf.extend_from_slice(&input.split_to(len));
+                    if self.buf.len() < 8 {
+                        return Ok((0, false));
+                    }
+                    let b = &self.buf;
+                    let crc = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                    let size = u32::from_le_bytes([b[4], b[5], b[6], b[7]]);
+                    if crc != self.crc.sum() || size != self.crc.amount() {
+                        return Err(invalid_data("gzip checksum mismatch"));
+                    }
+                    return Ok((0, true));
+                }
+            }
+        }
+    }
+}
+
+/// The length of the gzip header at the start of `buf`, if all of it is
+/// there.
+fn gzip_header_len(buf: &[u8]) -> io::Result<Option<usize>> {
+    let magic = [0x1f, 0x8b, 0x08];
+    let len = cmp::min(buf.len(), magic.len());
+    if buf[..len] != magic[..len] {
+        return Err(invalid_data("invalid gzip header"));
+    }
+    if buf.len() < 10 {
+        return Ok(None);
+    }
+
+    let flags = buf[3];
+    let mut pos = 10;
+    if flags & GZIP_FEXTRA != 0 {
+        if buf.len() < pos + 2 {
+            return Ok(None);
+        }
+        pos += 2 + u16::from_le_bytes([buf[pos], buf[pos + 1]]) as usize;
+    }
+    for &flag in &[GZIP_FNAME, GZIP_FCOMMENT] {
+        if flags & flag != 0 {
+            match buf
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+            {
+                Some(end) => pos += end + 1,
+                None => return Ok(None),
+            }
+        }
+    }
+    if flags & GZIP_FHCRC != 0 {
+        pos += 2;
+    }
+    Ok(if buf.len() < pos { None } else { Some(pos) })
+}
+
+/// Inflate some of `input` into `out`, returning the length of the output
+/// and whether the end of the stream was reached.
+fn inflate_into(
+    inflate: &mut Decompress,
+    input: &mut Bytes,
+    out: &mut [u8],
+) -> io::Result<(usize, bool)> {
+    let (total_in, total_out) = (inflate.total_in(), inflate.total_out());
+    let status = inflate
+        .decompress(input, out, FlushDecompress::None)
+        .map_err(invalid_data)?;
+    input.advance((inflate.total_in() - total_in) as usize);
+    let n = (inflate.total_out() - total_out) as usize;
+    Ok((n, status == Status::StreamEnd))
+}
+
+/// Decompress some of `input` into `out`, like `inflate_into`.
+fn brotli_into(
+    state: &mut BrotliState<HeapAlloc<u8>, HeapAlloc<u32>, HeapAlloc<HuffmanCode>>,
+    input: &mut Bytes,
+    out: &mut [u8],
+) -> io::Result<(usize, bool)> {
+    let mut available_in = input.len();
+    let mut input_offset = 0;
+    let mut available_out = out.len();
+    let mut output_offset = 0;
+    let mut total_out = 0;
+    let result = BrotliDecompressStream(
+        &mut available_in,
+        &mut input_offset,
+        input,
+        &mut available_out,
+        &mut output_offset,
+        out,
+        &mut total_out,
+        state,
+    );
+    input.advance(input_offset);
+    match result {
+        BrotliResult::ResultFailure => Err(invalid_data("invalid brotli stream")),
+        BrotliResult::ResultSuccess => Ok((output_offset, true)),
+        BrotliResult::NeedsMoreInput | BrotliResult::NeedsMoreOutput => Ok((output_offset, false)),
+    }
+}
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn StdError + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+// ===== impl Coded =====
+
+impl<B> HttpBody for Coded<B>
+where
+    B: HttpBody,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = Box<dyn StdError + Send + Sync>;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let mut this = self.project();
+        loop {
+            let codec = match this.codec.as_mut() {
+                Some(codec) => codec,
+                None => return Poll::Ready(None),
+            };
+            match codec.write(this.input) {
+                Ok(out) if !out.is_empty() => return Poll::Ready(Some(Ok(out))),
+                Ok(_) if !this.input.is_empty() => continue,
+                Ok(_) => (),
+                Err(err) => {
+                    *this.codec = None;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+            }
+            let result = match ready!(this.body.as_mut().poll_data(cx)) {
+                Some(Ok(mut data)) => {
+                    *this.input = data.to_bytes();
+                    continue;
+                }
+                Some(Err(err)) => {
+                    *this.codec = None;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                None => this.codec.take().expect("codec").finish(),
+            };
+            match result {
+                Ok(out) if out.is_empty() => continue,
+                Ok(out) => return Poll::Ready(Some(Ok(out))),
+                Err(err) => {
+                    *this.codec = None;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        self.project().body.poll_trailers(cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.codec.is_none() && self.body.is_end_stream()
+    }
+}
+
+impl<B> fmt::Debug for Coded<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coded")
+            .field("finished", &self.codec.is_none())
+            .finish()
+    }
+}
+
+// ===== impl MaybeCoded =====
+
+impl<B> HttpBody for MaybeCoded<B>
+where
+    B: HttpBody,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = Box<dyn StdError + Send + Sync>;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        match self.project() {
+            MaybeCodedProj::Identity(body) => match ready!(body.poll_data(cx)) {
+                Some(Ok(mut data)) => Poll::Ready(Some(Ok(data.to_bytes()))),
+                Some(Err(err)) => Poll::Ready(Some(Err(err.into()))),
+                None => Poll::Ready(None),
+            },
+            MaybeCodedProj::Coded(body) => body.as_mut().poll_data(cx),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        match self.project() {
+            MaybeCodedProj::Identity(body) => body.poll_trailers(cx).map_err(Into::into),
+            MaybeCodedProj::Coded(body) => body.as_mut().poll_trailers(cx),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match *self {
+            MaybeCoded::Identity(ref body) => body.is_end_stream(),
+            MaybeCoded::Coded(ref body) => body.is_end_stream(),
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match *self {
+            MaybeCoded::Identity(ref body) => body.size_hint(),
+            // The length of the encoded body is not known.
+            MaybeCoded::Coded(_) => SizeHint::default(),
+        }
+    }
+}
+
+impl<B> fmt::Debug for MaybeCoded<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MaybeCoded::Identity(_) => f.pad("Identity"),
+            MaybeCoded::Coded(ref body) => fmt::Debug::fmt(body, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as StdError;
+    use std::io;
+
+    use http::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, VARY};
+    use http::Response;
+
+    use super::{
+        decode_response, encode_response, preferred_encoding, Encoding, DECODE_BUFFER_SIZE,
+    };
+    use crate::body::{to_bytes, Body, Bytes, HttpBody};
+    use crate::common::{task, Pin, Poll};
+
+    /// A body of a single chunk, followed by an `x-checksum` trailer.
+    struct WithTrailers(Option<Bytes>);
+
+    impl HttpBody for WithTrailers {
+        type Data = Bytes;
+        type Error = crate::Error;
+
+        fn poll_data(
+            mut self: Pin<&mut Self>,
+            _: &mut task::Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            Poll::Ready(self.0.take().map(Ok))
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _: &mut task::Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            let mut trailers = HeaderMap::new();
+            trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+            Poll::Ready(Ok(Some(trailers)))
+        }
+    }
+
+    async fn encode(encoding: Encoding, data: Bytes) -> Response<Body> {
+        let res = encode_response(Response::new(Body::from(data)), Some(encoding));
+        let (parts, body) = res.into_parts();
+        let compressed = to_bytes(body).await.expect("encode");
+        Response::from_parts(parts, Body::from(compressed))
+    }
+
+    fn accept(val: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(val));
+        headers
+    }
+
+    #[test]
+    fn test_preferred_encoding() {
+        let cases = &[
+            ("gzip", Some(Encoding::Gzip)),
+            ("deflate, gzip", Some(Encoding::Gzip)),
+            ("gzip, deflate, br", Some(Encoding::Brotli)),
+            ("br;q=0.5, gzip;q=0.8", Some(Encoding::Gzip)),
+            ("br;q=0, identity", None),
+            ("*", Some(Encoding::Gzip)),
+            ("compress", None),
+        ];
+        for &(val, expected) in cases {
+            assert_eq!(preferred_encoding(&accept(val)), expected, "{:?}", val);
+        }
+        assert_eq!(preferred_encoding(&HeaderMap::new()), None);
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let text = "hello compression ".repeat(1000);
+        for &encoding in &[Encoding::Gzip, Encoding::Deflate, Encoding::Brotli] {
+            let res = encode_response(Response::new(Body::from(text.clone())), Some(encoding));
+            assert_eq!(res.headers()[CONTENT_ENCODING], encoding.to_header());
+            assert_eq!(res.headers()[VARY], "accept-encoding");
+
+            let (parts, body) = res.into_parts();
+            let compressed = to_bytes(body).await.expect("encode");
+            assert!(compressed.len() < text.len(), "{:?}", encoding);
+
+            let res = decode_response(Response::from_parts(parts, Body::from(compressed)));
+            assert!(res.headers().get(CONTENT_ENCODING).is_none());
+            let decoded = to_bytes(res.into_body()).await.expect("decode");
+            assert_eq!(decoded, text, "{:?}", encoding);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encode_skipped() {
+        let res = Response::builder().status(204).body(Body::empty()).unwrap();
+        let res = encode_response(res, Some(Encoding::Gzip));
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+
+        let res = Response::builder()
+            .header(CONTENT_ENCODING, "gzip")
+            .body(Body::from("already"))
+            .unwrap();
+        let res = encode_response(res, Some(Encoding::Brotli));
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(to_bytes(res.into_body()).await.unwrap(), "already");
+    }
+
+    #[tokio::test]
+    async fn test_encode_keeps_length_and_trailers() {
+        let res = encode_response(Response::new(Body::from("hello")), None);
+        assert_eq!(res.headers()[VARY], "accept-encoding");
+        assert_eq!(res.body().size_hint().exact(), Some(5));
+
+        for &encoding in &[None, Some(Encoding::Gzip)] {
+            let body = WithTrailers(Some(Bytes::from("hello")));
+            let res = encode_response(Response::new(body), encoding);
+            let mut body = res.into_body();
+            while let Some(chunk) = body.data().await {
+                chunk.expect("data");
+            }
+            let trailers = body.trailers().await.expect("trailers");
+            assert_eq!(trailers.expect("some trailers")["x-checksum"], "abc");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_bounded_chunks() {
+        let zeros = Bytes::from(vec![0; 4 * 1024 * 1024]);
+        for &encoding in &[Encoding::Gzip, Encoding::Deflate, Encoding::Brotli] {
+            let res = encode(encoding, zeros.clone()).await;
+            assert!(res.body().size_hint().exact().unwrap() < 64 * 1024);
+
+            let mut body = decode_response(res).into_body();
+            let mut len = 0;
+            while let Some(chunk) = body.data().await {
+                let chunk = chunk.expect("decode");
+                assert!(chunk.len() <= DECODE_BUFFER_SIZE, "{:?}", encoding);
+                assert!(chunk.iter().all(|&b| b == 0));
+                len += chunk.len();
+            }
+            assert_eq!(len, zeros.len(), "{:?}", encoding);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_truncated() {
+        let text = Bytes::from("hello compression ".repeat(100));
+        for &encoding in &[Encoding::Gzip, Encoding::Deflate, Encoding::Brotli] {
+            let (parts, body) = encode(encoding, text.clone()).await.into_parts();
+            let compressed = to_bytes(body).await.unwrap();
+            // Cut into the gzip trailer, and the end of the other streams.
+            let truncated = compressed.slice(..compressed.len() - 2);
+
+            let res = decode_response(Response::from_parts(parts, Body::from(truncated)));
+            let err = to_bytes(res.into_body()).await.expect_err("truncated");
+            let cause = StdError::source(&err)
+                .and_then(|cause| cause.downcast_ref::<io::Error>())
+                .expect("io::Error");
+            assert_eq!(cause.kind(), io::ErrorKind::UnexpectedEof, "{:?}", encoding);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_keeps_trailers() {
+        let (parts, body) = encode(Encoding::Gzip, Bytes::from("hello"))
+            .await
+            .into_parts();
+        let (mut tx, body_rx) = Body::channel();
+        tx.try_send_data(to_bytes(body).await.unwrap()).unwrap();
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+        tx.try_send_trailers(trailers).unwrap();
+        drop(tx);
+
+        let res = decode_response(Response::from_parts(parts, body_rx));
+        let mut body = res.into_body();
+        assert_eq!(body.data().await.unwrap().unwrap(), "hello");
+        assert!(body.data().await.is_none());
+        let trailers = body.trailers().await.expect("trailers");
+        assert_eq!(trailers.expect("some trailers")["x-checksum"], "abc");
+    }
+}
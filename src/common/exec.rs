@@ -124,7 +124,7 @@ pub struct H2Stream<F, B>(std::marker::PhantomData<(F, B)>);
 impl<F, B, E> Future for H2Stream<F, B>
 where
     F: Future<Output = Result<http::Response<B>, E>>,
-    B: HttpBody,
+    B: HttpBody + 'static,
     B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
     E: Into<Box<dyn std::error::Error + Send + Sync>>,
 {
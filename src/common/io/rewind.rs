@@ -39,6 +39,10 @@ impl<T> Rewind<T> {
         (self.inner, self.pre.unwrap_or_else(Bytes::new))
     }
 
+    pub(crate) fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
     // pub(crate) fn get_mut(&mut self) -> &mut T {
     //     &mut self.inner
     // }
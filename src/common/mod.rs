@@ -8,6 +8,8 @@ macro_rules! ready {
 }
 
 pub(crate) mod buf;
+#[cfg(feature = "compression")]
+pub(crate) mod compression;
 pub(crate) mod drain;
 pub(crate) mod exec;
 pub(crate) mod io;
@@ -24,6 +24,20 @@ pub(crate) enum Kind {
     User(User),
     /// A message reached EOF, but is not complete.
     IncompleteMessage,
+    /// A client did not send a complete message head in time.
+    #[cfg(feature = "runtime")]
+    HeaderTimeout,
+    /// A server did not send a response head in time.
+    #[cfg(feature = "runtime")]
+    ResponseHeadTimeout,
+    /// A request did not receive a response in time.
+    #[cfg(feature = "runtime")]
+    RequestTimeout,
+    /// A request waited too long for the connection limits of the pool.
+    #[cfg(feature = "runtime")]
+    PoolTimeout,
+    /// A redirect policy's limit on the number of redirects was reached.
+    TooManyRedirects,
     /// A connection received a message (or bytes) when not waiting for one.
     UnexpectedMessage,
     /// A pending item was dropped before ever being processed.
@@ -34,8 +48,8 @@ pub(crate) enum Kind {
     Io,
     /// Error occurred while connecting.
     Connect,
-    /// Error creating a TcpListener.
-    #[cfg(feature = "tcp")]
+    /// Error creating a TcpListener or UnixListener.
+    #[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
     Listen,
     /// Error accepting on an Incoming stream.
     Accept,
@@ -48,6 +62,10 @@ pub(crate) enum Kind {
     /// Error calling AsyncWrite::shutdown()
     Shutdown,
 
+    /// A request asked to upgrade its HTTP/1 connection to h2c.
+    #[cfg(feature = "http2")]
+    UpgradeH2c,
+
     /// A general error from h2.
     #[cfg(feature = "http2")]
     Http2,
@@ -61,6 +79,7 @@ pub(crate) enum Parse {
     Uri,
     Header,
     TooLarge,
+    TooManyHeaders,
     Status,
 }
 
@@ -143,6 +162,16 @@ impl Error {
         self.find_source::<TimedOut>().is_some()
     }
 
+    /// Returns true if this was a timeout waiting for the connection limits
+    /// of the `Client`'s pool.
+    pub fn is_pool_timeout(&self) -> bool {
+        match self.inner.kind {
+            #[cfg(feature = "runtime")]
+            Kind::PoolTimeout => true,
+            _ => false,
+        }
+    }
+
     /// Consumes the error, returning its cause.
     pub fn into_cause(self) -> Option<Box<dyn StdError + Send + Sync>> {
         self.inner.cause
@@ -197,6 +226,30 @@ impl Error {
         Error::new(Kind::Parse(Parse::TooLarge))
     }
 
+    #[cfg(feature = "runtime")]
+    pub(crate) fn new_header_timeout() -> Error {
+        Error::new(Kind::HeaderTimeout).with(TimedOut)
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) fn new_response_head_timeout() -> Error {
+        Error::new(Kind::ResponseHeadTimeout).with(TimedOut)
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) fn new_request_timeout() -> Error {
+        Error::new(Kind::RequestTimeout).with(TimedOut)
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) fn new_pool_timeout() -> Error {
+        Error::new(Kind::PoolTimeout).with(TimedOut)
+    }
+
+    pub(crate) fn new_too_many_redirects() -> Error {
+        Error::new(Kind::TooManyRedirects)
+    }
+
     pub(crate) fn new_version_h2() -> Error {
         Error::new(Kind::Parse(Parse::VersionH2))
     }
@@ -209,7 +262,7 @@ impl Error {
         Error::new(Kind::Io).with(cause)
     }
 
-    #[cfg(feature = "tcp")]
+    #[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
     pub(crate) fn new_listen<E: Into<Cause>>(cause: E) -> Error {
         Error::new(Kind::Listen).with(cause)
     }
@@ -286,6 +339,11 @@ impl Error {
         Error::new(Kind::Shutdown).with(cause)
     }
 
+    #[cfg(feature = "http2")]
+    pub(crate) fn new_upgrade_h2c() -> Error {
+        Error::new(Kind::UpgradeH2c)
+    }
+
     #[cfg(feature = "http2")]
     pub(crate) fn new_h2(cause: ::h2::Error) -> Error {
         if cause.is_io() {
@@ -303,13 +361,23 @@ impl Error {
             Kind::Parse(Parse::Uri) => "invalid URI",
             Kind::Parse(Parse::Header) => "invalid HTTP header parsed",
             Kind::Parse(Parse::TooLarge) => "message head is too large",
+            Kind::Parse(Parse::TooManyHeaders) => "message head has too many headers",
             Kind::Parse(Parse::Status) => "invalid HTTP status-code parsed",
             Kind::IncompleteMessage => "connection closed before message completed",
+            #[cfg(feature = "runtime")]
+            Kind::HeaderTimeout => "read header from client timeout",
+            #[cfg(feature = "runtime")]
+            Kind::ResponseHeadTimeout => "read response head from server timeout",
+            #[cfg(feature = "runtime")]
+            Kind::RequestTimeout => "request timeout",
+            #[cfg(feature = "runtime")]
+            Kind::PoolTimeout => "timed out waiting for a pooled connection",
+            Kind::TooManyRedirects => "too many redirects",
             Kind::UnexpectedMessage => "received unexpected message from connection",
             Kind::ChannelClosed => "channel closed",
             Kind::Connect => "error trying to connect",
             Kind::Canceled => "operation was canceled",
-            #[cfg(feature = "tcp")]
+            #[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
             Kind::Listen => "error creating server listener",
             Kind::Accept => "error accepting connection",
             Kind::Body => "error reading a body from connection",
@@ -317,6 +385,8 @@ impl Error {
             Kind::BodyWriteAborted => "body write aborted",
             Kind::Shutdown => "error shutting down connection",
             #[cfg(feature = "http2")]
+            Kind::UpgradeH2c => "connection upgrade to h2c requested",
+            #[cfg(feature = "http2")]
             Kind::Http2 => "http2 error",
             Kind::Io => "connection error",
 
@@ -381,7 +451,7 @@ impl From<httparse::Error> for Parse {
             | httparse::Error::NewLine
             | httparse::Error::Token => Parse::Header,
             httparse::Error::Status => Parse::Status,
-            httparse::Error::TooManyHeaders => Parse::TooLarge,
+            httparse::Error::TooManyHeaders => Parse::TooManyHeaders,
             httparse::Error::Version => Parse::Version,
         }
     }
@@ -436,6 +506,15 @@ mod tests {
         assert_eq!(mem::size_of::<Error>(), mem::size_of::<usize>());
     }
 
+    #[cfg(feature = "runtime")]
+    #[test]
+    fn pool_timeout_is_timeout() {
+        let err = Error::new_pool_timeout();
+        assert!(err.is_pool_timeout());
+        assert!(err.is_timeout());
+        assert!(!Error::new_canceled().is_pool_timeout());
+    }
+
     #[cfg(feature = "http2")]
     #[test]
     fn h2_reason_unknown() {
@@ -0,0 +1,138 @@
+//! Extensions recorded on HTTP/1 messages to preserve details of the wire
+//! format that `HeaderMap` can't represent.
+
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// The original casing of header names, as received in an HTTP/1 message.
+///
+/// For each header name, the original names are stored in the same order
+/// as the values in the `HeaderMap` they belong to.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HeaderCaseMap(HeaderMap<Bytes>);
+
+impl HeaderCaseMap {
+    /// Returns the original name of the `idx`th value of the `name` header.
+    pub(crate) fn get(&self, name: &HeaderName, idx: usize) -> Option<&Bytes> {
+        self.0.get_all(name).iter().nth(idx)
+    }
+
+    pub(crate) fn append(&mut self, name: HeaderName, orig: Bytes) {
+        self.0.append(name, orig);
+    }
+}
+
+/// The original order of headers, as received in an HTTP/1 message.
+///
+/// `HeaderMap` groups all values of the same name together, so this records
+/// the position of each value on the wire.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OriginalHeaderOrder {
+    positions: HeaderMap<usize>,
+    len: usize,
+}
+
+impl OriginalHeaderOrder {
+    /// Returns the wire position of the `idx`th value of the `name` header.
+    pub(crate) fn position(&self, name: &HeaderName, idx: usize) -> Option<usize> {
+        self.positions.get_all(name).iter().nth(idx).copied()
+    }
+
+    pub(crate) fn append(&mut self, name: HeaderName) {
+        self.positions.append(name, self.len);
+        self.len += 1;
+    }
+}
+
+/// A header line to write, with the original name if one was recorded.
+///
+/// The name is `None` if it is the same as for the previous line.
+pub(crate) type OriginalHeaderLine = (Option<HeaderName>, HeaderValue, Option<Bytes>);
+
+/// Drains the headers in their original order, paired with their original
+/// names.
+///
+/// Headers that weren't in the original message, such as those added by
+/// hyper or the user, keep their `HeaderMap` order and come after the
+/// original ones.
+pub(crate) fn drain_original(
+    headers: &mut HeaderMap,
+    case: Option<&HeaderCaseMap>,
+    order: Option<&OriginalHeaderOrder>,
+) -> Vec<OriginalHeaderLine> {
+    let mut lines = Vec::with_capacity(headers.len());
+    let mut cur_name: Option<HeaderName> = None;
+    let mut idx = 0;
+    for (opt_name, value) in headers.drain() {
+        if let Some(name) = opt_name {
+            cur_name = Some(name);
+            idx = 0;
+        } else {
+            idx += 1;
+        }
+        let name = cur_name.clone().expect("current header name");
+        let position = order
+            .and_then(|order| order.position(&name, idx))
+            .unwrap_or(usize::MAX);
+        let orig = case.and_then(|case| case.get(&name, idx)).cloned();
+        lines.push((position, name, value, orig));
+    }
+
+    // stable, so unknown headers keep their relative order
+    lines.sort_by_key(|line| line.0);
+
+    let mut prev: Option<HeaderName> = None;
+    lines
+        .into_iter()
+        .map(|(_, name, value, orig)| {
+            if prev.as_ref() == Some(&name) {
+                (None, value, orig)
+            } else {
+                prev = Some(name.clone());
+                (Some(name), value, orig)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drain_original_order_and_case() {
+        let mut headers = HeaderMap::new();
+        let mut case = HeaderCaseMap::default();
+        let mut order = OriginalHeaderOrder::default();
+
+        for &(name, value) in &[("X-Foo", "1"), ("host", "example.domain"), ("x-foo", "2")] {
+            let header = HeaderName::from_bytes(name.as_bytes()).unwrap();
+            headers.append(header.clone(), HeaderValue::from_static(value));
+            case.append(header.clone(), Bytes::from_static(name.as_bytes()));
+            order.append(header);
+        }
+        headers.insert("content-length", HeaderValue::from_static("0"));
+
+        let lines = drain_original(&mut headers, Some(&case), Some(&order));
+        let lines = lines
+            .iter()
+            .map(|(name, value, orig)| {
+                (
+                    name.as_ref().map(|n| n.as_str()),
+                    value.to_str().unwrap(),
+                    orig.as_ref().map(|o| &o[..]),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                (Some("x-foo"), "1", Some(&b"X-Foo"[..])),
+                (Some("host"), "example.domain", Some(&b"host"[..])),
+                (Some("x-foo"), "2", Some(&b"x-foo"[..])),
+                (Some("content-length"), "0", None),
+            ]
+        );
+    }
+}
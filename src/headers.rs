@@ -13,6 +13,10 @@ pub fn connection_close(value: &HeaderValue) -> bool {
     connection_has(value, "close")
 }
 
+pub fn te_trailers(value: &HeaderValue) -> bool {
+    connection_has(value, "trailers")
+}
+
 fn connection_has(value: &HeaderValue, needle: &str) -> bool {
     if let Ok(s) = value.to_str() {
         for val in s.split(',') {
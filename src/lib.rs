@@ -39,6 +39,12 @@
 //! - `tcp` (*enabled by default*): Enables convenient implementations over
 //!   TCP (using tokio).
 //! - `stream` (*enabled by default*): Provides `futures::Stream` capabilities.
+//! - `unix`: Enables a connector and acceptor for Unix domain sockets (using
+//!   tokio).
+//! - `compression`: Enables decoding `gzip`, `deflate` and `br` responses in
+//!   the `Client`, and compressing responses with `service::compress`.
+//! - `tls`: Enables traits for plugging a TLS implementation into the
+//!   `Client` and `Server`.
 
 #[doc(hidden)]
 pub use http;
@@ -63,6 +69,7 @@ pub mod body;
 pub mod client;
 #[doc(hidden)] // Mistakenly public...
 pub mod error;
+mod ext;
 mod headers;
 #[cfg(test)]
 mod mock;
@@ -70,4 +77,6 @@ mod proto;
 pub mod rt;
 pub mod server;
 pub mod service;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod upgrade;
@@ -1,16 +1,21 @@
 use std::fmt;
 use std::io::{self};
 use std::marker::PhantomData;
+#[cfg(feature = "runtime")]
+use std::time::Duration;
 
 use bytes::{Buf, Bytes};
-use http::header::{HeaderValue, CONNECTION};
+use http::header::{HeaderValue, CONNECTION, TE};
 use http::{HeaderMap, Method, Version};
 use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(feature = "runtime")]
+use tokio::time::Sleep;
 
 use super::io::Buffered;
 use super::{Decoder, Encode, EncodedBuf, Encoder, Http1Transaction, ParseContext, Wants};
 use crate::common::{task, Pin, Poll, Unpin};
-use crate::headers::connection_keep_alive;
+use crate::headers::{connection_keep_alive, te_trailers};
+use crate::proto::metrics::ConnectionMetrics;
 use crate::proto::{BodyLength, DecodedLength, MessageHead};
 use crate::Result;
 
@@ -40,11 +45,22 @@ where
             io: Buffered::new(io),
             state: State {
                 allow_half_close: false,
+                allow_trailer_fields: false,
                 cached_headers: None,
                 error: None,
                 keep_alive: KA::Busy,
                 method: None,
+                h1_max_headers: None,
+                preserve_header_case: false,
+                preserve_header_order: false,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout: None,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_fut: None,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_running: false,
                 title_case_headers: false,
+                incoming_trailers: None,
                 notify_read: false,
                 reading: Reading::Init,
                 writing: Writing::Init,
@@ -85,6 +101,27 @@ where
         self.state.allow_half_close = true;
     }
 
+    pub(crate) fn set_http1_max_headers(&mut self, val: usize) {
+        self.state.h1_max_headers = Some(val);
+    }
+
+    pub(crate) fn set_preserve_header_case(&mut self) {
+        self.state.preserve_header_case = true;
+    }
+
+    pub(crate) fn set_preserve_header_order(&mut self) {
+        self.state.preserve_header_order = true;
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) fn set_http1_header_read_timeout(&mut self, val: Duration) {
+        self.state.h1_header_read_timeout = Some(val);
+    }
+
+    pub(crate) fn set_metrics(&mut self, metrics: ConnectionMetrics) {
+        self.io.set_metrics(metrics);
+    }
+
     pub fn into_inner(self) -> (I, Bytes) {
         self.io.into_inner()
     }
@@ -146,6 +183,15 @@ where
             ParseContext {
                 cached_headers: &mut self.state.cached_headers,
                 req_method: &mut self.state.method,
+                h1_max_headers: self.state.h1_max_headers,
+                preserve_header_case: self.state.preserve_header_case,
+                preserve_header_order: self.state.preserve_header_order,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout: self.state.h1_header_read_timeout,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_fut: &mut self.state.h1_header_read_timeout_fut,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_running: &mut self.state.h1_header_read_timeout_running,
             }
         )) {
             Ok(msg) => msg,
@@ -161,6 +207,12 @@ where
         self.state.keep_alive &= msg.keep_alive;
         self.state.version = msg.head.version;
 
+        if T::is_server() {
+            // Trailers may only be sent back if the client said it
+            // would accept them.
+            self.state.allow_trailer_fields = msg.head.headers.get_all(TE).iter().any(te_trailers);
+        }
+
         let mut wants = if msg.wants_upgrade {
             Wants::UPGRADE
         } else {
@@ -223,6 +275,7 @@ where
                     Ok(slice) => {
                         let (reading, chunk) = if decoder.is_eof() {
                             debug!("incoming body completed");
+                            self.state.incoming_trailers = decoder.take_trailers();
                             (
                                 Reading::KeepAlive,
                                 if !slice.is_empty() {
@@ -268,6 +321,11 @@ where
         ret
     }
 
+    /// Takes the trailers received at the end of the last incoming body.
+    pub(super) fn take_incoming_trailers(&mut self) -> Option<HeaderMap> {
+        self.state.incoming_trailers.take()
+    }
+
     pub fn wants_read_again(&mut self) -> bool {
         let ret = self.state.notify_read;
         self.state.notify_read = false;
@@ -589,6 +647,45 @@ where
         self.state.writing = state;
     }
 
+    /// Whether trailers of the outgoing body could be written.
+    ///
+    /// Only a chunked body can carry trailers, and a server may only send
+    /// them if the request included `TE: trailers`.
+    pub(super) fn can_write_trailers(&self) -> bool {
+        match self.state.writing {
+            Writing::Body(ref encoder) => {
+                encoder.is_chunked() && (T::is_client() || self.state.allow_trailer_fields)
+            }
+            _ => false,
+        }
+    }
+
+    /// Writes the trailers, ending the body.
+    pub(super) fn write_trailers(&mut self, trailers: HeaderMap) -> Result<()> {
+        debug_assert!(self.can_write_trailers() && self.can_buffer_body());
+
+        let encoded = match self.state.writing {
+            Writing::Body(ref encoder) => encoder
+                .encode_trailers(trailers, self.state.title_case_headers)
+                .map(|enc_buf| (enc_buf, encoder.is_last())),
+            _ => unreachable!("write_trailers invalid state: {:?}", self.state.writing),
+        };
+
+        match encoded {
+            Some((enc_buf, is_last)) => {
+                self.io.buffer(enc_buf);
+                self.state.writing = if is_last {
+                    Writing::Closed
+                } else {
+                    Writing::KeepAlive
+                };
+                Ok(())
+            }
+            // No trailer fields were left to send, so end the body as usual.
+            None => self.end_body(),
+        }
+    }
+
     pub fn end_body(&mut self) -> Result<()> {
         debug_assert!(self.can_write_body());
 
@@ -726,6 +823,8 @@ impl<I: Unpin, B, T> Unpin for Conn<I, B, T> {}
 
 struct State {
     allow_half_close: bool,
+    /// Whether the peer accepts trailer fields, as signaled by `TE: trailers`.
+    allow_trailer_fields: bool,
     /// Re-usable HeaderMap to reduce allocating new ones.
     cached_headers: Option<HeaderMap>,
     /// If an error occurs when there wasn't a direct way to return it
@@ -738,7 +837,23 @@ struct State {
     /// This is used to know things such as if the message can include
     /// a body or not.
     method: Option<Method>,
+    /// The maximum number of headers allowed in a message head.
+    h1_max_headers: Option<usize>,
+    /// Whether to record the original case of received header names.
+    preserve_header_case: bool,
+    /// Whether to record the original order of received headers.
+    preserve_header_order: bool,
+    /// How long to wait for a complete message head before giving up.
+    #[cfg(feature = "runtime")]
+    h1_header_read_timeout: Option<Duration>,
+    #[cfg(feature = "runtime")]
+    h1_header_read_timeout_fut: Option<Sleep>,
+    #[cfg(feature = "runtime")]
+    h1_header_read_timeout_running: bool,
     title_case_headers: bool,
+    /// Trailers received at the end of a chunked body, waiting to be
+    /// handed to the body channel.
+    incoming_trailers: Option<HeaderMap>,
     /// Set to true when the Dispatcher should poll read operations
     /// again. See the `maybe_notify` method for more.
     notify_read: bool,
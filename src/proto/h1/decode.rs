@@ -3,7 +3,8 @@ use std::fmt;
 use std::io;
 use std::usize;
 
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 
 use crate::common::{task, Poll};
 
@@ -12,6 +13,12 @@ use super::DecodedLength;
 
 use self::Kind::{Chunked, Eof, Length};
 
+/// Maximum amount of bytes allowed in the trailer section of a chunked body.
+const TRAILER_LIMIT: usize = 1024 * 16;
+
+/// Maximum number of fields allowed in the trailer section of a chunked body.
+const TRAILER_MAX_FIELDS: usize = 100;
+
 /// Decoders to handle different Transfer-Encodings.
 ///
 /// If a message body does not include a Transfer-Encoding, it *should*
@@ -19,6 +26,10 @@ use self::Kind::{Chunked, Eof, Length};
 #[derive(Clone, PartialEq)]
 pub struct Decoder {
     kind: Kind,
+    /// The raw trailer section of a chunked body, while it's being read.
+    trailers_buf: Option<BytesMut>,
+    /// The parsed trailer fields, once the chunked body is complete.
+    trailers: Option<HeaderMap>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -57,6 +68,8 @@ enum ChunkedState {
     BodyLf,
     EndCr,
     EndLf,
+    Trailer,
+    TrailerLf,
     End,
 }
 
@@ -64,20 +77,22 @@ impl Decoder {
     // constructors
 
     pub fn length(x: u64) -> Decoder {
-        Decoder {
-            kind: Kind::Length(x),
-        }
+        Decoder::with_kind(Kind::Length(x))
     }
 
     pub fn chunked() -> Decoder {
-        Decoder {
-            kind: Kind::Chunked(ChunkedState::Size, 0),
-        }
+        Decoder::with_kind(Kind::Chunked(ChunkedState::Size, 0))
     }
 
     pub fn eof() -> Decoder {
+        Decoder::with_kind(Kind::Eof(false))
+    }
+
+    fn with_kind(kind: Kind) -> Decoder {
         Decoder {
-            kind: Kind::Eof(false),
+            kind,
+            trailers_buf: None,
+            trailers: None,
         }
     }
 
@@ -98,6 +113,11 @@ impl Decoder {
         }
     }
 
+    /// Takes the trailer fields received at the end of a chunked body, if any.
+    pub(super) fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take()
+    }
+
     pub fn decode<R: MemRead>(
         &mut self,
         cx: &mut task::Context<'_>,
@@ -129,9 +149,12 @@ impl Decoder {
                 loop {
                     let mut buf = None;
                     // advances the chunked state
-                    *state = ready!(state.step(cx, body, size, &mut buf))?;
+                    *state = ready!(state.step(cx, body, size, &mut buf, &mut self.trailers_buf))?;
                     if *state == ChunkedState::End {
                         trace!("end of chunked");
+                        if let Some(raw) = self.trailers_buf.take() {
+                            self.trailers = Some(decode_trailers(&raw)?);
+                        }
                         return Poll::Ready(Ok(Bytes::new()));
                     }
                     if let Some(buf) = buf {
@@ -186,6 +209,7 @@ impl ChunkedState {
         body: &mut R,
         size: &mut u64,
         buf: &mut Option<Bytes>,
+        trailers_buf: &mut Option<BytesMut>,
     ) -> Poll<Result<ChunkedState, io::Error>> {
         use self::ChunkedState::*;
         match *self {
@@ -196,8 +220,10 @@ impl ChunkedState {
             Body => ChunkedState::read_body(cx, body, size, buf),
             BodyCr => ChunkedState::read_body_cr(cx, body),
             BodyLf => ChunkedState::read_body_lf(cx, body),
-            EndCr => ChunkedState::read_end_cr(cx, body),
-            EndLf => ChunkedState::read_end_lf(cx, body),
+            EndCr => ChunkedState::read_end_cr(cx, body, trailers_buf),
+            EndLf => ChunkedState::read_end_lf(cx, body, trailers_buf),
+            Trailer => ChunkedState::read_trailer(cx, body, trailers_buf),
+            TrailerLf => ChunkedState::read_trailer_lf(cx, body, trailers_buf),
             End => Poll::Ready(Ok(ChunkedState::End)),
         }
     }
@@ -343,27 +369,110 @@ impl ChunkedState {
     fn read_end_cr<R: MemRead>(
         cx: &mut task::Context<'_>,
         rdr: &mut R,
+        trailers_buf: &mut Option<BytesMut>,
     ) -> Poll<Result<ChunkedState, io::Error>> {
         match byte!(rdr, cx) {
             b'\r' => Poll::Ready(Ok(ChunkedState::EndLf)),
+            byte => {
+                // anything else starts a trailer field
+                trailers_buf
+                    .get_or_insert_with(|| BytesMut::with_capacity(64))
+                    .put_u8(byte);
+                ChunkedState::check_trailer_limit(trailers_buf)?;
+                Poll::Ready(Ok(ChunkedState::Trailer))
+            }
+        }
+    }
+    fn read_end_lf<R: MemRead>(
+        cx: &mut task::Context<'_>,
+        rdr: &mut R,
+        trailers_buf: &mut Option<BytesMut>,
+    ) -> Poll<Result<ChunkedState, io::Error>> {
+        match byte!(rdr, cx) {
+            b'\n' => {
+                if let Some(ref mut buf) = *trailers_buf {
+                    // terminate the trailer section for the header parser
+                    buf.put_slice(b"\r\n");
+                }
+                Poll::Ready(Ok(ChunkedState::End))
+            }
             _ => Poll::Ready(Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                "Invalid chunk end CR",
+                "Invalid chunk end LF",
             ))),
         }
     }
-    fn read_end_lf<R: MemRead>(
+
+    fn read_trailer<R: MemRead>(
         cx: &mut task::Context<'_>,
         rdr: &mut R,
+        trailers_buf: &mut Option<BytesMut>,
+    ) -> Poll<Result<ChunkedState, io::Error>> {
+        trace!("read_trailer");
+        let byte = byte!(rdr, cx);
+        trailers_buf
+            .as_mut()
+            .expect("trailers_buf is set when reading a trailer")
+            .put_u8(byte);
+        ChunkedState::check_trailer_limit(trailers_buf)?;
+        match byte {
+            b'\r' => Poll::Ready(Ok(ChunkedState::TrailerLf)),
+            _ => Poll::Ready(Ok(ChunkedState::Trailer)),
+        }
+    }
+    fn read_trailer_lf<R: MemRead>(
+        cx: &mut task::Context<'_>,
+        rdr: &mut R,
+        trailers_buf: &mut Option<BytesMut>,
     ) -> Poll<Result<ChunkedState, io::Error>> {
         match byte!(rdr, cx) {
-            b'\n' => Poll::Ready(Ok(ChunkedState::End)),
+            b'\n' => {
+                trailers_buf
+                    .as_mut()
+                    .expect("trailers_buf is set when reading a trailer")
+                    .put_u8(b'\n');
+                ChunkedState::check_trailer_limit(trailers_buf)?;
+                Poll::Ready(Ok(ChunkedState::EndCr))
+            }
             _ => Poll::Ready(Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                "Invalid chunk end LF",
+                "Invalid trailer end LF",
             ))),
         }
     }
+
+    fn check_trailer_limit(trailers_buf: &Option<BytesMut>) -> io::Result<()> {
+        match *trailers_buf {
+            Some(ref buf) if buf.len() > TRAILER_LIMIT => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "chunk trailers bytes over limit",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn decode_trailers(raw: &[u8]) -> Result<HeaderMap, io::Error> {
+    let mut headers = [httparse::EMPTY_HEADER; TRAILER_MAX_FIELDS];
+
+    match httparse::parse_headers(raw, &mut headers) {
+        Ok(httparse::Status::Complete((_, headers))) => {
+            let mut trailers = HeaderMap::with_capacity(headers.len());
+            for header in headers {
+                let name = HeaderName::from_bytes(header.name.as_bytes())
+                    .map_err(|_| invalid_trailers())?;
+                let value =
+                    HeaderValue::from_bytes(header.value).map_err(|_| invalid_trailers())?;
+                trailers.append(name, value);
+            }
+            Ok(trailers)
+        }
+        Ok(httparse::Status::Partial) | Err(_) => Err(invalid_trailers()),
+    }
+}
+
+fn invalid_trailers() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "Invalid chunk trailers")
 }
 
 #[derive(Debug)]
@@ -435,9 +544,10 @@ mod tests {
             let rdr = &mut s.as_bytes();
             let mut size = 0;
             loop {
-                let result =
-                    futures_util::future::poll_fn(|cx| state.step(cx, rdr, &mut size, &mut None))
-                        .await;
+                let result = futures_util::future::poll_fn(|cx| {
+                    state.step(cx, rdr, &mut size, &mut None, &mut None)
+                })
+                .await;
                 let desc = format!("read_size failed for {:?}", s);
                 state = result.expect(desc.as_str());
                 if state == ChunkedState::Body || state == ChunkedState::EndCr {
@@ -452,9 +562,10 @@ mod tests {
             let rdr = &mut s.as_bytes();
             let mut size = 0;
             loop {
-                let result =
-                    futures_util::future::poll_fn(|cx| state.step(cx, rdr, &mut size, &mut None))
-                        .await;
+                let result = futures_util::future::poll_fn(|cx| {
+                    state.step(cx, rdr, &mut size, &mut None, &mut None)
+                })
+                .await;
                 state = match result {
                     Ok(s) => s,
                     Err(e) => {
@@ -558,6 +669,51 @@ mod tests {
         assert_eq!(0, buf.len());
     }
 
+    #[tokio::test]
+    async fn test_read_chunked_trailers() {
+        let mut mock_buf = &b"3\r\nfoo\r\n0\r\nchecksum: abc\r\nexpires: never\r\n\r\n"[..];
+        let mut decoder = Decoder::chunked();
+
+        let buf = decoder.decode_fut(&mut mock_buf).await.unwrap();
+        assert_eq!(&buf[..], b"foo");
+        assert!(decoder.take_trailers().is_none());
+
+        let buf = decoder.decode_fut(&mut mock_buf).await.expect("decode");
+        assert_eq!(0, buf.len());
+        assert!(decoder.is_eof());
+
+        let trailers = decoder.take_trailers().expect("trailers");
+        assert_eq!(trailers.len(), 2);
+        assert_eq!(trailers["checksum"], "abc");
+        assert_eq!(trailers["expires"], "never");
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_trailers_over_limit() {
+        let mut content = b"0\r\n".to_vec();
+        while content.len() <= TRAILER_LIMIT {
+            content.extend_from_slice(b"x-filler: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\n");
+        }
+        content.extend_from_slice(b"\r\n");
+
+        let mut mock_buf = &content[..];
+        let e = Decoder::chunked()
+            .decode_fut(&mut mock_buf)
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_trailers_invalid() {
+        let mut mock_buf = &b"0\r\nno colon here\r\n\r\n"[..];
+        let e = Decoder::chunked()
+            .decode_fut(&mut mock_buf)
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
     // perform an async read using a custom buffer size and causing a blocking
     // read at the specified byte
     async fn read_async(mut decoder: Decoder, content: &[u8], block_at: usize) -> String {
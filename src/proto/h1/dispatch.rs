@@ -7,6 +7,8 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use super::{Http1Transaction, Wants};
 use crate::body::{Body, HttpBody};
 use crate::common::{task, Future, Never, Pin, Poll, Unpin};
+use crate::proto::info::AddrInfo;
+use crate::proto::metrics::ConnectionMetrics;
 use crate::proto::{
     BodyLength, Conn, DecodedLength, Dispatched, MessageHead, RequestHead, RequestLine,
     ResponseHead,
@@ -38,6 +40,12 @@ pub(crate) trait Dispatch {
 pub struct Server<S: HttpService<B>, B> {
     in_flight: Pin<Box<Option<S::Future>>>,
     pub(crate) service: S,
+    #[cfg(feature = "http2")]
+    h2c_upgrade: bool,
+    #[cfg(feature = "http2")]
+    h2c: Option<crate::server::h2c::Upgrade>,
+    metrics: Option<ConnectionMetrics>,
+    addr_info: Option<AddrInfo>,
 }
 
 #[pin_project::pin_project]
@@ -85,6 +93,10 @@ where
         (io, buf, self.dispatch)
     }
 
+    pub(crate) fn dispatch_mut(&mut self) -> &mut D {
+        &mut self.dispatch
+    }
+
     /// Run this dispatcher until HTTP says this connection is done,
     /// but don't call `AsyncWrite::shutdown` on the underlying IO.
     ///
@@ -207,7 +219,11 @@ where
                             }
                         },
                         Poll::Ready(None) => {
-                            // just drop, the body will close automatically
+                            // hand over any trailers, then just drop,
+                            // the body will close automatically
+                            if let Some(trailers) = self.conn.take_incoming_trailers() {
+                                let _ = body.try_send_trailers(trailers);
+                            }
                         }
                         Poll::Pending => {
                             self.body_tx = Some(body);
@@ -352,8 +368,20 @@ where
                             self.conn.write_body(chunk);
                         }
                     } else {
+                        let trailers = if self.conn.can_write_trailers() {
+                            ready!(body.as_mut().poll_trailers(cx)).map_err(|e| {
+                                *clear_body = true;
+                                crate::Error::new_user_body(e)
+                            })?
+                        } else {
+                            None
+                        };
                         *clear_body = true;
-                        self.conn.end_body()?;
+                        match trailers {
+                            // Writing the trailers also ends the body.
+                            Some(trailers) => self.conn.write_trailers(trailers)?,
+                            None => self.conn.end_body()?,
+                        }
                     }
                 } else {
                     return Poll::Pending;
@@ -448,12 +476,42 @@ where
         Server {
             in_flight: Box::pin(None),
             service,
+            #[cfg(feature = "http2")]
+            h2c_upgrade: false,
+            #[cfg(feature = "http2")]
+            h2c: None,
+            metrics: None,
+            addr_info: None,
         }
     }
 
     pub fn into_service(self) -> S {
         self.service
     }
+
+    pub(crate) fn set_metrics(&mut self, metrics: ConnectionMetrics) {
+        self.metrics = Some(metrics);
+    }
+
+    pub(crate) fn set_addr_info(&mut self, info: AddrInfo) {
+        self.addr_info = Some(info);
+    }
+
+    #[cfg(feature = "http2")]
+    pub(crate) fn take_addr_info(&mut self) -> Option<AddrInfo> {
+        self.addr_info.take()
+    }
+
+    /// Stop dispatching if the first request asks to upgrade to h2c.
+    #[cfg(feature = "http2")]
+    pub(crate) fn enable_h2c_upgrade(&mut self) {
+        self.h2c_upgrade = true;
+    }
+
+    #[cfg(feature = "http2")]
+    pub(crate) fn take_h2c_upgrade(&mut self) -> Option<crate::server::h2c::Upgrade> {
+        self.h2c.take()
+    }
 }
 
 // Service is never pinned
@@ -482,6 +540,7 @@ where
                 version: parts.version,
                 subject: parts.status,
                 headers: parts.headers,
+                extensions: parts.extensions,
             };
             Poll::Ready(Some(Ok((head, body))))
         } else {
@@ -500,6 +559,28 @@ where
         *req.uri_mut() = msg.subject.1;
         *req.headers_mut() = msg.headers;
         *req.version_mut() = msg.version;
+        *req.extensions_mut() = msg.extensions;
+        #[cfg(feature = "http2")]
+        {
+            if self.h2c_upgrade {
+                // Only the first request of a connection can upgrade it, and
+                // only if it has no body to read with HTTP/1.
+                self.h2c_upgrade = false;
+                if req.body().is_end_stream() {
+                    self.h2c = crate::server::h2c::Upgrade::from_request(&req);
+                    if self.h2c.is_some() {
+                        trace!("request asked to upgrade to h2c");
+                        return Err(crate::Error::new_upgrade_h2c());
+                    }
+                }
+            }
+        }
+        if let Some(ref mut info) = self.addr_info {
+            req.extensions_mut().insert(info.next_request());
+        }
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_h1_request(req.version());
+        }
         let fut = self.service.call(req);
         self.in_flight.set(Some(fut));
         Ok(())
@@ -562,6 +643,7 @@ where
                             version: parts.version,
                             subject: RequestLine(parts.method, parts.uri),
                             headers: parts.headers,
+                            extensions: parts.extensions,
                         };
                         *this.callback = Some(cb);
                         Poll::Ready(Some(Ok((head, body))))
@@ -586,6 +668,7 @@ where
                     *res.status_mut() = msg.subject;
                     *res.headers_mut() = msg.headers;
                     *res.version_mut() = msg.version;
+                    *res.extensions_mut() = msg.extensions;
                     cb.send(Ok(res));
                     Ok(())
                 } else {
@@ -2,9 +2,11 @@ use std::fmt;
 use std::io::IoSlice;
 
 use bytes::buf::ext::{BufExt, Chain, Take};
-use bytes::Buf;
+use bytes::{Buf, Bytes};
+use http::header::{self, HeaderMap, HeaderName};
 
 use super::io::WriteBuf;
+use super::role::{write_headers, write_headers_title_case};
 
 type StaticBuf = &'static [u8];
 
@@ -44,6 +46,7 @@ enum BufKind<B> {
     Limited(Take<B>),
     Chunked(Chain<Chain<ChunkSize, B>, StaticBuf>),
     ChunkedEnd(StaticBuf),
+    Trailers(Bytes),
 }
 
 impl Encoder {
@@ -81,6 +84,13 @@ impl Encoder {
         self.is_last
     }
 
+    pub fn is_chunked(&self) -> bool {
+        match self.kind {
+            Kind::Chunked => true,
+            _ => false,
+        }
+    }
+
     pub fn is_close_delimited(&self) -> bool {
         match self.kind {
             Kind::CloseDelimited => true,
@@ -99,6 +109,52 @@ impl Encoder {
         }
     }
 
+    /// Encodes the last chunk followed by the trailer fields.
+    ///
+    /// Returns `None` if this isn't a chunked encoder, or if no trailer
+    /// fields were left after removing those not allowed in trailers.
+    pub(super) fn encode_trailers<B>(
+        &self,
+        mut trailers: HeaderMap,
+        title_case_headers: bool,
+    ) -> Option<EncodedBuf<B>> {
+        match self.kind {
+            Kind::Chunked => {
+                let names = trailers
+                    .keys()
+                    .filter(|name| !is_allowed_trailer_field(name))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for name in names {
+                    debug!("removing disallowed trailer field: {}", name);
+                    trailers.remove(name);
+                }
+
+                if trailers.is_empty() {
+                    return None;
+                }
+
+                let mut buf = Vec::with_capacity(64);
+                buf.extend_from_slice(b"0\r\n");
+                if title_case_headers {
+                    write_headers_title_case(&trailers, &mut buf);
+                } else {
+                    write_headers(&trailers, &mut buf);
+                }
+                buf.extend_from_slice(b"\r\n");
+
+                trace!("encoding trailers {}B", buf.len());
+                Some(EncodedBuf {
+                    kind: BufKind::Trailers(Bytes::from(buf)),
+                })
+            }
+            _ => {
+                debug!("attempted to encode trailers for non-chunked body");
+                None
+            }
+        }
+    }
+
     pub fn encode<B>(&mut self, msg: B) -> EncodedBuf<B>
     where
         B: Buf,
@@ -221,6 +277,7 @@ where
             BufKind::Limited(ref b) => b.remaining(),
             BufKind::Chunked(ref b) => b.remaining(),
             BufKind::ChunkedEnd(ref b) => b.remaining(),
+            BufKind::Trailers(ref b) => b.remaining(),
         }
     }
 
@@ -231,6 +288,7 @@ where
             BufKind::Limited(ref b) => b.bytes(),
             BufKind::Chunked(ref b) => b.bytes(),
             BufKind::ChunkedEnd(ref b) => b.bytes(),
+            BufKind::Trailers(ref b) => b.bytes(),
         }
     }
 
@@ -241,6 +299,7 @@ where
             BufKind::Limited(ref mut b) => b.advance(cnt),
             BufKind::Chunked(ref mut b) => b.advance(cnt),
             BufKind::ChunkedEnd(ref mut b) => b.advance(cnt),
+            BufKind::Trailers(ref mut b) => b.advance(cnt),
         }
     }
 
@@ -251,10 +310,53 @@ where
             BufKind::Limited(ref b) => b.bytes_vectored(dst),
             BufKind::Chunked(ref b) => b.bytes_vectored(dst),
             BufKind::ChunkedEnd(ref b) => b.bytes_vectored(dst),
+            BufKind::Trailers(ref b) => b.bytes_vectored(dst),
         }
     }
 }
 
+/// Whether a field may be sent in a trailer section.
+///
+/// Fields used for message framing, routing, request modifiers,
+/// authentication, response control and payload processing are not
+/// allowed, as described in RFC 7230 section 4.1.2.
+fn is_allowed_trailer_field(name: &HeaderName) -> bool {
+    const DISALLOWED: &[HeaderName] = &[
+        // framing
+        header::CONTENT_LENGTH,
+        header::TRANSFER_ENCODING,
+        header::TRAILER,
+        // routing
+        header::HOST,
+        // request modifiers
+        header::CACHE_CONTROL,
+        header::EXPECT,
+        header::MAX_FORWARDS,
+        header::PRAGMA,
+        header::RANGE,
+        header::TE,
+        // authentication
+        header::AUTHORIZATION,
+        header::PROXY_AUTHENTICATE,
+        header::PROXY_AUTHORIZATION,
+        header::WWW_AUTHENTICATE,
+        // response control
+        header::AGE,
+        header::DATE,
+        header::EXPIRES,
+        header::LOCATION,
+        header::RETRY_AFTER,
+        header::VARY,
+        header::WARNING,
+        // payload processing
+        header::CONTENT_ENCODING,
+        header::CONTENT_RANGE,
+        header::CONTENT_TYPE,
+    ];
+
+    !DISALLOWED.contains(name)
+}
+
 #[cfg(target_pointer_width = "32")]
 const USIZE_BYTES: usize = 4;
 
@@ -349,6 +451,7 @@ impl<B: Buf> From<Chain<Chain<ChunkSize, B>, StaticBuf>> for EncodedBuf<B> {
 #[cfg(test)]
 mod tests {
     use bytes::BufMut;
+    use http::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING};
 
     use super::super::io::Cursor;
     use super::Encoder;
@@ -378,6 +481,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn chunked_with_trailers() {
+        let mut encoder = Encoder::chunked();
+        let mut dst = Vec::new();
+
+        let msg1 = b"foo bar".as_ref();
+        let buf1 = encoder.encode(msg1);
+        dst.put(buf1);
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("chunky-trailer", HeaderValue::from_static("header data"));
+        // not allowed in trailers, should be dropped
+        trailers.insert(CONTENT_LENGTH, HeaderValue::from_static("7"));
+
+        let trailers = encoder.encode_trailers::<&[u8]>(trailers, false).unwrap();
+        dst.put(trailers);
+
+        assert_eq!(
+            dst,
+            b"7\r\nfoo bar\r\n0\r\nchunky-trailer: header data\r\n\r\n".as_ref()
+        );
+    }
+
+    #[test]
+    fn chunked_with_no_allowed_trailers() {
+        let encoder = Encoder::chunked();
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+
+        assert!(encoder.encode_trailers::<&[u8]>(trailers, false).is_none());
+    }
+
+    #[test]
+    fn length_with_trailers() {
+        let encoder = Encoder::length(8);
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("chunky-trailer", HeaderValue::from_static("header data"));
+
+        assert!(encoder.encode_trailers::<&[u8]>(trailers, false).is_none());
+    }
+
     #[test]
     fn length() {
         let max_len = 8;
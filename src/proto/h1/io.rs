@@ -5,10 +5,15 @@ use std::io::{self, IoSlice};
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+#[cfg(feature = "runtime")]
+use tokio::time::Instant;
 
 use super::{Http1Transaction, ParseContext, ParsedMessage};
 use crate::common::buf::BufList;
+#[cfg(feature = "runtime")]
+use crate::common::Future;
 use crate::common::{task, Pin, Poll, Unpin};
+use crate::proto::metrics::ConnectionMetrics;
 
 /// The initial buffer size allocated before trying to read from IO.
 pub(crate) const INIT_BUFFER_SIZE: usize = 8192;
@@ -35,6 +40,7 @@ pub struct Buffered<T, B> {
     read_buf: BytesMut,
     read_buf_strategy: ReadStrategy,
     write_buf: WriteBuf<B>,
+    metrics: Option<ConnectionMetrics>,
 }
 
 impl<T, B> fmt::Debug for Buffered<T, B>
@@ -62,6 +68,7 @@ where
             read_buf: BytesMut::with_capacity(0),
             read_buf_strategy: ReadStrategy::default(),
             write_buf: WriteBuf::new(),
+            metrics: None,
         }
     }
 
@@ -101,6 +108,11 @@ where
         self.write_buf.set_strategy(WriteStrategy::Queue);
     }
 
+    /// Record the bytes read and written in `metrics`.
+    pub(crate) fn set_metrics(&mut self, metrics: ConnectionMetrics) {
+        self.metrics = Some(metrics);
+    }
+
     pub fn read_buf(&self) -> &[u8] {
         self.read_buf.as_ref()
     }
@@ -155,16 +167,52 @@ where
     where
         S: Http1Transaction,
     {
+        #[cfg(feature = "runtime")]
+        {
+            if !*parse_ctx.h1_header_read_timeout_running {
+                if let Some(timeout) = parse_ctx.h1_header_read_timeout {
+                    let deadline = Instant::now() + timeout;
+                    *parse_ctx.h1_header_read_timeout_running = true;
+                    match *parse_ctx.h1_header_read_timeout_fut {
+                        Some(ref mut timer) => {
+                            trace!("resetting h1 header read timeout timer");
+                            timer.reset(deadline);
+                        }
+                        None => {
+                            trace!("setting h1 header read timeout timer");
+                            *parse_ctx.h1_header_read_timeout_fut =
+                                Some(tokio::time::sleep_until(deadline));
+                        }
+                    }
+                }
+            }
+        }
+
         loop {
             match super::role::parse_headers::<S>(
                 &mut self.read_buf,
                 ParseContext {
                     cached_headers: parse_ctx.cached_headers,
                     req_method: parse_ctx.req_method,
+                    h1_max_headers: parse_ctx.h1_max_headers,
+                    preserve_header_case: parse_ctx.preserve_header_case,
+                    preserve_header_order: parse_ctx.preserve_header_order,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: parse_ctx.h1_header_read_timeout,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: parse_ctx.h1_header_read_timeout_fut,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: parse_ctx.h1_header_read_timeout_running,
                 },
             )? {
                 Some(msg) => {
                     debug!("parsed {} headers", msg.head.headers.len());
+                    #[cfg(feature = "runtime")]
+                    {
+                        // The head is complete, the timer will be armed
+                        // again when the next message starts.
+                        *parse_ctx.h1_header_read_timeout_running = false;
+                    }
                     return Poll::Ready(Ok(msg));
                 }
                 None => {
@@ -173,6 +221,19 @@ where
                         debug!("max_buf_size ({}) reached, closing", max);
                         return Poll::Ready(Err(crate::Error::new_too_large()));
                     }
+
+                    #[cfg(feature = "runtime")]
+                    {
+                        if *parse_ctx.h1_header_read_timeout_running {
+                            if let Some(ref mut timer) = *parse_ctx.h1_header_read_timeout_fut {
+                                if Pin::new(timer).poll(cx).is_ready() {
+                                    *parse_ctx.h1_header_read_timeout_running = false;
+                                    warn!("read header from client timeout");
+                                    return Poll::Ready(Err(crate::Error::new_header_timeout()));
+                                }
+                            }
+                        }
+                    }
                 }
             }
             if ready!(self.poll_read_from_io(cx)).map_err(crate::Error::new_io)? == 0 {
@@ -199,6 +260,11 @@ where
                     self.read_buf.advance_mut(n);
                 }
                 self.read_buf_strategy.record(n);
+                if let Some(ref metrics) = self.metrics {
+                    if n > 0 {
+                        metrics.record_read(n);
+                    }
+                }
                 Poll::Ready(Ok(n))
             }
             Poll::Pending => {
@@ -242,6 +308,7 @@ where
                 // `poll_write_buf` comes back, the manual advance will need to leave!
                 self.write_buf.advance(n);
                 debug!("flushed {} bytes", n);
+                self.record_written(n);
                 if self.write_buf.remaining() == 0 {
                     break;
                 } else if n == 0 {
@@ -264,6 +331,7 @@ where
         loop {
             let n = ready!(Pin::new(&mut self.io).poll_write(cx, self.write_buf.headers.bytes()))?;
             debug!("flushed {} bytes", n);
+            self.record_written(n);
             self.write_buf.headers.advance(n);
             if self.write_buf.headers.remaining() == 0 {
                 self.write_buf.headers.reset();
@@ -279,6 +347,14 @@ where
         Pin::new(&mut self.io).poll_flush(cx)
     }
 
+    fn record_written(&self, n: usize) {
+        if let Some(ref metrics) = self.metrics {
+            if n > 0 {
+                metrics.record_written(n);
+            }
+        }
+    }
+
     #[cfg(test)]
     fn flush<'a>(&'a mut self) -> impl std::future::Future<Output = io::Result<()>> + 'a {
         futures_util::future::poll_fn(move |cx| self.poll_flush(cx))
@@ -685,6 +761,15 @@ mod tests {
             let parse_ctx = ParseContext {
                 cached_headers: &mut None,
                 req_method: &mut None,
+                h1_max_headers: None,
+                preserve_header_case: false,
+                preserve_header_order: false,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout: None,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_fut: &mut None,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_running: &mut false,
             };
             assert!(buffered
                 .parse::<ClientTransaction>(cx, parse_ctx)
@@ -1,5 +1,10 @@
+#[cfg(feature = "runtime")]
+use std::time::Duration;
+
 use bytes::BytesMut;
-use http::{HeaderMap, Method};
+use http::{HeaderMap, Method, StatusCode};
+#[cfg(feature = "runtime")]
+use tokio::time::Sleep;
 
 use crate::proto::{BodyLength, DecodedLength, MessageHead};
 
@@ -49,6 +54,32 @@ pub(crate) trait Http1Transaction {
     fn update_date() {}
 }
 
+/// Parses the response head to a `CONNECT` request, such as one sent to an
+/// HTTP proxy to open a tunnel.
+///
+/// Returns `None` if more bytes are needed.
+pub(crate) fn parse_connect_response(
+    buf: &mut BytesMut,
+) -> Result<Option<MessageHead<StatusCode>>, crate::error::Parse> {
+    let msg = ClientTransaction::parse(
+        buf,
+        ParseContext {
+            cached_headers: &mut None,
+            req_method: &mut Some(Method::CONNECT),
+            h1_max_headers: None,
+            preserve_header_case: false,
+            preserve_header_order: false,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout: None,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout_fut: &mut None,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout_running: &mut false,
+        },
+    )?;
+    Ok(msg.map(|msg| msg.head))
+}
+
 /// Result newtype for Http1Transaction::parse.
 pub(crate) type ParseResult<T> = Result<Option<ParsedMessage<T>>, crate::error::Parse>;
 
@@ -64,6 +95,15 @@ pub(crate) struct ParsedMessage<T> {
 pub(crate) struct ParseContext<'a> {
     cached_headers: &'a mut Option<HeaderMap>,
     req_method: &'a mut Option<Method>,
+    h1_max_headers: Option<usize>,
+    preserve_header_case: bool,
+    preserve_header_order: bool,
+    #[cfg(feature = "runtime")]
+    h1_header_read_timeout: Option<Duration>,
+    #[cfg(feature = "runtime")]
+    h1_header_read_timeout_fut: &'a mut Option<Sleep>,
+    #[cfg(feature = "runtime")]
+    h1_header_read_timeout_running: &'a mut bool,
 }
 
 /// Passed to Http1Transaction::encode
@@ -5,18 +5,21 @@
 use std::fmt::{self, Write};
 use std::mem;
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use http::header::{self, Entry, HeaderName, HeaderValue};
 use http::{HeaderMap, Method, StatusCode, Version};
 
 use crate::error::Parse;
+use crate::ext::{drain_original, HeaderCaseMap, OriginalHeaderOrder};
 use crate::headers;
 use crate::proto::h1::{
     date, Encode, Encoder, Http1Transaction, ParseContext, ParseResult, ParsedMessage,
 };
 use crate::proto::{BodyLength, DecodedLength, MessageHead, RequestHead, RequestLine};
 
-const MAX_HEADERS: usize = 100;
+/// The number of headers parsed with stack allocated storage. If more are
+/// allowed by `h1_max_headers`, the storage is allocated on the heap instead.
+const DEFAULT_MAX_HEADERS: usize = 100;
 const AVERAGE_HEADER_SIZE: usize = 30; // totally scientific
 
 macro_rules! header_name {
@@ -110,20 +113,37 @@ impl Http1Transaction for Server {
         let version;
         let len;
         let headers_len;
+        let max_headers = ctx.h1_max_headers.unwrap_or(DEFAULT_MAX_HEADERS);
 
         // Unsafe: both headers_indices and headers are using uninitialized memory,
         // but we *never* read any of it until after httparse has assigned
         // values into it. By not zeroing out the stack memory, this saves
         // a good ~5% on pipeline benchmarks.
-        let mut headers_indices: [HeaderIndices; MAX_HEADERS] = unsafe { mem::uninitialized() };
+        let mut headers_indices_stack: [HeaderIndices; DEFAULT_MAX_HEADERS] =
+            unsafe { mem::uninitialized() };
+        let mut headers_indices_heap;
+        let headers_indices: &mut [HeaderIndices] = if max_headers <= DEFAULT_MAX_HEADERS {
+            &mut headers_indices_stack[..max_headers]
+        } else {
+            headers_indices_heap = vec![HeaderIndices::EMPTY; max_headers];
+            &mut headers_indices_heap
+        };
         {
-            let mut headers: [httparse::Header<'_>; MAX_HEADERS] = unsafe { mem::uninitialized() };
+            let mut headers_stack: [httparse::Header<'_>; DEFAULT_MAX_HEADERS] =
+                unsafe { mem::uninitialized() };
+            let mut headers_heap;
+            let headers: &mut [httparse::Header<'_>] = if max_headers <= DEFAULT_MAX_HEADERS {
+                &mut headers_stack[..max_headers]
+            } else {
+                headers_heap = vec![httparse::EMPTY_HEADER; max_headers];
+                &mut headers_heap
+            };
             trace!(
                 "Request.parse([Header; {}], [u8; {}])",
                 headers.len(),
                 buf.len()
             );
-            let mut req = httparse::Request::new(&mut headers);
+            let mut req = httparse::Request::new(headers);
             let bytes = buf.as_ref();
             match req.parse(bytes) {
                 Ok(httparse::Status::Complete(parsed_len)) => {
@@ -143,7 +163,7 @@ impl Http1Transaction for Server {
                         Version::HTTP_10
                     };
 
-                    record_header_indices(bytes, &req.headers, &mut headers_indices)?;
+                    record_header_indices(bytes, &req.headers, headers_indices)?;
                     headers_len = req.headers.len();
                 }
                 Ok(httparse::Status::Partial) => return Ok(None),
@@ -186,6 +206,17 @@ impl Http1Transaction for Server {
 
         headers.reserve(headers_len);
 
+        let mut header_case_map = if ctx.preserve_header_case {
+            Some(HeaderCaseMap::default())
+        } else {
+            None
+        };
+        let mut header_order = if ctx.preserve_header_order {
+            Some(OriginalHeaderOrder::default())
+        } else {
+            None
+        };
+
         for header in &headers_indices[..headers_len] {
             let name = header_name!(&slice[header.name.0..header.name.1]);
             let value = header_value!(slice.slice(header.value.0..header.value.1));
@@ -249,6 +280,13 @@ impl Http1Transaction for Server {
                 _ => (),
             }
 
+            if let Some(ref mut header_case_map) = header_case_map {
+                header_case_map.append(name.clone(), slice.slice(header.name.0..header.name.1));
+            }
+            if let Some(ref mut header_order) = header_order {
+                header_order.append(name.clone());
+            }
+
             headers.append(name, value);
         }
 
@@ -259,11 +297,20 @@ impl Http1Transaction for Server {
 
         *ctx.req_method = Some(subject.0.clone());
 
+        let mut extensions = http::Extensions::default();
+        if let Some(header_case_map) = header_case_map {
+            extensions.insert(header_case_map);
+        }
+        if let Some(header_order) = header_order {
+            extensions.insert(header_order);
+        }
+
         Ok(Some(ParsedMessage {
             head: MessageHead {
                 version,
                 subject,
                 headers,
+                extensions,
             },
             decode: decoder,
             expect_continue,
@@ -373,7 +420,27 @@ impl Http1Transaction for Server {
             }};
         }
 
-        'headers: for (opt_name, value) in msg.head.headers.drain() {
+        // If the original case or order of the headers was recorded, write
+        // them back the same way.
+        let orig_case = msg.head.extensions.remove::<HeaderCaseMap>();
+        let orig_order = msg.head.extensions.remove::<OriginalHeaderOrder>();
+        let mut orig_lines = if orig_case.is_some() || orig_order.is_some() {
+            let lines = drain_original(
+                &mut msg.head.headers,
+                orig_case.as_ref(),
+                orig_order.as_ref(),
+            );
+            Some(lines.into_iter())
+        } else {
+            None
+        };
+        let mut drain = msg.head.headers.drain();
+        let mut next_header = || match orig_lines {
+            Some(ref mut lines) => lines.next(),
+            None => drain.next().map(|(name, value)| (name, value, None)),
+        };
+
+        'headers: while let Some((opt_name, value, orig_name)) = next_header() {
             if let Some(n) = opt_name {
                 cur_name = Some(n);
                 handle_is_name_written!();
@@ -410,7 +477,7 @@ impl Http1Transaction for Server {
 
                             if !is_name_written {
                                 encoder = Encoder::length(known_len);
-                                extend(dst, b"content-length: ");
+                                extend_name(dst, &orig_name, b"content-length");
                                 extend(dst, value.as_bytes());
                                 wrote_len = true;
                                 is_name_written = true;
@@ -438,7 +505,7 @@ impl Http1Transaction for Server {
                                 } else {
                                     // we haven't written content-length yet!
                                     encoder = Encoder::length(len);
-                                    extend(dst, b"content-length: ");
+                                    extend_name(dst, &orig_name, b"content-length");
                                     extend(dst, value.as_bytes());
                                     wrote_len = true;
                                     is_name_written = true;
@@ -493,7 +560,7 @@ impl Http1Transaction for Server {
                     if !is_name_written {
                         encoder = Encoder::chunked();
                         is_name_written = true;
-                        extend(dst, b"transfer-encoding: ");
+                        extend_name(dst, &orig_name, b"transfer-encoding");
                         extend(dst, value.as_bytes());
                     } else {
                         extend(dst, b", ");
@@ -507,7 +574,7 @@ impl Http1Transaction for Server {
                     }
                     if !is_name_written {
                         is_name_written = true;
-                        extend(dst, b"connection: ");
+                        extend_name(dst, &orig_name, b"connection");
                         extend(dst, value.as_bytes());
                     } else {
                         extend(dst, b", ");
@@ -529,8 +596,7 @@ impl Http1Transaction for Server {
                 "{:?} set is_name_written and didn't continue loop",
                 name,
             );
-            extend(dst, name.as_str().as_bytes());
-            extend(dst, b": ");
+            extend_name(dst, &orig_name, name.as_str().as_bytes());
             extend(dst, value.as_bytes());
             extend(dst, b"\r\n");
         }
@@ -597,7 +663,11 @@ impl Http1Transaction for Server {
             | Kind::Parse(Parse::Header)
             | Kind::Parse(Parse::Uri)
             | Kind::Parse(Parse::Version) => StatusCode::BAD_REQUEST,
-            Kind::Parse(Parse::TooLarge) => StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            Kind::Parse(Parse::TooLarge) | Kind::Parse(Parse::TooManyHeaders) => {
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+            }
+            #[cfg(feature = "runtime")]
+            Kind::HeaderTimeout => StatusCode::REQUEST_TIMEOUT,
             _ => return None,
         };
 
@@ -646,19 +716,36 @@ impl Http1Transaction for Client {
     fn parse(buf: &mut BytesMut, ctx: ParseContext<'_>) -> ParseResult<StatusCode> {
         debug_assert!(!buf.is_empty(), "parse called with empty buf");
 
+        let max_headers = ctx.h1_max_headers.unwrap_or(DEFAULT_MAX_HEADERS);
+
         // Loop to skip information status code headers (100 Continue, etc).
         loop {
             // Unsafe: see comment in Server Http1Transaction, above.
-            let mut headers_indices: [HeaderIndices; MAX_HEADERS] = unsafe { mem::uninitialized() };
+            let mut headers_indices_stack: [HeaderIndices; DEFAULT_MAX_HEADERS] =
+                unsafe { mem::uninitialized() };
+            let mut headers_indices_heap;
+            let headers_indices: &mut [HeaderIndices] = if max_headers <= DEFAULT_MAX_HEADERS {
+                &mut headers_indices_stack[..max_headers]
+            } else {
+                headers_indices_heap = vec![HeaderIndices::EMPTY; max_headers];
+                &mut headers_indices_heap
+            };
             let (len, status, version, headers_len) = {
-                let mut headers: [httparse::Header<'_>; MAX_HEADERS] =
+                let mut headers_stack: [httparse::Header<'_>; DEFAULT_MAX_HEADERS] =
                     unsafe { mem::uninitialized() };
+                let mut headers_heap;
+                let headers: &mut [httparse::Header<'_>] = if max_headers <= DEFAULT_MAX_HEADERS {
+                    &mut headers_stack[..max_headers]
+                } else {
+                    headers_heap = vec![httparse::EMPTY_HEADER; max_headers];
+                    &mut headers_heap
+                };
                 trace!(
                     "Response.parse([Header; {}], [u8; {}])",
                     headers.len(),
                     buf.len()
                 );
-                let mut res = httparse::Response::new(&mut headers);
+                let mut res = httparse::Response::new(headers);
                 let bytes = buf.as_ref();
                 match res.parse(bytes)? {
                     httparse::Status::Complete(len) => {
@@ -669,7 +756,7 @@ impl Http1Transaction for Client {
                         } else {
                             Version::HTTP_10
                         };
-                        record_header_indices(bytes, &res.headers, &mut headers_indices)?;
+                        record_header_indices(bytes, &res.headers, headers_indices)?;
                         let headers_len = res.headers.len();
                         (len, status, version, headers_len)
                     }
@@ -683,6 +770,17 @@ impl Http1Transaction for Client {
 
             let mut keep_alive = version == Version::HTTP_11;
 
+            let mut header_case_map = if ctx.preserve_header_case {
+                Some(HeaderCaseMap::default())
+            } else {
+                None
+            };
+            let mut header_order = if ctx.preserve_header_order {
+                Some(OriginalHeaderOrder::default())
+            } else {
+                None
+            };
+
             headers.reserve(headers_len);
             for header in &headers_indices[..headers_len] {
                 let name = header_name!(&slice[header.name.0..header.name.1]);
@@ -698,13 +796,30 @@ impl Http1Transaction for Client {
                         keep_alive = headers::connection_keep_alive(&value);
                     }
                 }
+
+                if let Some(ref mut header_case_map) = header_case_map {
+                    header_case_map.append(name.clone(), slice.slice(header.name.0..header.name.1));
+                }
+                if let Some(ref mut header_order) = header_order {
+                    header_order.append(name.clone());
+                }
+
                 headers.append(name, value);
             }
 
+            let mut extensions = http::Extensions::default();
+            if let Some(header_case_map) = header_case_map {
+                extensions.insert(header_case_map);
+            }
+            if let Some(header_order) = header_order {
+                extensions.insert(header_order);
+            }
+
             let head = MessageHead {
                 version,
                 subject: status,
                 headers,
+                extensions,
             };
             if let Some((decode, is_upgrade)) = Client::decoder(&head, ctx.req_method)? {
                 return Ok(Some(ParsedMessage {
@@ -756,7 +871,19 @@ impl Http1Transaction for Client {
         }
         extend(dst, b"\r\n");
 
-        if msg.title_case_headers {
+        // If the original case or order of the headers was recorded, write
+        // them back the same way.
+        let orig_case = msg.head.extensions.remove::<HeaderCaseMap>();
+        let orig_order = msg.head.extensions.remove::<OriginalHeaderOrder>();
+        if orig_case.is_some() || orig_order.is_some() {
+            write_headers_original(
+                &mut msg.head.headers,
+                orig_case.as_ref(),
+                orig_order.as_ref(),
+                msg.title_case_headers,
+                dst,
+            );
+        } else if msg.title_case_headers {
             write_headers_title_case(&msg.head.headers, dst);
         } else {
             write_headers(&msg.head.headers, dst);
@@ -999,6 +1126,13 @@ struct HeaderIndices {
     value: (usize, usize),
 }
 
+impl HeaderIndices {
+    const EMPTY: HeaderIndices = HeaderIndices {
+        name: (0, 0),
+        value: (0, 0),
+    };
+}
+
 fn record_header_indices(
     bytes: &[u8],
     headers: &[httparse::Header<'_>],
@@ -1055,7 +1189,7 @@ fn title_case(dst: &mut Vec<u8>, name: &[u8]) {
     }
 }
 
-fn write_headers_title_case(headers: &HeaderMap, dst: &mut Vec<u8>) {
+pub(super) fn write_headers_title_case(headers: &HeaderMap, dst: &mut Vec<u8>) {
     for (name, value) in headers {
         title_case(dst, name.as_str().as_bytes());
         extend(dst, b": ");
@@ -1064,7 +1198,7 @@ fn write_headers_title_case(headers: &HeaderMap, dst: &mut Vec<u8>) {
     }
 }
 
-fn write_headers(headers: &HeaderMap, dst: &mut Vec<u8>) {
+pub(super) fn write_headers(headers: &HeaderMap, dst: &mut Vec<u8>) {
     for (name, value) in headers {
         extend(dst, name.as_str().as_bytes());
         extend(dst, b": ");
@@ -1073,6 +1207,31 @@ fn write_headers(headers: &HeaderMap, dst: &mut Vec<u8>) {
     }
 }
 
+fn write_headers_original(
+    headers: &mut HeaderMap,
+    orig_case: Option<&HeaderCaseMap>,
+    orig_order: Option<&OriginalHeaderOrder>,
+    title_case_headers: bool,
+    dst: &mut Vec<u8>,
+) {
+    let mut cur_name = None;
+    for (opt_name, value, orig_name) in drain_original(headers, orig_case, orig_order) {
+        if let Some(name) = opt_name {
+            cur_name = Some(name);
+        }
+        let name = cur_name.as_ref().expect("current header name");
+
+        match orig_name {
+            Some(ref orig_name) => extend(dst, orig_name),
+            None if title_case_headers => title_case(dst, name.as_str().as_bytes()),
+            None => extend(dst, name.as_str().as_bytes()),
+        }
+        extend(dst, b": ");
+        extend(dst, value.as_bytes());
+        extend(dst, b"\r\n");
+    }
+}
+
 struct FastWrite<'a>(&'a mut Vec<u8>);
 
 impl<'a> fmt::Write for FastWrite<'a> {
@@ -1093,6 +1252,16 @@ fn extend(dst: &mut Vec<u8>, data: &[u8]) {
     dst.extend_from_slice(data);
 }
 
+/// Writes a header name followed by `": "`, using the original name if one
+/// was recorded.
+fn extend_name(dst: &mut Vec<u8>, orig_name: &Option<Bytes>, name: &[u8]) {
+    match *orig_name {
+        Some(ref orig_name) => extend(dst, orig_name),
+        None => extend(dst, name),
+    }
+    extend(dst, b": ");
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::BytesMut;
@@ -1109,6 +1278,15 @@ mod tests {
             ParseContext {
                 cached_headers: &mut None,
                 req_method: &mut method,
+                h1_max_headers: None,
+                preserve_header_case: false,
+                preserve_header_order: false,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout: None,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_fut: &mut None,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_running: &mut false,
             },
         )
         .unwrap()
@@ -1122,6 +1300,89 @@ mod tests {
         assert_eq!(method, Some(crate::Method::GET));
     }
 
+    #[test]
+    fn test_parse_request_max_headers() {
+        let _ = pretty_env_logger::try_init();
+
+        fn parse(num_headers: usize, max_headers: Option<usize>) -> ParseResult<RequestLine> {
+            let mut raw = String::from("GET /echo HTTP/1.1\r\n");
+            for i in 0..num_headers {
+                raw.push_str(&format!("x-header-{}: value\r\n", i));
+            }
+            raw.push_str("\r\n");
+            let mut raw = BytesMut::from(raw.as_str());
+            Server::parse(
+                &mut raw,
+                ParseContext {
+                    cached_headers: &mut None,
+                    req_method: &mut None,
+                    h1_max_headers: max_headers,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
+                },
+            )
+        }
+
+        // default stack storage
+        assert_eq!(parse(100, None).unwrap().unwrap().head.headers.len(), 100);
+        assert_eq!(parse(101, None).unwrap_err(), Parse::TooManyHeaders);
+
+        // lowered limit, still on the stack
+        assert_eq!(parse(10, Some(10)).unwrap().unwrap().head.headers.len(), 10);
+        assert_eq!(parse(11, Some(10)).unwrap_err(), Parse::TooManyHeaders);
+
+        // raised limit, on the heap
+        assert_eq!(
+            parse(200, Some(200)).unwrap().unwrap().head.headers.len(),
+            200
+        );
+        assert_eq!(parse(201, Some(200)).unwrap_err(), Parse::TooManyHeaders);
+    }
+
+    #[test]
+    fn test_parse_response_max_headers() {
+        let _ = pretty_env_logger::try_init();
+
+        fn parse(num_headers: usize, max_headers: Option<usize>) -> ParseResult<StatusCode> {
+            let mut raw = String::from("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n");
+            for _ in 1..num_headers {
+                raw.push_str("set-cookie: a=b\r\n");
+            }
+            raw.push_str("\r\n");
+            let mut raw = BytesMut::from(raw.as_str());
+            Client::parse(
+                &mut raw,
+                ParseContext {
+                    cached_headers: &mut None,
+                    req_method: &mut Some(Method::GET),
+                    h1_max_headers: max_headers,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
+                },
+            )
+        }
+
+        assert_eq!(parse(100, None).unwrap().unwrap().head.headers.len(), 100);
+        assert_eq!(parse(101, None).unwrap_err(), Parse::TooManyHeaders);
+        assert_eq!(
+            parse(1000, Some(1000)).unwrap().unwrap().head.headers.len(),
+            1000
+        );
+        assert_eq!(parse(1001, Some(1000)).unwrap_err(), Parse::TooManyHeaders);
+    }
+
     #[test]
     fn test_parse_response() {
         let _ = pretty_env_logger::try_init();
@@ -1129,6 +1390,15 @@ mod tests {
         let ctx = ParseContext {
             cached_headers: &mut None,
             req_method: &mut Some(crate::Method::GET),
+            h1_max_headers: None,
+            preserve_header_case: false,
+            preserve_header_order: false,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout: None,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout_fut: &mut None,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout_running: &mut false,
         };
         let msg = Client::parse(&mut raw, ctx).unwrap().unwrap();
         assert_eq!(raw.len(), 0);
@@ -1144,6 +1414,15 @@ mod tests {
         let ctx = ParseContext {
             cached_headers: &mut None,
             req_method: &mut None,
+            h1_max_headers: None,
+            preserve_header_case: false,
+            preserve_header_order: false,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout: None,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout_fut: &mut None,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout_running: &mut false,
         };
         Server::parse(&mut raw, ctx).unwrap_err();
     }
@@ -1157,6 +1436,15 @@ mod tests {
                 ParseContext {
                     cached_headers: &mut None,
                     req_method: &mut None,
+                    h1_max_headers: None,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
                 },
             )
             .expect("parse ok")
@@ -1170,6 +1458,15 @@ mod tests {
                 ParseContext {
                     cached_headers: &mut None,
                     req_method: &mut None,
+                    h1_max_headers: None,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
                 },
             )
             .expect_err(comment)
@@ -1372,6 +1669,15 @@ mod tests {
                 ParseContext {
                     cached_headers: &mut None,
                     req_method: &mut Some(Method::GET),
+                    h1_max_headers: None,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
                 }
             )
             .expect("parse ok")
@@ -1385,6 +1691,15 @@ mod tests {
                 ParseContext {
                     cached_headers: &mut None,
                     req_method: &mut Some(m),
+                    h1_max_headers: None,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
                 },
             )
             .expect("parse ok")
@@ -1398,6 +1713,15 @@ mod tests {
                 ParseContext {
                     cached_headers: &mut None,
                     req_method: &mut Some(Method::GET),
+                    h1_max_headers: None,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
                 },
             )
             .expect_err("parse should err")
@@ -1711,6 +2035,15 @@ mod tests {
             ParseContext {
                 cached_headers: &mut None,
                 req_method: &mut Some(Method::GET),
+                h1_max_headers: None,
+                preserve_header_case: false,
+                preserve_header_order: false,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout: None,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_fut: &mut None,
+                #[cfg(feature = "runtime")]
+                h1_header_read_timeout_running: &mut false,
             },
         )
         .expect("parse ok")
@@ -1754,6 +2087,15 @@ mod tests {
                 ParseContext {
                     cached_headers: &mut headers,
                     req_method: &mut None,
+                    h1_max_headers: None,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
                 },
             )
             .unwrap()
@@ -1787,6 +2129,15 @@ mod tests {
                 ParseContext {
                     cached_headers: &mut headers,
                     req_method: &mut None,
+                    h1_max_headers: None,
+                    preserve_header_case: false,
+                    preserve_header_order: false,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout: None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_fut: &mut None,
+                    #[cfg(feature = "runtime")]
+                    h1_header_read_timeout_running: &mut false,
                 },
             )
             .unwrap()
@@ -2,21 +2,30 @@ use std::error::Error as StdError;
 #[cfg(feature = "runtime")]
 use std::time::Duration;
 
+use bytes::Bytes;
 use futures_channel::{mpsc, oneshot};
 use futures_util::future::{self, Either, FutureExt as _, TryFutureExt as _};
 use futures_util::stream::StreamExt as _;
-use h2::client::{Builder, SendRequest};
+use h2::client::{Builder, PushPromises, SendRequest};
+use http::Method;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-use super::{decode_content_length, ping, PipeToSendStream, SendBuf};
+use super::{decode_content_length, ping, H2Upgraded, PipeToSendStream, SendBuf};
 use crate::body::HttpBody;
 use crate::common::{task, Exec, Future, Never, Pin, Poll};
 use crate::headers;
 use crate::proto::Dispatched;
+use crate::upgrade::Upgraded;
 use crate::{Body, Request, Response};
 
 type ClientRx<B> = crate::client::dispatch::Receiver<Request<B>, Response<Body>>;
 
+/// A request pushed by the server, and the promise of its response.
+pub(crate) type PushedResponse = (
+    Request<()>,
+    crate::client::dispatch::Promise<Response<Body>>,
+);
+
 ///// An mpsc channel is used to help notify the `Connection` task when *all*
 ///// other handles to it have been dropped, so that it can shutdown.
 type ConnDropRef = mpsc::Sender<Never>;
@@ -32,12 +41,17 @@ const DEFAULT_CONN_WINDOW: u32 = 1024 * 1024 * 5; // 5mb
 const DEFAULT_STREAM_WINDOW: u32 = 1024 * 1024 * 2; // 2mb
 const DEFAULT_MAX_FRAME_SIZE: u32 = 1024 * 16; // 16kb
 
+// Pushed responses not yet taken from `PushPromises`, beyond which further
+// pushes are refused.
+pub(crate) const PUSH_PROMISE_BUFFER: usize = 16;
+
 #[derive(Clone, Debug)]
 pub(crate) struct Config {
     pub(crate) adaptive_window: bool,
     pub(crate) initial_conn_window_size: u32,
     pub(crate) initial_stream_window_size: u32,
     pub(crate) max_frame_size: u32,
+    pub(crate) enable_push: bool,
     #[cfg(feature = "runtime")]
     pub(crate) keep_alive_interval: Option<Duration>,
     #[cfg(feature = "runtime")]
@@ -53,6 +67,7 @@ impl Default for Config {
             initial_conn_window_size: DEFAULT_CONN_WINDOW,
             initial_stream_window_size: DEFAULT_STREAM_WINDOW,
             max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            enable_push: false,
             #[cfg(feature = "runtime")]
             keep_alive_interval: None,
             #[cfg(feature = "runtime")]
@@ -66,6 +81,7 @@ impl Default for Config {
 pub(crate) async fn handshake<T, B>(
     io: T,
     req_rx: ClientRx<B>,
+    push_tx: Option<mpsc::Sender<PushedResponse>>,
     config: &Config,
     exec: Exec,
 ) -> crate::Result<ClientTask<B>>
@@ -78,7 +94,7 @@ where
         .initial_window_size(config.initial_stream_window_size)
         .initial_connection_window_size(config.initial_conn_window_size)
         .max_frame_size(config.max_frame_size)
-        .enable_push(false)
+        .enable_push(config.enable_push)
         .handshake::<_, SendBuf<B::Data>>(io)
         .await
         .map_err(crate::Error::new_h2)?;
@@ -148,6 +164,7 @@ where
         executor: exec,
         h2_tx,
         req_rx,
+        push_tx,
     })
 }
 
@@ -171,6 +188,58 @@ where
     }
 }
 
+async fn forward_push_promises(
+    mut promises: PushPromises,
+    mut push_tx: mpsc::Sender<PushedResponse>,
+    ping: ping::Recorder,
+    exec: Exec,
+) {
+    while let Some(result) = promises.push_promise().await {
+        let promise = match result {
+            Ok(promise) => promise,
+            Err(err) => {
+                debug!("client push promise error: {}", err);
+                return;
+            }
+        };
+
+        // record that we got the promised request headers
+        ping.record_non_data();
+
+        let (req, fut) = promise.into_parts();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        if let Err(err) = push_tx.try_send((req, rx)) {
+            if err.is_disconnected() {
+                trace!("push promises receiver dropped, refusing pushed streams");
+                return;
+            }
+            // Dropping the response future resets the pushed stream.
+            trace!("push promises buffer is full, refusing pushed stream");
+            continue;
+        }
+
+        let ping = ping.clone();
+        exec.execute(async move {
+            let res = match fut.await {
+                Ok(res) => {
+                    ping.record_non_data();
+
+                    let content_length = decode_content_length(res.headers());
+                    Ok(res.map(|stream| {
+                        let ping = ping.for_stream(&stream);
+                        crate::Body::h2(stream, content_length, ping)
+                    }))
+                }
+                Err(err) => {
+                    debug!("client pushed response error: {}", err);
+                    Err(crate::Error::new_h2(err))
+                }
+            };
+            let _ = tx.send(res);
+        });
+    }
+}
+
 pub(crate) struct ClientTask<B>
 where
     B: HttpBody,
@@ -181,6 +250,7 @@ where
     executor: Exec,
     h2_tx: SendRequest<SendBuf<B::Data>>,
     req_rx: ClientRx<B>,
+    push_tx: Option<mpsc::Sender<PushedResponse>>,
 }
 
 impl<B> Future for ClientTask<B>
@@ -221,8 +291,11 @@ where
                             headers::set_content_length_if_missing(req.headers_mut(), len);
                         }
                     }
-                    let eos = body.is_end_stream();
-                    let (fut, body_tx) = match self.h2_tx.send_request(req, eos) {
+                    // The stream of a `CONNECT` request is kept open, to be
+                    // used as a tunnel once the response comes.
+                    let is_connect = req.method() == Method::CONNECT;
+                    let eos = !is_connect && body.is_end_stream();
+                    let (mut fut, body_tx) = match self.h2_tx.send_request(req, eos) {
                         Ok(ok) => ok,
                         Err(err) => {
                             debug!("client send request error: {}", err);
@@ -232,7 +305,22 @@ where
                     };
 
                     let ping = self.ping.clone();
-                    if !eos {
+                    if self.push_tx.as_ref().map_or(false, |tx| tx.is_closed()) {
+                        // Nobody takes the pushed responses anymore.
+                        self.push_tx = None;
+                    }
+                    if let Some(ref push_tx) = self.push_tx {
+                        let promises = fut.push_promises();
+                        self.executor.execute(forward_push_promises(
+                            promises,
+                            push_tx.clone(),
+                            ping.clone(),
+                            self.executor.clone(),
+                        ));
+                    }
+                    let send_stream = if is_connect {
+                        Some(body_tx)
+                    } else if !eos {
                         let mut pipe = Box::pin(PipeToSendStream::new(body, body_tx)).map(|res| {
                             if let Err(e) = res {
                                 debug!("client request body error: {}", e);
@@ -257,7 +345,10 @@ where
                                 self.executor.execute(pipe);
                             }
                         }
-                    }
+                        None
+                    } else {
+                        None
+                    };
 
                     let fut = fut.map(move |result| match result {
                         Ok(res) => {
@@ -265,6 +356,28 @@ where
                             ping.record_non_data();
 
                             let content_length = decode_content_length(res.headers());
+                            if let Some(mut send_stream) = send_stream {
+                                if res.status().is_success() {
+                                    if content_length.into_opt().map_or(false, |len| len != 0) {
+                                        warn!(
+                                            "h2 connect response with non-zero body not supported"
+                                        );
+
+                                        send_stream.send_reset(h2::Reason::INTERNAL_ERROR);
+                                        return Err((
+                                            crate::Error::new_h2(h2::Reason::INTERNAL_ERROR.into()),
+                                            None,
+                                        ));
+                                    }
+                                    let (parts, recv_stream) = res.into_parts();
+                                    let mut res = Response::from_parts(parts, Body::empty());
+                                    let (pending, on_upgrade) = crate::upgrade::pending();
+                                    let io = H2Upgraded::new(ping, send_stream, recv_stream);
+                                    pending.fulfill(Upgraded::new(io, Bytes::new()));
+                                    res.body_mut().set_on_upgrade(on_upgrade);
+                                    return Ok(res);
+                                }
+                            }
                             let res = res.map(|stream| {
                                 let ping = ping.for_stream(&stream);
                                 crate::Body::h2(stream, content_length, ping)
@@ -0,0 +1,295 @@
+//! Pieces shared by the client and server sides of h2c upgrades.
+//!
+//! An h2c upgrade (RFC 7540, section 3.2) starts with a request sent with
+//! HTTP/1.1, and the response to it is then sent as stream 1 of the HTTP/2
+//! connection. The `h2` crate doesn't support streams that were opened before
+//! the connection, so both sides make up for it by filtering and injecting
+//! frames on the IO.
+
+use std::io;
+
+use tokio::io::AsyncWrite;
+
+use crate::common::{task, Pin, Poll};
+
+/// The connection preface a client sends first on an HTTP/2 connection.
+pub(crate) const PREFACE: &[u8; 24] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const FRAME_HEADER_LEN: usize = 9;
+/// The smallest `SETTINGS_MAX_FRAME_SIZE` a peer may use.
+const MAX_FRAME_SIZE: usize = 16_384;
+
+pub(crate) const DATA: u8 = 0x0;
+pub(crate) const HEADERS: u8 = 0x1;
+pub(crate) const SETTINGS: u8 = 0x4;
+pub(crate) const CONTINUATION: u8 = 0x9;
+
+pub(crate) const END_STREAM: u8 = 0x1;
+pub(crate) const ACK: u8 = 0x1;
+pub(crate) const END_HEADERS: u8 = 0x4;
+
+/// The header of an HTTP/2 frame.
+#[derive(Debug)]
+pub(crate) struct FrameHead {
+    pub(crate) len: usize,
+    pub(crate) kind: u8,
+    pub(crate) flags: u8,
+    pub(crate) stream_id: u32,
+}
+
+/// Tracks the frames written to an IO, so that some can be dropped.
+#[derive(Debug)]
+pub(crate) struct FrameFilter {
+    preface: usize,
+    header: [u8; FRAME_HEADER_LEN],
+    header_len: usize,
+    // The part of `header` that still needs to be written.
+    pending: usize,
+    remaining: usize,
+    dropping: bool,
+}
+
+impl FrameFilter {
+    /// Create a filter of the frames a client writes, after its preface.
+    pub(crate) fn client() -> FrameFilter {
+        FrameFilter {
+            preface: PREFACE.len(),
+            ..FrameFilter::server()
+        }
+    }
+
+    /// Create a filter of the frames a server writes.
+    pub(crate) fn server() -> FrameFilter {
+        FrameFilter {
+            preface: 0,
+            header: [0; FRAME_HEADER_LEN],
+            header_len: 0,
+            pending: 0,
+            remaining: 0,
+            dropping: false,
+        }
+    }
+
+    /// Returns whether everything written so far were whole frames.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.preface == 0 && self.header_len == 0 && self.pending == 0 && self.remaining == 0
+    }
+
+    /// Write some of `buf` to `io`, dropping any frame that `filter` returns
+    /// `true` for.
+    pub(crate) fn poll_write<T, F>(
+        &mut self,
+        io: &mut T,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+        filter: F,
+    ) -> Poll<io::Result<usize>>
+    where
+        T: AsyncWrite + Unpin,
+        F: FnOnce(&FrameHead) -> bool,
+    {
+        ready!(self.poll_write_pending(io, cx))?;
+
+        if self.preface > 0 {
+            let len = buf.len().min(self.preface);
+            let n = ready!(Pin::new(io).poll_write(cx, &buf[..len]))?;
+            self.preface -= n;
+            return Poll::Ready(Ok(n));
+        }
+
+        if self.remaining == 0 {
+            // Buffer a frame header, and only write it once it's known
+            // whether the frame is kept.
+            let len = buf.len().min(FRAME_HEADER_LEN - self.header_len);
+            self.header[self.header_len..self.header_len + len].copy_from_slice(&buf[..len]);
+            self.header_len += len;
+            if self.header_len == FRAME_HEADER_LEN {
+                let head = parse_frame_head(&self.header);
+                self.header_len = 0;
+                self.remaining = head.len;
+                self.dropping = filter(&head);
+                if self.dropping {
+                    trace!("h2c dropping frame; {:?}", head);
+                } else {
+                    self.pending = FRAME_HEADER_LEN;
+                }
+            }
+            return Poll::Ready(Ok(len));
+        }
+
+        let len = buf.len().min(self.remaining);
+        let n = if self.dropping {
+            len
+        } else {
+            ready!(Pin::new(io).poll_write(cx, &buf[..len]))?
+        };
+        self.remaining -= n;
+        Poll::Ready(Ok(n))
+    }
+
+    /// Write a frame header that was buffered by `poll_write`.
+    pub(crate) fn poll_write_pending<T>(
+        &mut self,
+        io: &mut T,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<io::Result<()>>
+    where
+        T: AsyncWrite + Unpin,
+    {
+        while self.pending > 0 {
+            let start = FRAME_HEADER_LEN - self.pending;
+            let n = ready!(Pin::new(&mut *io).poll_write(cx, &self.header[start..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending -= n;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn parse_frame_head(h: &[u8; FRAME_HEADER_LEN]) -> FrameHead {
+    FrameHead {
+        len: (h[0] as usize) << 16 | (h[1] as usize) << 8 | h[2] as usize,
+        kind: h[3],
+        flags: h[4],
+        stream_id: u32::from_be_bytes([h[5], h[6], h[7], h[8]]) & 0x7fff_ffff,
+    }
+}
+
+/// Append a frame to `dst`.
+pub(crate) fn encode_frame(dst: &mut Vec<u8>, kind: u8, flags: u8, stream_id: u32, payload: &[u8]) {
+    debug_assert!(payload.len() <= MAX_FRAME_SIZE);
+    dst.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    dst.push(kind);
+    dst.push(flags);
+    dst.extend_from_slice(&stream_id.to_be_bytes());
+    dst.extend_from_slice(payload);
+}
+
+/// Append a `HEADERS` frame, and any `CONTINUATION` frames needed, with a
+/// block of the `fields` to `dst`.
+///
+/// The fields are encoded as HPACK literals that aren't indexed, so the
+/// dynamic table of the receiver isn't changed.
+pub(crate) fn encode_headers<'a, I>(dst: &mut Vec<u8>, flags: u8, stream_id: u32, fields: I)
+where
+    I: IntoIterator<Item = (&'a [u8], &'a [u8])>,
+{
+    let mut block = Vec::new();
+    for (name, value) in fields {
+        // Literal Header Field without Indexing, with a new name.
+        block.push(0);
+        encode_string(&mut block, name);
+        encode_string(&mut block, value);
+    }
+
+    let mut chunks = block.chunks(MAX_FRAME_SIZE).peekable();
+    let mut kind = HEADERS;
+    let mut flags = flags;
+    loop {
+        let chunk = chunks.next().unwrap_or(&[]);
+        let is_last = chunks.peek().is_none();
+        let end = if is_last { END_HEADERS } else { 0 };
+        encode_frame(dst, kind, flags | end, stream_id, chunk);
+        if is_last {
+            return;
+        }
+        kind = CONTINUATION;
+        flags = 0;
+    }
+}
+
+fn encode_string(dst: &mut Vec<u8>, s: &[u8]) {
+    // Not Huffman encoded, with a 7-bit prefix for the length.
+    let mut len = s.len();
+    if len < 0x7f {
+        dst.push(len as u8);
+    } else {
+        dst.push(0x7f);
+        len -= 0x7f;
+        while len >= 0x80 {
+            dst.push((len & 0x7f) as u8 | 0x80);
+            len >>= 7;
+        }
+        dst.push(len as u8);
+    }
+    dst.extend_from_slice(s);
+}
+
+/// Decode the value of an `HTTP2-Settings` header into the payload of a
+/// `SETTINGS` frame.
+pub(crate) fn decode_settings(value: &[u8]) -> Option<Vec<u8>> {
+    // base64url, where the padding is optional.
+    let value = match value.iter().position(|&b| b == b'=') {
+        Some(pos) => &value[..pos],
+        None => value,
+    };
+    let mut payload = Vec::with_capacity(value.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for &b in value {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => return None,
+        };
+        acc = acc << 6 | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            payload.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    // Each setting is 6 bytes.
+    if payload.len() % 6 == 0 && payload.len() <= MAX_FRAME_SIZE {
+        Some(payload)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn decode_settings_base64url() {
+        assert_eq!(decode_settings(b"AAIAAAAA"), Some(vec![0, 2, 0, 0, 0, 0]));
+        assert_eq!(
+            decode_settings(b"AAMAAABkAAQAoAAA"),
+            Some(vec![0, 3, 0, 0, 0, 100, 0, 4, 0, 160, 0, 0])
+        );
+        assert_eq!(decode_settings(b""), Some(vec![]));
+        assert_eq!(decode_settings(b"AAIAAA=="), None);
+        assert_eq!(decode_settings(b"AAIA+AAA"), None);
+    }
+
+    #[test]
+    fn encode_headers_continuation() {
+        let value = vec![b'a'; MAX_FRAME_SIZE];
+        let mut dst = Vec::new();
+        encode_headers(
+            &mut dst,
+            END_STREAM,
+            1,
+            vec![(&b":method"[..], &b"GET"[..]), (b"x-big", &value)],
+        );
+
+        let head = parse_frame_head(dst[..9].try_into().unwrap());
+        assert_eq!(head.kind, HEADERS);
+        assert_eq!(head.flags, END_STREAM);
+        assert_eq!(head.len, MAX_FRAME_SIZE);
+
+        let rest = &dst[9 + MAX_FRAME_SIZE..];
+        let head = parse_frame_head(rest[..9].try_into().unwrap());
+        assert_eq!(head.kind, CONTINUATION);
+        assert_eq!(head.flags, END_HEADERS);
+        assert_eq!(rest.len(), 9 + head.len);
+    }
+}
@@ -1,5 +1,5 @@
-use bytes::Buf;
-use h2::SendStream;
+use bytes::{Buf, Bytes};
+use h2::{Reason, RecvStream, SendStream};
 use http::header::{
     HeaderName, CONNECTION, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE, TRAILER,
     TRANSFER_ENCODING, UPGRADE,
@@ -7,7 +7,9 @@ use http::header::{
 use http::HeaderMap;
 use pin_project::pin_project;
 use std::error::Error as StdError;
-use std::io::IoSlice;
+use std::io::{self, IoSlice};
+use std::mem;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
 use super::DecodedLength;
 use crate::body::HttpBody;
@@ -15,6 +17,7 @@ use crate::common::{task, Future, Pin, Poll};
 use crate::headers::content_length_parse_all;
 
 pub(crate) mod client;
+pub(crate) mod h2c;
 pub(crate) mod ping;
 pub(crate) mod server;
 
@@ -91,12 +94,13 @@ fn decode_content_length(headers: &HeaderMap) -> DecodedLength {
 // body adapters used by both Client and Server
 
 #[pin_project]
-struct PipeToSendStream<S>
+struct PipeToSendStream<S, D = <S as HttpBody>::Data>
 where
     S: HttpBody,
 {
-    body_tx: SendStream<SendBuf<S::Data>>,
+    body_tx: SendStream<SendBuf<D>>,
     data_done: bool,
+    wrap: fn(S::Data) -> SendBuf<D>,
     #[pin]
     stream: S,
 }
@@ -109,15 +113,33 @@ where
         PipeToSendStream {
             body_tx: tx,
             data_done: false,
+            wrap: SendBuf::Buf,
             stream,
         }
     }
 }
 
-impl<S> Future for PipeToSendStream<S>
+impl<S, D> PipeToSendStream<S, D>
+where
+    S: HttpBody<Data = Bytes>,
+{
+    /// Pipes a body of `Bytes` into a stream sending another buffer type,
+    /// such as a pushed stream of the server.
+    fn from_bytes(stream: S, tx: SendStream<SendBuf<D>>) -> PipeToSendStream<S, D> {
+        PipeToSendStream {
+            body_tx: tx,
+            data_done: false,
+            wrap: SendBuf::Bytes,
+            stream,
+        }
+    }
+}
+
+impl<S, D> Future for PipeToSendStream<S, D>
 where
     S: HttpBody,
     S::Error: Into<Box<dyn StdError + Send + Sync>>,
+    D: Buf,
 {
     type Output = crate::Result<()>;
 
@@ -168,7 +190,7 @@ where
                             is_eos,
                         );
 
-                        let buf = SendBuf(Some(chunk));
+                        let buf = (me.wrap)(chunk);
                         me.body_tx
                             .send_data(buf, is_eos)
                             .map_err(crate::Error::new_body_write)?;
@@ -239,32 +261,280 @@ impl<B: Buf> SendStreamExt for SendStream<SendBuf<B>> {
 
     fn send_eos_frame(&mut self) -> crate::Result<()> {
         trace!("send body eos");
-        self.send_data(SendBuf(None), true)
+        self.send_data(SendBuf::None, true)
             .map_err(crate::Error::new_body_write)
     }
 }
 
-struct SendBuf<B>(Option<B>);
+#[repr(usize)]
+enum SendBuf<B> {
+    Buf(B),
+    Bytes(Bytes),
+    None,
+}
 
 impl<B: Buf> Buf for SendBuf<B> {
     #[inline]
     fn remaining(&self) -> usize {
-        self.0.as_ref().map(|b| b.remaining()).unwrap_or(0)
+        match *self {
+            SendBuf::Buf(ref b) => b.remaining(),
+            SendBuf::Bytes(ref b) => b.remaining(),
+            SendBuf::None => 0,
+        }
     }
 
     #[inline]
     fn bytes(&self) -> &[u8] {
-        self.0.as_ref().map(|b| b.bytes()).unwrap_or(&[])
+        match *self {
+            SendBuf::Buf(ref b) => b.bytes(),
+            SendBuf::Bytes(ref b) => b.bytes(),
+            SendBuf::None => &[],
+        }
     }
 
     #[inline]
     fn advance(&mut self, cnt: usize) {
-        if let Some(b) = self.0.as_mut() {
-            b.advance(cnt)
+        match *self {
+            SendBuf::Buf(ref mut b) => b.advance(cnt),
+            SendBuf::Bytes(ref mut b) => b.advance(cnt),
+            SendBuf::None => {}
         }
     }
 
     fn bytes_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
-        self.0.as_ref().map(|b| b.bytes_vectored(dst)).unwrap_or(0)
+        match *self {
+            SendBuf::Buf(ref b) => b.bytes_vectored(dst),
+            SendBuf::Bytes(ref b) => b.bytes_vectored(dst),
+            SendBuf::None => 0,
+        }
+    }
+}
+
+/// The IO of an HTTP/2 stream that was upgraded, such as for a `CONNECT`
+/// request.
+pub(crate) struct H2Upgraded<B>
+where
+    B: Buf,
+{
+    ping: ping::Recorder,
+    send_stream: UpgradedSendStream<B>,
+    recv_stream: RecvStream,
+    buf: Bytes,
+}
+
+impl<B> H2Upgraded<B>
+where
+    B: Buf,
+{
+    fn new(
+        ping: ping::Recorder,
+        send_stream: SendStream<SendBuf<B>>,
+        recv_stream: RecvStream,
+    ) -> H2Upgraded<B> {
+        H2Upgraded {
+            ping,
+            // Safety: the upgraded stream only ever sends `SendBuf::Bytes`.
+            send_stream: unsafe { UpgradedSendStream::new(send_stream) },
+            recv_stream,
+            buf: Bytes::new(),
+        }
+    }
+}
+
+impl<B> AsyncRead for H2Upgraded<B>
+where
+    B: Buf,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        read_buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.buf.is_empty() {
+            self.buf = loop {
+                match ready!(self.recv_stream.poll_data(cx)) {
+                    None => return Poll::Ready(Ok(())),
+                    Some(Ok(buf)) if buf.is_empty() && !self.recv_stream.is_end_stream() => {
+                        continue
+                    }
+                    Some(Ok(buf)) => {
+                        self.ping.record_data(buf.len());
+                        break buf;
+                    }
+                    Some(Err(e)) => {
+                        return Poll::Ready(match e.reason() {
+                            Some(Reason::NO_ERROR) | Some(Reason::CANCEL) => Ok(()),
+                            Some(Reason::STREAM_CLOSED) => {
+                                Err(io::Error::new(io::ErrorKind::BrokenPipe, e))
+                            }
+                            _ => Err(h2_to_io_error(e)),
+                        })
+                    }
+                }
+            };
+        }
+        let cnt = std::cmp::min(self.buf.len(), read_buf.remaining());
+        read_buf.put_slice(&self.buf[..cnt]);
+        self.buf.advance(cnt);
+        let _ = self.recv_stream.flow_control().release_capacity(cnt);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<B> AsyncWrite for H2Upgraded<B>
+where
+    B: Buf,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        self.send_stream.reserve_capacity(buf.len());
+
+        // Errors of `poll_capacity` and `write` are ignored, since
+        // `poll_reset` reports the reason the stream was closed.
+        let cnt = match ready!(self.send_stream.poll_capacity(cx)) {
+            None => Some(0),
+            Some(Ok(cnt)) => self
+                .send_stream
+                .write(&buf[..cnt], false)
+                .ok()
+                .map(|()| cnt),
+            Some(Err(_)) => None,
+        };
+
+        if let Some(cnt) = cnt {
+            return Poll::Ready(Ok(cnt));
+        }
+
+        Poll::Ready(Err(h2_to_io_error(
+            match ready!(self.send_stream.poll_reset(cx)) {
+                Ok(Reason::NO_ERROR) | Ok(Reason::CANCEL) | Ok(Reason::STREAM_CLOSED) => {
+                    return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+                }
+                Ok(reason) => reason.into(),
+                Err(e) => e,
+            },
+        )))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        if self.send_stream.write(&[], true).is_ok() {
+            return Poll::Ready(Ok(()));
+        }
+
+        Poll::Ready(Err(h2_to_io_error(
+            match ready!(self.send_stream.poll_reset(cx)) {
+                Ok(Reason::NO_ERROR) => return Poll::Ready(Ok(())),
+                Ok(Reason::CANCEL) | Ok(Reason::STREAM_CLOSED) => {
+                    return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+                }
+                Ok(reason) => reason.into(),
+                Err(e) => e,
+            },
+        )))
+    }
+}
+
+fn h2_to_io_error(e: h2::Error) -> io::Error {
+    if e.is_io() {
+        e.into_io().unwrap()
+    } else {
+        io::Error::new(io::ErrorKind::Other, e)
+    }
+}
+
+/// A `SendStream` that is `Send`, even if the body data of its connection
+/// isn't.
+///
+/// # Safety
+///
+/// The stream is transmuted from `SendStream<SendBuf<B>>` to
+/// `SendStream<SendBuf<Neutered<B>>>`, and back when it is used. This is
+/// sound because:
+///
+/// - `Neutered<B>` is `repr(transparent)` over `B`, so the two stream types
+///   have the same layout (the sizes are also asserted in `new`).
+/// - A `Neutered<B>` can never exist, since it holds an uninhabited field.
+///   The only values of `B` on this stream are the ones it sends, and it
+///   only ever sends `SendBuf::Bytes`, so it never moves, reads or drops a
+///   `B`. Other streams of the connection keep using `SendStream<SendBuf<B>>`
+///   directly.
+/// - Because no `B` is ever reached through it, the `Send` impl of
+///   `Neutered<B>` can't be used to send a `B` to another thread.
+struct UpgradedSendStream<B>(SendStream<SendBuf<Neutered<B>>>);
+
+impl<B> UpgradedSendStream<B>
+where
+    B: Buf,
+{
+    unsafe fn new(inner: SendStream<SendBuf<B>>) -> Self {
+        assert_eq!(mem::size_of::<B>(), mem::size_of::<Neutered<B>>());
+        UpgradedSendStream(mem::transmute::<
+            SendStream<SendBuf<B>>,
+            SendStream<SendBuf<Neutered<B>>>,
+        >(inner))
+    }
+
+    fn reserve_capacity(&mut self, cnt: usize) {
+        unsafe { self.as_inner_unchecked().reserve_capacity(cnt) }
+    }
+
+    fn poll_capacity(
+        &mut self,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<usize, h2::Error>>> {
+        unsafe { self.as_inner_unchecked().poll_capacity(cx) }
+    }
+
+    fn poll_reset(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<Reason, h2::Error>> {
+        unsafe { self.as_inner_unchecked().poll_reset(cx) }
+    }
+
+    fn write(&mut self, buf: &[u8], end_of_stream: bool) -> io::Result<()> {
+        let send_buf = SendBuf::Bytes(Bytes::copy_from_slice(buf));
+        unsafe {
+            self.as_inner_unchecked()
+                .send_data(send_buf, end_of_stream)
+                .map_err(h2_to_io_error)
+        }
+    }
+
+    unsafe fn as_inner_unchecked(&mut self) -> &mut SendStream<SendBuf<B>> {
+        &mut *(&mut self.0 as *mut _ as *mut _)
+    }
+}
+
+/// A `B` that can never be constructed, see `UpgradedSendStream`.
+#[repr(transparent)]
+struct Neutered<B> {
+    _inner: B,
+    impossible: Impossible,
+}
+
+enum Impossible {}
+
+// Safety: no `Neutered<B>` value can exist, so there is nothing to send.
+unsafe impl<B> Send for Neutered<B> {}
+
+impl<B> Buf for Neutered<B> {
+    fn remaining(&self) -> usize {
+        match self.impossible {}
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self.impossible {}
+    }
+
+    fn advance(&mut self, _cnt: usize) {
+        match self.impossible {}
     }
 }
@@ -3,18 +3,26 @@ use std::marker::Unpin;
 #[cfg(feature = "runtime")]
 use std::time::Duration;
 
+use bytes::{Buf, Bytes};
+use futures_channel::mpsc;
+use futures_util::stream::StreamExt as _;
 use h2::server::{Connection, Handshake, SendResponse};
-use h2::Reason;
+use h2::{Reason, RecvStream};
+use http::{Method, Request};
 use pin_project::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-use super::{decode_content_length, ping, PipeToSendStream, SendBuf};
+use super::{decode_content_length, ping, H2Upgraded, PipeToSendStream, SendBuf};
 use crate::body::HttpBody;
 use crate::common::exec::ConnStreamExec;
 use crate::common::{task, Future, Pin, Poll};
 use crate::headers;
+use crate::proto::info::AddrInfo;
+use crate::proto::metrics::ConnectionMetrics;
 use crate::proto::Dispatched;
+use crate::server::push::{Push, Pusher};
 use crate::service::HttpService;
+use crate::upgrade::{Pending, Upgraded};
 
 use crate::{Body, Response};
 
@@ -35,6 +43,7 @@ pub(crate) struct Config {
     pub(crate) initial_stream_window_size: u32,
     pub(crate) max_frame_size: u32,
     pub(crate) max_concurrent_streams: Option<u32>,
+    pub(crate) enable_push: bool,
     #[cfg(feature = "runtime")]
     pub(crate) keep_alive_interval: Option<Duration>,
     #[cfg(feature = "runtime")]
@@ -49,6 +58,7 @@ impl Default for Config {
             initial_stream_window_size: DEFAULT_STREAM_WINDOW,
             max_frame_size: DEFAULT_MAX_FRAME_SIZE,
             max_concurrent_streams: None,
+            enable_push: false,
             #[cfg(feature = "runtime")]
             keep_alive_interval: None,
             #[cfg(feature = "runtime")]
@@ -66,6 +76,7 @@ where
     exec: E,
     service: S,
     state: State<T, B>,
+    addr_info: Option<AddrInfo>,
 }
 
 enum State<T, B>
@@ -75,6 +86,8 @@ where
     Handshaking {
         ping_config: ping::Config,
         hs: Handshake<T, SendBuf<B::Data>>,
+        enable_push: bool,
+        metrics: ConnectionMetrics,
     },
     Serving(Serving<T, B>),
     Closed,
@@ -87,6 +100,8 @@ where
     ping: Option<(ping::Recorder, ping::Ponger)>,
     conn: Connection<T, SendBuf<B::Data>>,
     closing: Option<crate::Error>,
+    enable_push: bool,
+    metrics: ConnectionMetrics,
 }
 
 impl<T, S, B, E> Server<T, S, B, E>
@@ -97,7 +112,13 @@ where
     B: HttpBody + 'static,
     E: ConnStreamExec<S::Future, B>,
 {
-    pub(crate) fn new(io: T, service: S, config: &Config, exec: E) -> Server<T, S, B, E> {
+    pub(crate) fn new(
+        io: T,
+        service: S,
+        config: &Config,
+        exec: E,
+        metrics: ConnectionMetrics,
+    ) -> Server<T, S, B, E> {
         let mut builder = h2::server::Builder::default();
         builder
             .initial_window_size(config.initial_stream_window_size)
@@ -107,6 +128,7 @@ where
             builder.max_concurrent_streams(max);
         }
         let handshake = builder.handshake(io);
+        metrics.set_h2();
 
         let bdp = if config.adaptive_window {
             Some(config.initial_stream_window_size)
@@ -131,11 +153,18 @@ where
             state: State::Handshaking {
                 ping_config,
                 hs: handshake,
+                enable_push: config.enable_push,
+                metrics,
             },
             service,
+            addr_info: None,
         }
     }
 
+    pub(crate) fn set_addr_info(&mut self, info: AddrInfo) {
+        self.addr_info = Some(info);
+    }
+
     pub fn graceful_shutdown(&mut self) {
         trace!("graceful_shutdown");
         match self.state {
@@ -173,6 +202,8 @@ where
                 State::Handshaking {
                     ref mut hs,
                     ref ping_config,
+                    enable_push,
+                    ref metrics,
                 } => {
                     let mut conn = ready!(Pin::new(hs).poll(cx).map_err(crate::Error::new_h2))?;
                     let ping = if ping_config.is_enabled() {
@@ -185,10 +216,12 @@ where
                         ping,
                         conn,
                         closing: None,
+                        enable_push,
+                        metrics: metrics.clone(),
                     })
                 }
                 State::Serving(ref mut srv) => {
-                    ready!(srv.poll_server(cx, &mut me.service, &mut me.exec))?;
+                    ready!(srv.poll_server(cx, &mut me.service, &mut me.exec, &mut me.addr_info))?;
                     return Poll::Ready(Ok(Dispatched::Shutdown));
                 }
                 State::Closed => {
@@ -212,6 +245,7 @@ where
         cx: &mut task::Context<'_>,
         service: &mut S,
         exec: &mut E,
+        addr_info: &mut Option<AddrInfo>,
     ) -> Poll<crate::Result<()>>
     where
         S: HttpService<Body, ResBody = B>,
@@ -255,8 +289,12 @@ where
 
                 // When the service is ready, accepts an incoming request.
                 match ready!(self.conn.poll_accept(cx)) {
-                    Some(Ok((req, respond))) => {
+                    Some(Ok((mut req, respond))) => {
                         trace!("incoming request");
+                        if let Some(ref mut info) = *addr_info {
+                            req.extensions_mut().insert(info.next_request());
+                        }
+                        self.metrics.record_h2_request();
                         let content_length = decode_content_length(req.headers());
                         let ping = self
                             .ping
@@ -267,8 +305,30 @@ where
                         // Record the headers received
                         ping.record_non_data();
 
-                        let req = req.map(|stream| crate::Body::h2(stream, content_length, ping));
-                        let fut = H2Stream::new(service.call(req), respond);
+                        let (req, connect_parts, pushes) = if req.method() == Method::CONNECT {
+                            let (parts, recv_stream) = req.into_parts();
+                            let (pending, on_upgrade) = crate::upgrade::pending();
+                            let mut body = Body::empty();
+                            body.set_on_upgrade(on_upgrade);
+                            let connect_parts = ConnectParts {
+                                pending,
+                                ping,
+                                recv_stream,
+                            };
+                            (Request::from_parts(parts, body), Some(connect_parts), None)
+                        } else {
+                            let mut req =
+                                req.map(|stream| crate::Body::h2(stream, content_length, ping));
+                            let pushes = if self.enable_push {
+                                let (pusher, rx) = Pusher::channel();
+                                req.extensions_mut().insert(pusher);
+                                Some(Box::new(Pushes::new(rx)))
+                            } else {
+                                None
+                            };
+                            (req, None, pushes)
+                        };
+                        let fut = H2Stream::new(service.call(req), connect_parts, pushes, respond);
                         exec.execute_h2stream(fut);
                     }
                     Some(Err(e)) => {
@@ -322,16 +382,25 @@ where
     B: HttpBody,
 {
     reply: SendResponse<SendBuf<B::Data>>,
+    pushes: Option<Box<Pushes<B::Data>>>,
     #[pin]
     state: H2StreamState<F, B>,
 }
 
+/// The parts of a tunnel request, to upgrade its stream once the response
+/// is sent.
+struct ConnectParts {
+    pending: Pending,
+    ping: ping::Recorder,
+    recv_stream: RecvStream,
+}
+
 #[pin_project(project = H2StreamStateProj)]
 enum H2StreamState<F, B>
 where
     B: HttpBody,
 {
-    Service(#[pin] F),
+    Service(#[pin] F, Option<ConnectParts>),
     Body(#[pin] PipeToSendStream<B>),
 }
 
@@ -339,10 +408,93 @@ impl<F, B> H2Stream<F, B>
 where
     B: HttpBody,
 {
-    fn new(fut: F, respond: SendResponse<SendBuf<B::Data>>) -> H2Stream<F, B> {
+    fn new(
+        fut: F,
+        connect_parts: Option<ConnectParts>,
+        pushes: Option<Box<Pushes<B::Data>>>,
+        respond: SendResponse<SendBuf<B::Data>>,
+    ) -> H2Stream<F, B> {
         H2Stream {
             reply: respond,
-            state: H2StreamState::Service(fut),
+            pushes,
+            state: H2StreamState::Service(fut, connect_parts),
+        }
+    }
+}
+
+/// The pushes of a stream, queued by its `Pusher`, and the bodies of the
+/// pushed responses being sent.
+struct Pushes<D> {
+    // `None` once the stream's own response is complete.
+    rx: Option<mpsc::UnboundedReceiver<Push>>,
+    pipes: Vec<Pin<Box<PipeToSendStream<Body, D>>>>,
+}
+
+impl<D: Buf> Pushes<D> {
+    fn new(rx: mpsc::UnboundedReceiver<Push>) -> Pushes<D> {
+        Pushes {
+            rx: Some(rx),
+            pipes: Vec::new(),
+        }
+    }
+
+    /// Sends a `PUSH_PROMISE` and the response headers for each queued push.
+    fn poll_promises(&mut self, reply: &mut SendResponse<SendBuf<D>>, cx: &mut task::Context<'_>) {
+        let rx = match self.rx {
+            Some(ref mut rx) => rx,
+            None => return,
+        };
+        while let Poll::Ready(Some(push)) = rx.poll_next_unpin(cx) {
+            let result = match reply.push_request(push.request) {
+                Ok(mut pushed) => {
+                    let (head, body) = push.response.into_parts();
+                    let mut res = ::http::Response::from_parts(head, ());
+                    super::strip_connection_headers(res.headers_mut(), false);
+                    res.headers_mut()
+                        .entry(::http::header::DATE)
+                        .or_insert_with(crate::proto::h1::date::update_and_header_value);
+                    if let Some(len) = body.size_hint().exact() {
+                        headers::set_content_length_if_missing(res.headers_mut(), len);
+                    }
+
+                    let eos = body.is_end_stream();
+                    match pushed.send_response(res, eos) {
+                        Ok(body_tx) => {
+                            if !eos {
+                                self.pipes
+                                    .push(Box::pin(PipeToSendStream::from_bytes(body, body_tx)));
+                            }
+                            Ok(())
+                        }
+                        Err(e) => Err(crate::Error::new_h2(e)),
+                    }
+                }
+                Err(e) => Err(crate::Error::new_h2(e)),
+            };
+            if let Err(ref e) = result {
+                debug!("push error: {}", e);
+            }
+            let _ = push.tx.send(result);
+        }
+    }
+
+    /// Drives the bodies of the pushed responses, until all are sent.
+    fn poll_pipes(&mut self, cx: &mut task::Context<'_>) -> Poll<()> {
+        let mut i = 0;
+        while i < self.pipes.len() {
+            if let Poll::Ready(res) = self.pipes[i].as_mut().poll(cx) {
+                if let Err(e) = res {
+                    debug!("pushed stream error: {}", e);
+                }
+                self.pipes.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        if self.pipes.is_empty() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
         }
     }
 }
@@ -363,7 +515,7 @@ macro_rules! reply {
 impl<F, B, E> H2Stream<F, B>
 where
     F: Future<Output = Result<Response<B>, E>>,
-    B: HttpBody,
+    B: HttpBody + 'static,
     B::Error: Into<Box<dyn StdError + Send + Sync>>,
     E: Into<Box<dyn StdError + Send + Sync>>,
 {
@@ -371,8 +523,14 @@ where
         let mut me = self.project();
         loop {
             let next = match me.state.as_mut().project() {
-                H2StreamStateProj::Service(h) => {
-                    let res = match h.poll(cx) {
+                H2StreamStateProj::Service(h, connect_parts) => {
+                    let res = h.poll(cx);
+                    // Pushes must be promised before the response is sent, in
+                    // case it ends the stream.
+                    if let Some(ref mut pushes) = me.pushes {
+                        pushes.poll_promises(me.reply, cx);
+                    }
+                    let res = match res {
                         Poll::Ready(Ok(r)) => r,
                         Poll::Pending => {
                             // Response is not yet ready, so we want to check if the client has sent a
@@ -407,6 +565,28 @@ where
                         headers::set_content_length_if_missing(res.headers_mut(), len);
                     }
 
+                    if let Some(connect_parts) = connect_parts.take() {
+                        if res.status().is_success() {
+                            if headers::content_length_parse_all(res.headers())
+                                .map_or(false, |len| len != 0)
+                            {
+                                warn!("h2 successful response to CONNECT request with body not supported");
+                                me.reply.send_reset(Reason::INTERNAL_ERROR);
+                                return Poll::Ready(Err(crate::Error::new_user_header()));
+                            }
+                            let send_stream = reply!(me, res, false);
+                            let io = H2Upgraded::new(
+                                connect_parts.ping,
+                                send_stream,
+                                connect_parts.recv_stream,
+                            );
+                            connect_parts
+                                .pending
+                                .fulfill(Upgraded::new(io, Bytes::new()));
+                            return Poll::Ready(Ok(()));
+                        }
+                    }
+
                     if !body.is_end_stream() {
                         let body_tx = reply!(me, res, false);
                         H2StreamState::Body(PipeToSendStream::new(body, body_tx))
@@ -416,6 +596,9 @@ where
                     }
                 }
                 H2StreamStateProj::Body(pipe) => {
+                    if let Some(ref mut pushes) = me.pushes {
+                        pushes.poll_promises(me.reply, cx);
+                    }
                     return pipe.poll(cx);
                 }
             };
@@ -427,17 +610,42 @@ where
 impl<F, B, E> Future for H2Stream<F, B>
 where
     F: Future<Output = Result<Response<B>, E>>,
-    B: HttpBody,
+    B: HttpBody + 'static,
     B::Error: Into<Box<dyn StdError + Send + Sync>>,
     E: Into<Box<dyn StdError + Send + Sync>>,
 {
     type Output = ();
 
-    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
-        self.poll2(cx).map(|res| {
-            if let Err(e) = res {
-                debug!("stream error: {}", e);
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let responding = self
+            .pushes
+            .as_ref()
+            .map_or(true, |pushes| pushes.rx.is_some());
+        if responding {
+            let res = self.as_mut().poll2(cx).map(|res| {
+                if let Err(e) = res {
+                    debug!("stream error: {}", e);
+                }
+            });
+            match self.as_mut().project().pushes {
+                Some(pushes) => {
+                    if res.is_ready() {
+                        // No more pushes can be promised once the response is
+                        // complete, but the pushed responses may still be
+                        // sending their bodies.
+                        pushes.rx = None;
+                    }
+                }
+                None => return res,
             }
-        })
+        }
+
+        let pushes = self.project().pushes.as_mut().expect("pushes");
+        ready!(pushes.poll_pipes(cx));
+        if pushes.rx.is_some() {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
     }
 }
@@ -0,0 +1,68 @@
+use std::net::SocketAddr;
+use std::time::Instant;
+
+/// Information about the connection a request was received on.
+///
+/// A `Server` puts this in the extensions of every request, if the `Accept`
+/// it serves knows the addresses of its connections, such as the
+/// `AddrStream`s of an `AddrIncoming`.
+///
+/// # Example
+///
+/// ```
+/// use hyper::{Body, Request};
+/// use hyper::server::conn::AddrInfo;
+///
+/// fn log(req: &Request<Body>) {
+///     if let Some(info) = req.extensions().get::<AddrInfo>() {
+///         println!("{} from {}", req.uri(), info.remote_addr());
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AddrInfo {
+    remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    accepted_at: Instant,
+    is_reused: bool,
+}
+
+impl AddrInfo {
+    pub(crate) fn new(remote_addr: SocketAddr, local_addr: SocketAddr) -> AddrInfo {
+        AddrInfo {
+            remote_addr,
+            local_addr,
+            accepted_at: Instant::now(),
+            is_reused: false,
+        }
+    }
+
+    /// Get the remote (peer) address of the connection.
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// Get the local address the connection was accepted on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns when the connection was accepted.
+    pub fn accepted_at(&self) -> Instant {
+        self.accepted_at
+    }
+
+    /// Returns whether the request was received on a connection that earlier
+    /// requests were received on.
+    pub fn is_reused(&self) -> bool {
+        self.is_reused
+    }
+
+    /// Returns the info to put in the next request, marking any after it
+    /// as reused.
+    pub(crate) fn next_request(&mut self) -> AddrInfo {
+        let info = self.clone();
+        self.is_reused = true;
+        info
+    }
+}
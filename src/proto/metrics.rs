@@ -0,0 +1,310 @@
+use std::fmt;
+#[cfg(feature = "http2")]
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http::Version;
+#[cfg(feature = "http2")]
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+#[cfg(feature = "http2")]
+use crate::common::{task, Pin, Poll};
+
+/// Statistics of a single server connection.
+///
+/// This is a handle to statistics that keep being updated while the
+/// connection is served, even after the `Connection` is gone. Clones of it
+/// refer to the same connection.
+#[derive(Clone)]
+pub struct ConnectionMetrics {
+    inner: Arc<Counts>,
+}
+
+struct Counts {
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    requests: AtomicU64,
+    keep_alive_reuses: AtomicU64,
+    // The version of the last request, see `version_to_u8`.
+    version: AtomicU8,
+    created_at: Instant,
+    // Nanoseconds from `created_at` to when bytes were last read or written.
+    last_active: AtomicU64,
+}
+
+/// An IO that records the bytes read and written in `ConnectionMetrics`.
+///
+/// HTTP/1 connections record them in their buffers instead.
+#[cfg(feature = "http2")]
+#[derive(Debug)]
+pub(crate) struct Metered<T> {
+    io: T,
+    metrics: ConnectionMetrics,
+}
+
+// ===== impl ConnectionMetrics =====
+
+impl ConnectionMetrics {
+    pub(crate) fn new() -> ConnectionMetrics {
+        ConnectionMetrics {
+            inner: Arc::new(Counts {
+                bytes_read: AtomicU64::new(0),
+                bytes_written: AtomicU64::new(0),
+                requests: AtomicU64::new(0),
+                keep_alive_reuses: AtomicU64::new(0),
+                version: AtomicU8::new(version_to_u8(Version::HTTP_11)),
+                created_at: Instant::now(),
+                last_active: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns the number of bytes read from the connection.
+    pub fn bytes_read(&self) -> u64 {
+        self.inner.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes written to the connection.
+    pub fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests received on the connection.
+    pub fn requests(&self) -> u64 {
+        self.inner.requests.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of HTTP/1 requests that reused the connection,
+    /// which is every HTTP/1 request after the first.
+    ///
+    /// HTTP/2 requests share the connection at the same time instead, and
+    /// aren't counted.
+    pub fn keep_alive_reuses(&self) -> u64 {
+        self.inner.keep_alive_reuses.load(Ordering::Relaxed)
+    }
+
+    /// Returns how long it has been since bytes were last read from or
+    /// written to the connection.
+    pub fn idle_time(&self) -> Duration {
+        let last_active = Duration::from_nanos(self.inner.last_active.load(Ordering::Relaxed));
+        self.inner
+            .created_at
+            .elapsed()
+            .checked_sub(last_active)
+            .unwrap_or_default()
+    }
+
+    /// Returns the HTTP version of the connection.
+    ///
+    /// For HTTP/1 connections, this is the version of the last request
+    /// received, and `HTTP/1.1` until there has been one. It changes to
+    /// `HTTP/2.0` if the connection is upgraded.
+    pub fn version(&self) -> Version {
+        version_from_u8(self.inner.version.load(Ordering::Relaxed))
+    }
+
+    #[cfg(feature = "http2")]
+    pub(crate) fn set_h2(&self) {
+        self.set_version(Version::HTTP_2);
+    }
+
+    fn set_version(&self, version: Version) {
+        self.inner
+            .version
+            .store(version_to_u8(version), Ordering::Relaxed);
+    }
+
+    /// Records a request received with HTTP/1, and the version it was
+    /// parsed with.
+    pub(crate) fn record_h1_request(&self, version: Version) {
+        self.set_version(version);
+        // HTTP/1 requests come before any upgrade to HTTP/2, so all but the
+        // first reused the connection.
+        if self.inner.requests.fetch_add(1, Ordering::Relaxed) > 0 {
+            self.inner.keep_alive_reuses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a request received with HTTP/2.
+    #[cfg(feature = "http2")]
+    pub(crate) fn record_h2_request(&self) {
+        self.inner.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_read(&self, n: usize) {
+        self.inner.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        self.record_active();
+    }
+
+    pub(crate) fn record_written(&self, n: usize) {
+        self.inner
+            .bytes_written
+            .fetch_add(n as u64, Ordering::Relaxed);
+        self.record_active();
+    }
+
+    fn record_active(&self) {
+        let nanos = self.inner.created_at.elapsed().as_nanos() as u64;
+        self.inner.last_active.store(nanos, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for ConnectionMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionMetrics")
+            .field("bytes_read", &self.bytes_read())
+            .field("bytes_written", &self.bytes_written())
+            .field("requests", &self.requests())
+            .field("version", &self.version())
+            .finish()
+    }
+}
+
+fn version_to_u8(version: Version) -> u8 {
+    if version == Version::HTTP_09 {
+        0
+    } else if version == Version::HTTP_10 {
+        1
+    } else if version == Version::HTTP_2 {
+        3
+    } else if version == Version::HTTP_3 {
+        4
+    } else {
+        2
+    }
+}
+
+fn version_from_u8(version: u8) -> Version {
+    match version {
+        0 => Version::HTTP_09,
+        1 => Version::HTTP_10,
+        3 => Version::HTTP_2,
+        4 => Version::HTTP_3,
+        _ => Version::HTTP_11,
+    }
+}
+
+// ===== impl Metered =====
+
+#[cfg(feature = "http2")]
+impl<T> Metered<T> {
+    pub(crate) fn new(io: T, metrics: ConnectionMetrics) -> Metered<T> {
+        Metered { io, metrics }
+    }
+}
+
+#[cfg(feature = "http2")]
+impl<T: AsyncRead + Unpin> AsyncRead for Metered<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut self.io).poll_read(cx, buf))?;
+        let n = buf.filled().len() - filled;
+        if n > 0 {
+            self.metrics.record_read(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "http2")]
+impl<T: AsyncWrite + Unpin> AsyncWrite for Metered<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let n = ready!(Pin::new(&mut self.io).poll_write(cx, buf))?;
+        if n > 0 {
+            self.metrics.record_written(n);
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::Version;
+
+    use super::ConnectionMetrics;
+
+    #[test]
+    fn counts_h1_keep_alive_reuses() {
+        let metrics = ConnectionMetrics::new();
+        metrics.record_h1_request(Version::HTTP_11);
+        assert_eq!(metrics.keep_alive_reuses(), 0);
+        metrics.record_h1_request(Version::HTTP_11);
+        metrics.record_h1_request(Version::HTTP_11);
+        assert_eq!(metrics.requests(), 3);
+        assert_eq!(metrics.keep_alive_reuses(), 2);
+    }
+
+    #[test]
+    fn h1_version_is_the_request_version() {
+        let metrics = ConnectionMetrics::new();
+        assert_eq!(metrics.version(), Version::HTTP_11);
+        metrics.record_h1_request(Version::HTTP_10);
+        assert_eq!(metrics.version(), Version::HTTP_10);
+        metrics.record_h1_request(Version::HTTP_11);
+        assert_eq!(metrics.version(), Version::HTTP_11);
+    }
+
+    #[cfg(feature = "http2")]
+    #[test]
+    fn h2_requests_are_not_reuses() {
+        let metrics = ConnectionMetrics::new();
+        metrics.record_h1_request(Version::HTTP_11);
+        metrics.set_h2();
+        metrics.record_h2_request();
+        metrics.record_h2_request();
+        assert_eq!(metrics.requests(), 3);
+        assert_eq!(metrics.keep_alive_reuses(), 0);
+        assert_eq!(metrics.version(), Version::HTTP_2);
+    }
+
+    #[test]
+    fn idle_time_resets_on_activity() {
+        let metrics = ConnectionMetrics::new();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(metrics.idle_time() >= Duration::from_millis(20));
+        metrics.record_read(5);
+        assert!(metrics.idle_time() < Duration::from_millis(20));
+        assert_eq!(metrics.bytes_read(), 5);
+    }
+
+    #[cfg(feature = "http2")]
+    #[tokio::test]
+    async fn metered_counts_bytes() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mock = tokio_test::io::Builder::new()
+            .read(b"hello")
+            .write(b"world!")
+            .build();
+        let metrics = ConnectionMetrics::new();
+        let mut io = super::Metered::new(mock, metrics.clone());
+
+        let mut buf = [0; 5];
+        io.read_exact(&mut buf).await.expect("read");
+        io.write_all(b"world!").await.expect("write");
+
+        assert_eq!(metrics.bytes_read(), 5);
+        assert_eq!(metrics.bytes_written(), 6);
+        assert_eq!(metrics.version(), Version::HTTP_11);
+    }
+}
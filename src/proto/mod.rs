@@ -1,16 +1,18 @@
 //! Pieces pertaining to the HTTP message protocol.
-use http::{HeaderMap, Method, StatusCode, Uri, Version};
+use http::{Extensions, HeaderMap, Method, StatusCode, Uri, Version};
 
 pub(crate) use self::body_length::DecodedLength;
 pub(crate) use self::h1::{dispatch, Conn, ServerTransaction};
 
 pub(crate) mod h1;
+pub(crate) mod info;
+pub(crate) mod metrics;
 cfg_http2! {
     pub(crate) mod h2;
 }
 
 /// An Incoming Message head. Includes request/status line, and headers.
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Debug, Default)]
 pub struct MessageHead<S> {
     /// HTTP version of the message.
     pub version: Version,
@@ -18,6 +20,8 @@ pub struct MessageHead<S> {
     pub subject: S,
     /// Headers of the Incoming message.
     pub headers: HeaderMap,
+    /// Extensions, such as the original header case and order.
+    pub(crate) extensions: Extensions,
 }
 
 /// An incoming request message.
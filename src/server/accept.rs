@@ -6,6 +6,8 @@
 //!   connections.
 //! - Utilities like `poll_fn` to ease creating a custom `Accept`.
 
+use std::net::SocketAddr;
+
 #[cfg(feature = "stream")]
 use futures_core::Stream;
 #[cfg(feature = "stream")]
@@ -28,6 +30,33 @@ pub trait Accept {
         self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
     ) -> Poll<Option<Result<Self::Conn, Self::Error>>>;
+
+    /// Returns whether HTTP/2 was negotiated for an accepted connection,
+    /// such as with TLS ALPN.
+    ///
+    /// If `Some(true)`, the `Server` serves the connection with HTTP/2, and if
+    /// `Some(false)`, with HTTP/1. The default returns `None`, which leaves
+    /// the choice to the `Server`'s configuration.
+    fn negotiated_h2(&self, _conn: &Self::Conn) -> Option<bool> {
+        None
+    }
+
+    /// Returns the remote address of an accepted connection, if known.
+    ///
+    /// This is the address given to the `Server`'s connection hooks. The
+    /// default returns `None`.
+    fn remote_addr(&self, _conn: &Self::Conn) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Returns the local address of an accepted connection, if known.
+    ///
+    /// If both addresses of a connection are known, the `Server` puts an
+    /// [`AddrInfo`](crate::server::conn::AddrInfo) in the extensions of its
+    /// requests. The default returns `None`.
+    fn local_addr(&self, _conn: &Self::Conn) -> Option<SocketAddr> {
+        None
+    }
 }
 
 /// Create an `Accept` with a polling function.
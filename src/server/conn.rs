@@ -47,16 +47,18 @@ use std::error::Error as StdError;
 use std::fmt;
 use std::marker::PhantomData;
 use std::mem;
-#[cfg(feature = "tcp")]
 use std::net::SocketAddr;
+use std::sync::Arc;
 #[cfg(feature = "runtime")]
-#[cfg(feature = "http2")]
 use std::time::Duration;
 
 use bytes::Bytes;
+use http::Version;
 use pin_project::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite};
 
+#[cfg(feature = "http2")]
+use super::h2c::H2cIo;
 use super::Accept;
 use crate::body::{Body, HttpBody};
 use crate::common::exec::{ConnStreamExec, Exec, NewSvcExec};
@@ -66,6 +68,8 @@ use crate::common::{task, Future, Pin, Poll, Unpin};
 #[cfg(feature = "http2")]
 use crate::error::{Kind, Parse};
 use crate::proto;
+#[cfg(feature = "http2")]
+use crate::proto::metrics::Metered;
 use crate::service::{HttpService, MakeServiceRef};
 use crate::upgrade::Upgraded;
 
@@ -74,8 +78,14 @@ pub(super) use self::spawn_all::NoopWatcher;
 pub(super) use self::spawn_all::Watcher;
 pub(super) use self::upgrades::UpgradeableConnection;
 
+#[cfg(feature = "http2")]
+pub use super::push::Pusher;
 #[cfg(feature = "tcp")]
 pub use super::tcp::{AddrIncoming, AddrStream};
+#[cfg(all(unix, feature = "unix"))]
+pub use super::unix::UnixIncoming;
+pub use crate::proto::info::AddrInfo;
+pub use crate::proto::metrics::ConnectionMetrics;
 
 /// A lower-level configuration of the HTTP protocol.
 ///
@@ -88,9 +98,16 @@ pub struct Http<E = Exec> {
     exec: E,
     h1_half_close: bool,
     h1_keep_alive: bool,
+    #[cfg(feature = "runtime")]
+    h1_header_read_timeout: Option<Duration>,
+    h1_max_headers: Option<usize>,
+    h1_preserve_header_case: bool,
+    h1_preserve_header_order: bool,
     h1_writev: Option<bool>,
     #[cfg(feature = "http2")]
     h2_builder: proto::h2::server::Config,
+    #[cfg(feature = "http2")]
+    h2c_upgrade: bool,
     mode: ConnectionMode,
     max_buf_size: Option<usize>,
     pipeline_flush: bool,
@@ -134,6 +151,8 @@ pub struct Connecting<I, F, E = Exec> {
     future: F,
     io: Option<I>,
     protocol: Http<E>,
+    remote_addr: Option<SocketAddr>,
+    addr_info: Option<AddrInfo>,
 }
 
 #[must_use = "futures do nothing unless polled"]
@@ -145,8 +164,22 @@ pub(super) struct SpawnAll<I, S, E> {
     // See https://github.com/rust-lang/rust/issues/64705
     #[pin]
     pub serve: Serve<I, S, E>,
+    hooks: Hooks,
 }
 
+/// Callbacks of a `Server` for the connections it serves.
+#[derive(Clone, Default)]
+pub(super) struct Hooks {
+    pub(super) on_accept: Option<AcceptHook>,
+    pub(super) on_connection: Option<Arc<dyn Fn(ConnectionMetrics) + Send + Sync>>,
+    pub(super) on_protocol: Option<ProtocolHook>,
+    pub(super) on_close: Option<CloseHook>,
+}
+
+type AcceptHook = Arc<dyn Fn(Option<SocketAddr>) -> bool + Send + Sync>;
+type ProtocolHook = Arc<dyn Fn(Option<SocketAddr>, Version) + Send + Sync>;
+type CloseHook = Arc<dyn Fn(Option<SocketAddr>, Option<&crate::Error>) + Send + Sync>;
+
 /// A future binding a connection with a Service.
 ///
 /// Polling this future will drive HTTP forward.
@@ -159,6 +192,9 @@ where
     pub(super) conn: Option<ProtoServer<T, S::ResBody, S, E>>,
     #[cfg(feature = "http2")]
     fallback: Fallback<E>,
+    metrics: ConnectionMetrics,
+    on_protocol: Option<Box<dyn Fn(Version) + Send + Sync>>,
+    protocol: Option<Version>,
 }
 
 #[pin_project(project = ProtoServerProj)]
@@ -178,7 +214,7 @@ where
         PhantomData<E>,
     ),
     #[cfg(feature = "http2")]
-    H2(#[pin] proto::h2::Server<Rewind<T>, S, B, E>),
+    H2(#[pin] proto::h2::Server<H2cIo<Metered<T>>, S, B, E>),
 }
 
 #[cfg(feature = "http2")]
@@ -233,9 +269,16 @@ impl Http {
             exec: Exec::Default,
             h1_half_close: false,
             h1_keep_alive: true,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout: None,
+            h1_max_headers: None,
+            h1_preserve_header_case: false,
+            h1_preserve_header_order: false,
             h1_writev: None,
             #[cfg(feature = "http2")]
             h2_builder: Default::default(),
+            #[cfg(feature = "http2")]
+            h2c_upgrade: false,
             mode: ConnectionMode::default(),
             max_buf_size: None,
             pipeline_flush: false,
@@ -259,6 +302,24 @@ impl<E> Http<E> {
         self
     }
 
+    /// Serve a connection with the protocol that was negotiated for it.
+    fn negotiated_h2(&mut self, h2: bool) {
+        #[cfg(feature = "http2")]
+        {
+            self.mode = if h2 {
+                ConnectionMode::H2Only
+            } else {
+                ConnectionMode::H1Only
+            };
+        }
+        #[cfg(not(feature = "http2"))]
+        {
+            if h2 {
+                debug!("HTTP/2 was negotiated, but the http2 feature is disabled");
+            }
+        }
+    }
+
     /// Set whether HTTP/1 connections should support half-closures.
     ///
     /// Clients can chose to shutdown their write-side while waiting
@@ -287,6 +348,68 @@ impl<E> Http<E> {
         self.http1_keep_alive(val)
     }
 
+    /// Set a timeout for reading client request headers. If a client does not
+    /// transmit the entire header within this time, the connection is closed.
+    ///
+    /// If some bytes of the request head were already received, a
+    /// `408 Request Timeout` response is sent before closing.
+    ///
+    /// Default is None.
+    ///
+    /// # Cargo Feature
+    ///
+    /// Requires the `runtime` cargo feature to be enabled.
+    #[cfg(feature = "runtime")]
+    pub fn http1_header_read_timeout(&mut self, read_timeout: Duration) -> &mut Self {
+        self.h1_header_read_timeout = Some(read_timeout);
+        self
+    }
+
+    /// Set the maximum number of headers allowed in a request.
+    ///
+    /// If a client sends more headers than this, a `431 Request Header
+    /// Fields Too Large` response is sent and the connection is closed.
+    ///
+    /// Up to 100 headers are parsed using storage on the stack. A larger
+    /// maximum means the storage is allocated on the heap for every
+    /// request instead, which is somewhat slower.
+    ///
+    /// Default is 100.
+    pub fn http1_max_headers(&mut self, val: usize) -> &mut Self {
+        self.h1_max_headers = Some(val);
+        self
+    }
+
+    /// Set whether to record the original case of request header names.
+    ///
+    /// The original names are stored in the extensions of the `Request`.
+    /// If those extensions are moved to a `Response`, or to a `Request`
+    /// sent with a `Client`, its headers are written with the recorded case.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is false.
+    pub fn http1_preserve_header_case(&mut self, enabled: bool) -> &mut Self {
+        self.h1_preserve_header_case = enabled;
+        self
+    }
+
+    /// Set whether to record the original order of request headers.
+    ///
+    /// The original order is stored in the extensions of the `Request`.
+    /// If those extensions are moved to a `Response`, or to a `Request`
+    /// sent with a `Client`, its headers are written in the recorded order.
+    /// Headers that weren't part of the original message are written
+    /// afterwards.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is false.
+    pub fn http1_preserve_header_order(&mut self, enabled: bool) -> &mut Self {
+        self.h1_preserve_header_order = enabled;
+        self
+    }
+
     /// Set whether HTTP/1 connections should try to use vectored writes,
     /// or always flatten into a single buffer.
     ///
@@ -400,6 +523,20 @@ impl<E> Http<E> {
         self
     }
 
+    /// Sets whether HTTP2 requests can push responses to the client.
+    ///
+    /// If enabled, a [`Pusher`] is placed in the extensions of each HTTP2
+    /// request, to send `PUSH_PROMISE` frames on its stream. Clients may
+    /// still refuse pushes, by disabling them in their settings.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_enable_push(&mut self, enabled: bool) -> &mut Self {
+        self.h2_builder.enable_push = enabled;
+        self
+    }
+
     /// Sets an interval for HTTP2 Ping frames should be sent to keep a
     /// connection alive.
     ///
@@ -439,6 +576,24 @@ impl<E> Http<E> {
         self
     }
 
+    /// Set whether HTTP/1 connections can be upgraded to HTTP/2.
+    ///
+    /// If enabled, a client can ask to switch a connection to cleartext
+    /// HTTP/2 with `Upgrade: h2c` and `HTTP2-Settings` headers on its first
+    /// request. The server then responds with `101 Switching Protocols`, and
+    /// the request is served as the first stream of the HTTP/2 connection.
+    /// Requests with a body aren't upgraded.
+    ///
+    /// This does nothing if `http1_only` or `http2_only` is set.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_h2c_upgrade(&mut self, enabled: bool) -> &mut Self {
+        self.h2c_upgrade = enabled;
+        self
+    }
+
     /// Set the maximum buffer size for the connection.
     ///
     /// Default is ~400kb.
@@ -473,9 +628,16 @@ impl<E> Http<E> {
             exec,
             h1_half_close: self.h1_half_close,
             h1_keep_alive: self.h1_keep_alive,
+            #[cfg(feature = "runtime")]
+            h1_header_read_timeout: self.h1_header_read_timeout,
+            h1_max_headers: self.h1_max_headers,
+            h1_preserve_header_case: self.h1_preserve_header_case,
+            h1_preserve_header_order: self.h1_preserve_header_order,
             h1_writev: self.h1_writev,
             #[cfg(feature = "http2")]
             h2_builder: self.h2_builder,
+            #[cfg(feature = "http2")]
+            h2c_upgrade: self.h2c_upgrade,
             mode: self.mode,
             max_buf_size: self.max_buf_size,
             pipeline_flush: self.pipeline_flush,
@@ -519,6 +681,8 @@ impl<E> Http<E> {
         I: AsyncRead + AsyncWrite + Unpin,
         E: ConnStreamExec<S::Future, Bd>,
     {
+        let metrics = ConnectionMetrics::new();
+
         macro_rules! h1 {
             () => {{
                 let mut conn = proto::Conn::new(io);
@@ -528,6 +692,21 @@ impl<E> Http<E> {
                 if self.h1_half_close {
                     conn.set_allow_half_close();
                 }
+                #[cfg(feature = "runtime")]
+                {
+                    if let Some(header_read_timeout) = self.h1_header_read_timeout {
+                        conn.set_http1_header_read_timeout(header_read_timeout);
+                    }
+                }
+                if let Some(max) = self.h1_max_headers {
+                    conn.set_http1_max_headers(max);
+                }
+                if self.h1_preserve_header_case {
+                    conn.set_preserve_header_case();
+                }
+                if self.h1_preserve_header_order {
+                    conn.set_preserve_header_order();
+                }
                 if let Some(writev) = self.h1_writev {
                     if writev {
                         conn.set_write_strategy_queue();
@@ -539,7 +718,15 @@ impl<E> Http<E> {
                 if let Some(max) = self.max_buf_size {
                     conn.set_max_buf_size(max);
                 }
-                let sd = proto::h1::dispatch::Server::new(service);
+                conn.set_metrics(metrics.clone());
+                let mut sd = proto::h1::dispatch::Server::new(service);
+                sd.set_metrics(metrics.clone());
+                #[cfg(feature = "http2")]
+                {
+                    if self.h2c_upgrade && self.mode == ConnectionMode::Fallback {
+                        sd.enable_h2c_upgrade();
+                    }
+                }
                 ProtoServer::H1(proto::h1::Dispatcher::new(sd, conn), PhantomData)
             }};
         }
@@ -551,9 +738,14 @@ impl<E> Http<E> {
             ConnectionMode::H1Only | ConnectionMode::Fallback => h1!(),
             #[cfg(feature = "http2")]
             ConnectionMode::H2Only => {
-                let rewind_io = Rewind::new(io);
-                let h2 =
-                    proto::h2::Server::new(rewind_io, service, &self.h2_builder, self.exec.clone());
+                let io = H2cIo::new(Rewind::new(Metered::new(io, metrics.clone())));
+                let h2 = proto::h2::Server::new(
+                    io,
+                    service,
+                    &self.h2_builder,
+                    self.exec.clone(),
+                    metrics.clone(),
+                );
                 ProtoServer::H2(h2)
             }
         };
@@ -566,6 +758,9 @@ impl<E> Http<E> {
             } else {
                 Fallback::Http1Only
             },
+            metrics,
+            on_protocol: None,
+            protocol: None,
         }
     }
 
@@ -621,6 +816,21 @@ where
         }
     }
 
+    /// Returns a handle to the statistics of this connection.
+    pub fn metrics(&self) -> ConnectionMetrics {
+        self.metrics.clone()
+    }
+
+    /// Put `info` in the extensions of the requests of this connection.
+    fn set_addr_info(&mut self, info: AddrInfo) {
+        match self.conn {
+            Some(ProtoServer::H1(ref mut h1, _)) => h1.dispatch_mut().set_addr_info(info),
+            #[cfg(feature = "http2")]
+            Some(ProtoServer::H2(ref mut h2)) => h2.set_addr_info(info),
+            None => (),
+        }
+    }
+
     /// Return the inner IO object, and additional information.
     ///
     /// If the IO object has been "rewound" the io will not contain those bytes rewound.
@@ -686,6 +896,10 @@ where
                             self.upgrade_h2();
                             continue;
                         }
+                        Kind::UpgradeH2c if self.fallback.to_h2() => {
+                            self.upgrade_h2c();
+                            continue;
+                        }
                         _ => (),
                     }
 
@@ -715,19 +929,68 @@ where
         trace!("Trying to upgrade connection to h2");
         let conn = self.conn.take();
 
-        let (io, read_buf, dispatch) = match conn.unwrap() {
+        let (io, read_buf, mut dispatch) = match conn.unwrap() {
             ProtoServer::H1(h1, _) => h1.into_inner(),
             ProtoServer::H2(_h2) => {
                 panic!("h2 cannot into_inner");
             }
         };
-        let mut rewind_io = Rewind::new(io);
+        let addr_info = dispatch.take_addr_info();
+        let mut rewind_io = Rewind::new(Metered::new(io, self.metrics.clone()));
         rewind_io.rewind(read_buf);
         let (builder, exec) = match self.fallback {
             Fallback::ToHttp2(ref builder, ref exec) => (builder, exec),
             Fallback::Http1Only => unreachable!("upgrade_h2 with Fallback::Http1Only"),
         };
-        let h2 = proto::h2::Server::new(rewind_io, dispatch.into_service(), builder, exec.clone());
+        let mut h2 = proto::h2::Server::new(
+            H2cIo::new(rewind_io),
+            dispatch.into_service(),
+            builder,
+            exec.clone(),
+            self.metrics.clone(),
+        );
+        if let Some(info) = addr_info {
+            h2.set_addr_info(info);
+        }
+
+        debug_assert!(self.conn.is_none());
+        self.conn = Some(ProtoServer::H2(h2));
+    }
+
+    #[cfg(feature = "http2")]
+    fn upgrade_h2c(&mut self) {
+        trace!("Upgrading connection to h2c");
+        let conn = self.conn.take();
+
+        let (io, read_buf, mut dispatch) = match conn.unwrap() {
+            ProtoServer::H1(h1, _) => h1.into_inner(),
+            ProtoServer::H2(_h2) => {
+                panic!("h2 cannot into_inner");
+            }
+        };
+        let upgrade = dispatch
+            .take_h2c_upgrade()
+            .expect("upgrade_h2c without an h2c upgrade");
+        let addr_info = dispatch.take_addr_info();
+        // The bytes in `read_buf` were already counted by the HTTP/1 buffers.
+        let io = H2cIo::upgraded(
+            Rewind::new_buffered(Metered::new(io, self.metrics.clone()), read_buf),
+            upgrade,
+        );
+        let (builder, exec) = match self.fallback {
+            Fallback::ToHttp2(ref builder, ref exec) => (builder, exec),
+            Fallback::Http1Only => unreachable!("upgrade_h2c with Fallback::Http1Only"),
+        };
+        let mut h2 = proto::h2::Server::new(
+            io,
+            dispatch.into_service(),
+            builder,
+            exec.clone(),
+            self.metrics.clone(),
+        );
+        if let Some(info) = addr_info {
+            h2.set_addr_info(info);
+        }
 
         debug_assert!(self.conn.is_none());
         self.conn = Some(ProtoServer::H2(h2));
@@ -742,6 +1005,38 @@ where
     {
         UpgradeableConnection { inner: self }
     }
+
+    /// Call `on_protocol` once it is known which protocol the connection
+    /// speaks, and again if it switches to HTTP/2.
+    fn report_protocol(&mut self) {
+        if self.on_protocol.is_none() {
+            return;
+        }
+        let version = match self.conn {
+            Some(ProtoServer::H1(..)) => {
+                if self.protocol.is_some() {
+                    return;
+                }
+                // With a fallback to h2, it's HTTP/1 once a request was parsed.
+                #[cfg(feature = "http2")]
+                {
+                    if self.fallback.to_h2() && self.metrics.requests() == 0 {
+                        return;
+                    }
+                }
+                Version::HTTP_11
+            }
+            #[cfg(feature = "http2")]
+            Some(ProtoServer::H2(..)) => Version::HTTP_2,
+            None => return,
+        };
+        if self.protocol != Some(version) {
+            self.protocol = Some(version);
+            if let Some(ref on_protocol) = self.on_protocol {
+                on_protocol(version);
+            }
+        }
+    }
 }
 
 impl<I, B, S, E> Future for Connection<I, S, E>
@@ -775,6 +1070,10 @@ where
                             self.upgrade_h2();
                             continue;
                         }
+                        Kind::UpgradeH2c if self.fallback.to_h2() => {
+                            self.upgrade_h2c();
+                            continue;
+                        }
                         _ => (),
                     }
 
@@ -828,8 +1127,8 @@ impl<I, S, E> Serve<I, S, E> {
     */
 
     /// Spawn all incoming connections onto the executor in `Http`.
-    pub(super) fn spawn_all(self) -> SpawnAll<I, S, E> {
-        SpawnAll { serve: self }
+    pub(super) fn spawn_all(self, hooks: Hooks) -> SpawnAll<I, S, E> {
+        SpawnAll { serve: self, hooks }
     }
 }
 
@@ -845,8 +1144,9 @@ where
     fn poll_next_(
         self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
+        hooks: &Hooks,
     ) -> Poll<Option<crate::Result<Connecting<IO, S::Future, E>>>> {
-        let me = self.project();
+        let mut me = self.project();
         match ready!(me.make_service.poll_ready_ref(cx)) {
             Ok(()) => (),
             Err(e) => {
@@ -855,17 +1155,36 @@ where
             }
         }
 
-        if let Some(item) = ready!(me.incoming.poll_accept(cx)) {
+        while let Some(item) = ready!(me.incoming.as_mut().poll_accept(cx)) {
             let io = item.map_err(crate::Error::new_accept)?;
+            let incoming = me.incoming.as_ref().get_ref();
+            let remote_addr = incoming.remote_addr(&io);
+            if let Some(ref on_accept) = hooks.on_accept {
+                if !on_accept(remote_addr) {
+                    debug!("connection rejected by on_accept");
+                    continue;
+                }
+            }
+            let mut protocol = me.protocol.clone();
+            if let Some(h2) = incoming.negotiated_h2(&io) {
+                protocol.negotiated_h2(h2);
+            }
+            let addr_info = match (remote_addr, incoming.local_addr(&io)) {
+                (Some(remote_addr), Some(local_addr)) => {
+                    Some(AddrInfo::new(remote_addr, local_addr))
+                }
+                _ => None,
+            };
             let new_fut = me.make_service.make_service_ref(&io);
-            Poll::Ready(Some(Ok(Connecting {
+            return Poll::Ready(Some(Ok(Connecting {
                 future: new_fut,
                 io: Some(io),
-                protocol: me.protocol.clone(),
-            })))
-        } else {
-            Poll::Ready(None)
+                protocol,
+                remote_addr,
+                addr_info,
+            })));
         }
+        Poll::Ready(None)
     }
 }
 
@@ -886,7 +1205,11 @@ where
         let mut me = self.project();
         let service = ready!(me.future.poll(cx))?;
         let io = Option::take(&mut me.io).expect("polled after complete");
-        Poll::Ready(Ok(me.protocol.serve_connection(io, service)))
+        let mut conn = me.protocol.serve_connection(io, service);
+        if let Some(info) = me.addr_info.take() {
+            conn.set_addr_info(info);
+        }
+        Poll::Ready(Ok(conn))
     }
 }
 
@@ -925,8 +1248,8 @@ where
     {
         let mut me = self.project();
         loop {
-            if let Some(connecting) = ready!(me.serve.as_mut().poll_next_(cx)?) {
-                let fut = NewSvcTask::new(connecting, watcher.clone());
+            if let Some(connecting) = ready!(me.serve.as_mut().poll_next_(cx, me.hooks)?) {
+                let fut = NewSvcTask::new(connecting, watcher.clone(), me.hooks.clone());
                 me.serve
                     .as_mut()
                     .project()
@@ -940,6 +1263,19 @@ where
     }
 }
 
+// ===== impl Hooks =====
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("on_accept", &self.on_accept.is_some())
+            .field("on_connection", &self.on_connection.is_some())
+            .field("on_protocol", &self.on_protocol.is_some())
+            .field("on_close", &self.on_close.is_some())
+            .finish()
+    }
+}
+
 // ===== impl ProtoServer =====
 
 impl<T, B, S, E> Future for ProtoServer<T, B, S, E>
@@ -964,9 +1300,10 @@ where
 
 pub(crate) mod spawn_all {
     use std::error::Error as StdError;
+    use std::net::SocketAddr;
     use tokio::io::{AsyncRead, AsyncWrite};
 
-    use super::{Connecting, UpgradeableConnection};
+    use super::{CloseHook, Connecting, Hooks, UpgradeableConnection};
     use crate::body::{Body, HttpBody};
     use crate::common::exec::ConnStreamExec;
     use crate::common::{task, Future, Pin, Poll, Unpin};
@@ -1021,6 +1358,16 @@ pub(crate) mod spawn_all {
     pub struct NewSvcTask<I, N, S: HttpService<Body>, E, W: Watcher<I, S, E>> {
         #[pin]
         state: State<I, N, S, E, W>,
+        hooks: Hooks,
+        remote_addr: Option<SocketAddr>,
+        // Calls the `on_close` hook, even if this task is dropped before the
+        // connection finishes.
+        close: CloseGuard,
+    }
+
+    struct CloseGuard {
+        on_close: Option<CloseHook>,
+        remote_addr: Option<SocketAddr>,
     }
 
     #[pin_project(project = StateProj)]
@@ -1030,9 +1377,15 @@ pub(crate) mod spawn_all {
     }
 
     impl<I, N, S: HttpService<Body>, E, W: Watcher<I, S, E>> NewSvcTask<I, N, S, E, W> {
-        pub(super) fn new(connecting: Connecting<I, N, E>, watcher: W) -> Self {
+        pub(super) fn new(connecting: Connecting<I, N, E>, watcher: W, hooks: Hooks) -> Self {
             NewSvcTask {
+                remote_addr: connecting.remote_addr,
+                close: CloseGuard {
+                    on_close: hooks.on_close.clone(),
+                    remote_addr: connecting.remote_addr,
+                },
                 state: State::Connecting(connecting, watcher),
+                hooks,
             }
         }
     }
@@ -1061,23 +1414,35 @@ pub(crate) mod spawn_all {
                     match me.state.as_mut().project() {
                         StateProj::Connecting(connecting, watcher) => {
                             let res = ready!(connecting.poll(cx));
-                            let conn = match res {
+                            let mut conn = match res {
                                 Ok(conn) => conn,
                                 Err(err) => {
                                     let err = crate::Error::new_user_make_service(err);
                                     debug!("connecting error: {}", err);
+                                    me.close.closed(Some(&err));
                                     return Poll::Ready(());
                                 }
                             };
+                            if let Some(ref on_connection) = me.hooks.on_connection {
+                                on_connection(conn.metrics());
+                            }
+                            if let Some(ref on_protocol) = me.hooks.on_protocol {
+                                let on_protocol = on_protocol.clone();
+                                let remote_addr = *me.remote_addr;
+                                conn.on_protocol = Some(Box::new(move |version| {
+                                    on_protocol(remote_addr, version)
+                                }));
+                            }
                             let connected = watcher.watch(conn.with_upgrades());
                             State::Connected(connected)
                         }
                         StateProj::Connected(future) => {
-                            return future.poll(cx).map(|res| {
-                                if let Err(err) = res {
-                                    debug!("connection error: {}", err);
-                                }
-                            });
+                            let res = ready!(future.poll(cx));
+                            if let Err(ref err) = res {
+                                debug!("connection error: {}", err);
+                            }
+                            me.close.closed(res.as_ref().err());
+                            return Poll::Ready(());
                         }
                     }
                 };
@@ -1086,6 +1451,22 @@ pub(crate) mod spawn_all {
             }
         }
     }
+
+    impl CloseGuard {
+        fn closed(&mut self, err: Option<&crate::Error>) {
+            if let Some(on_close) = self.on_close.take() {
+                on_close(self.remote_addr, err);
+            }
+        }
+    }
+
+    impl Drop for CloseGuard {
+        fn drop(&mut self) {
+            // The task was dropped before the connection finished, such as
+            // when the runtime shuts down.
+            self.closed(None);
+        }
+    }
 }
 
 mod upgrades {
@@ -1135,7 +1516,9 @@ mod upgrades {
 
         fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
             loop {
-                match ready!(Pin::new(self.inner.conn.as_mut().unwrap()).poll(cx)) {
+                let polled = Pin::new(self.inner.conn.as_mut().unwrap()).poll(cx);
+                self.inner.report_protocol();
+                match ready!(polled) {
                     Ok(proto::Dispatched::Shutdown) => return Poll::Ready(Ok(())),
                     Ok(proto::Dispatched::Upgrade(pending)) => {
                         let h1 = match mem::replace(&mut self.inner.conn, None) {
@@ -1154,6 +1537,10 @@ mod upgrades {
                                 self.inner.upgrade_h2();
                                 continue;
                             }
+                            Kind::UpgradeH2c if self.inner.fallback.to_h2() => {
+                                self.inner.upgrade_h2c();
+                                continue;
+                            }
                             _ => (),
                         }
 
@@ -0,0 +1,356 @@
+//! Upgrading cleartext HTTP/1.1 connections to HTTP/2 (RFC 7540, section 3.2).
+//!
+//! A request asking for the upgrade is received with HTTP/1.1, and its
+//! response is sent as stream 1 of the new HTTP/2 connection. The `h2`
+//! server only knows about the streams it read, so `H2cIo` replays the
+//! upgrade as if the client had sent it with HTTP/2: a connection preface, a
+//! `SETTINGS` frame with the `HTTP2-Settings` of the request, and the request
+//! itself on stream 1. The client's real preface is then checked and skipped.
+//!
+//! On the write side, the `101 Switching Protocols` response is sent first,
+//! and the acknowledgement of the replayed `SETTINGS` frame is dropped, since
+//! the client never sent it.
+
+use std::cmp;
+use std::io;
+
+use bytes::{Buf, Bytes};
+use http::header::{HeaderName, HOST, TE};
+use http::{HeaderMap, Method, Request, Version};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::common::io::Rewind;
+use crate::common::{task, Pin, Poll};
+use crate::proto::h2::h2c::{
+    decode_settings, encode_frame, encode_headers, FrameFilter, ACK, END_STREAM, PREFACE, SETTINGS,
+};
+
+const SWITCHING_PROTOCOLS: &[u8] =
+    b"HTTP/1.1 101 Switching Protocols\r\nconnection: Upgrade\r\nupgrade: h2c\r\n\r\n";
+
+/// A request that asked to upgrade its connection to `h2c`.
+#[derive(Debug)]
+pub(crate) struct Upgrade {
+    /// The frames replayed to the `h2` server.
+    frames: Bytes,
+}
+
+impl Upgrade {
+    /// Returns the upgrade a request asked for, if it is a valid one.
+    pub(crate) fn from_request<B>(req: &Request<B>) -> Option<Upgrade> {
+        if req.version() != Version::HTTP_11 || req.method() == Method::CONNECT {
+            return None;
+        }
+        let headers = req.headers();
+        if !has_token(headers, "upgrade", "h2c")
+            || !has_token(headers, "connection", "upgrade")
+            || !has_token(headers, "connection", "http2-settings")
+        {
+            return None;
+        }
+
+        let mut values = headers.get_all("http2-settings").iter();
+        let settings = match (values.next(), values.next()) {
+            (Some(value), None) => decode_settings(value.as_bytes())?,
+            _ => return None,
+        };
+
+        let authority = req
+            .uri()
+            .authority()
+            .map(|a| a.as_str().as_bytes())
+            .or_else(|| headers.get(HOST).map(|v| v.as_bytes()));
+        let path = req
+            .uri()
+            .path_and_query()
+            .map_or("/", |p| p.as_str())
+            .as_bytes();
+
+        let mut fields = vec![
+            (&b":method"[..], req.method().as_str().as_bytes()),
+            (b":scheme", b"http"),
+        ];
+        if let Some(authority) = authority {
+            fields.push((b":authority", authority));
+        }
+        fields.push((b":path", path));
+        for (name, value) in headers {
+            if is_connection_specific(headers, name) {
+                continue;
+            }
+            if name == TE && value != "trailers" {
+                continue;
+            }
+            fields.push((name.as_str().as_bytes(), value.as_bytes()));
+        }
+
+        let mut frames = Vec::new();
+        frames.extend_from_slice(PREFACE);
+        encode_frame(&mut frames, SETTINGS, 0, 0, &settings);
+        encode_headers(&mut frames, END_STREAM, 1, fields);
+        Some(Upgrade {
+            frames: frames.into(),
+        })
+    }
+}
+
+/// Returns whether a header with a list of tokens contains `token`.
+fn has_token(headers: &HeaderMap, name: &str, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|t| t.trim().eq_ignore_ascii_case(token))
+}
+
+/// Returns whether a header only applies to the HTTP/1 connection.
+fn is_connection_specific(headers: &HeaderMap, name: &HeaderName) -> bool {
+    match name.as_str() {
+        "connection" | "upgrade" | "http2-settings" | "keep-alive" | "proxy-connection"
+        | "transfer-encoding" | "host" => true,
+        name => has_token(headers, "connection", name),
+    }
+}
+
+/// The IO of an HTTP/2 server connection, which may have been upgraded from
+/// HTTP/1.1.
+#[derive(Debug)]
+pub(crate) struct H2cIo<T> {
+    io: Rewind<T>,
+    upgrade: Option<Box<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    frames: Bytes,
+    // The part of the client's preface that still needs to be skipped.
+    preface: usize,
+    // The part of the `101` response that was written.
+    written: usize,
+    filter: FrameFilter,
+    acked: bool,
+}
+
+impl<T> H2cIo<T> {
+    /// Create an IO that is passed through as it is.
+    pub(crate) fn new(io: Rewind<T>) -> H2cIo<T> {
+        H2cIo { io, upgrade: None }
+    }
+
+    /// Create an IO of a connection that a request upgraded.
+    pub(crate) fn upgraded(io: Rewind<T>, upgrade: Upgrade) -> H2cIo<T> {
+        H2cIo {
+            io,
+            upgrade: Some(Box::new(State {
+                frames: upgrade.frames,
+                preface: PREFACE.len(),
+                written: 0,
+                filter: FrameFilter::server(),
+                acked: false,
+            })),
+        }
+    }
+}
+
+impl State {
+    fn poll_write_response<T>(
+        &mut self,
+        io: &mut Rewind<T>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<io::Result<()>>
+    where
+        T: AsyncWrite + Unpin,
+    {
+        while self.written < SWITCHING_PROTOCOLS.len() {
+            let n =
+                ready!(Pin::new(&mut *io).poll_write(cx, &SWITCHING_PROTOCOLS[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for H2cIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = &mut *self;
+        let state = match me.upgrade {
+            Some(ref mut state) => state,
+            None => return Pin::new(&mut me.io).poll_read(cx, buf),
+        };
+
+        if !state.frames.is_empty() {
+            let len = cmp::min(state.frames.len(), buf.remaining());
+            buf.put_slice(&state.frames[..len]);
+            state.frames.advance(len);
+            return Poll::Ready(Ok(()));
+        }
+
+        while state.preface > 0 {
+            let start = PREFACE.len() - state.preface;
+            let mut preface = [0; 24];
+            let mut preface_buf = ReadBuf::new(&mut preface[..state.preface]);
+            ready!(Pin::new(&mut me.io).poll_read(cx, &mut preface_buf))?;
+            let read = preface_buf.filled();
+            if read.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            if read != &PREFACE[start..start + read.len()] {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid h2c connection preface",
+                )));
+            }
+            state.preface -= read.len();
+        }
+
+        Pin::new(&mut me.io).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for H2cIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = &mut *self;
+        let state = match me.upgrade {
+            Some(ref mut state) => state,
+            None => return Pin::new(&mut me.io).poll_write(cx, buf),
+        };
+
+        ready!(state.poll_write_response(&mut me.io, cx))?;
+        if state.acked && state.filter.is_idle() {
+            return Pin::new(&mut me.io).poll_write(cx, buf);
+        }
+        let acked = &mut state.acked;
+        state.filter.poll_write(&mut me.io, cx, buf, |head| {
+            if !*acked && head.kind == SETTINGS && head.flags & ACK != 0 {
+                *acked = true;
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        let me = &mut *self;
+        if let Some(ref mut state) = me.upgrade {
+            ready!(state.poll_write_response(&mut me.io, cx))?;
+            ready!(state.filter.poll_write_pending(&mut me.io, cx))?;
+        }
+        Pin::new(&mut me.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        let me = &mut *self;
+        if let Some(ref mut state) = me.upgrade {
+            ready!(state.poll_write_response(&mut me.io, cx))?;
+            ready!(state.filter.poll_write_pending(&mut me.io, cx))?;
+        }
+        Pin::new(&mut me.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{H2cIo, Upgrade};
+    use crate::common::io::Rewind;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn upgrade_request() -> http::Request<()> {
+        http::Request::get("http://example.com/ping?a=1")
+            .header("connection", "Upgrade, HTTP2-Settings, x-hop")
+            .header("upgrade", "h2c")
+            .header("http2-settings", "AAIAAAAA")
+            .header("x-hop", "1")
+            .header("accept", "*/*")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn upgrade_needs_settings_and_connection_tokens() {
+        assert!(Upgrade::from_request(&upgrade_request()).is_some());
+
+        let mut req = upgrade_request();
+        req.headers_mut().remove("http2-settings");
+        assert!(Upgrade::from_request(&req).is_none());
+
+        let mut req = upgrade_request();
+        req.headers_mut()
+            .insert("connection", "Upgrade".parse().unwrap());
+        assert!(Upgrade::from_request(&req).is_none());
+
+        let mut req = upgrade_request();
+        req.headers_mut()
+            .append("http2-settings", "AAIAAAAA".parse().unwrap());
+        assert!(Upgrade::from_request(&req).is_none());
+
+        let mut req = upgrade_request();
+        *req.version_mut() = http::Version::HTTP_10;
+        assert!(Upgrade::from_request(&req).is_none());
+    }
+
+    #[tokio::test]
+    async fn replays_upgrade_and_skips_client_preface() {
+        let upgrade = Upgrade::from_request(&upgrade_request()).unwrap();
+        let replayed = upgrade.frames.clone();
+
+        let mut client = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        client.extend_from_slice(b"next");
+        let mut io = H2cIo::upgraded(Rewind::new(&client[..]), upgrade);
+
+        let mut read = Vec::new();
+        io.read_to_end(&mut read).await.unwrap();
+        assert_eq!(&read[..replayed.len()], &replayed[..]);
+        assert_eq!(&read[replayed.len()..], b"next");
+
+        // The connection-specific headers are left out of the request.
+        assert!(!contains(&replayed, b"x-hop"));
+        assert!(!contains(&replayed, b"http2-settings"));
+        assert!(contains(&replayed, b"accept"));
+        assert!(contains(&replayed, b"/ping?a=1"));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_client_preface() {
+        let upgrade = Upgrade::from_request(&upgrade_request()).unwrap();
+        let mut io = H2cIo::upgraded(Rewind::new(&b"GET / HTTP/1.1\r\n\r\n"[..]), upgrade);
+
+        let mut read = Vec::new();
+        let err = io.read_to_end(&mut read).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn writes_response_and_drops_first_settings_ack() {
+        let upgrade = Upgrade::from_request(&upgrade_request()).unwrap();
+        let mut io = H2cIo::upgraded(Rewind::new(Vec::new()), upgrade);
+
+        let settings = [0, 0, 0, 0x4, 0, 0, 0, 0, 0];
+        let ack = [0, 0, 0, 0x4, 0x1, 0, 0, 0, 0];
+        io.write_all(&settings).await.unwrap();
+        io.write_all(&ack).await.unwrap();
+        io.write_all(&ack).await.unwrap();
+        io.flush().await.unwrap();
+
+        let mut expected = super::SWITCHING_PROTOCOLS.to_vec();
+        expected.extend_from_slice(&settings);
+        expected.extend_from_slice(&ack);
+        assert_eq!(io.io.into_inner().0, expected);
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+}
@@ -53,18 +53,27 @@
 
 pub mod accept;
 pub mod conn;
+#[cfg(feature = "http2")]
+pub(crate) mod h2c;
+#[cfg(feature = "http2")]
+pub(crate) mod push;
 mod shutdown;
 #[cfg(feature = "tcp")]
 mod tcp;
+#[cfg(all(unix, feature = "unix"))]
+mod unix;
 
 use std::error::Error as StdError;
 use std::fmt;
+use std::net::SocketAddr;
 #[cfg(feature = "tcp")]
-use std::net::{SocketAddr, TcpListener as StdTcpListener};
+use std::net::TcpListener as StdTcpListener;
+use std::sync::Arc;
 
-#[cfg(feature = "tcp")]
+#[cfg(any(feature = "tcp", feature = "runtime"))]
 use std::time::Duration;
 
+use http::Version;
 use pin_project::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite};
 
@@ -75,7 +84,7 @@ use crate::common::{task, Future, Pin, Poll, Unpin};
 use crate::service::{HttpService, MakeServiceRef};
 // Renamed `Http` as `Http_` for now so that people upgrading don't see an
 // error that `hyper::server::Http` is private...
-use self::conn::{Http as Http_, NoopWatcher, SpawnAll};
+use self::conn::{ConnectionMetrics, Hooks, Http as Http_, NoopWatcher, SpawnAll};
 use self::shutdown::{Graceful, GracefulWatcher};
 #[cfg(feature = "tcp")]
 use self::tcp::AddrIncoming;
@@ -97,6 +106,7 @@ pub struct Server<I, S, E = Exec> {
 pub struct Builder<I, E = Exec> {
     incoming: I,
     protocol: Http_<E>,
+    hooks: Hooks,
 }
 
 // ===== impl Server =====
@@ -107,6 +117,7 @@ impl<I> Server<I, ()> {
         Builder {
             incoming,
             protocol: Http_::new(),
+            hooks: Hooks::default(),
         }
     }
 }
@@ -235,7 +246,11 @@ impl<I, E> Builder<I, E> {
     ///
     /// For a more convenient constructor, see [`Server::bind`](Server::bind).
     pub fn new(incoming: I, protocol: Http_<E>) -> Self {
-        Builder { incoming, protocol }
+        Builder {
+            incoming,
+            protocol,
+            hooks: Hooks::default(),
+        }
     }
 
     /// Sets whether to use keep-alive for HTTP/1 connections.
@@ -259,6 +274,48 @@ impl<I, E> Builder<I, E> {
         self
     }
 
+    /// Set a timeout for reading client request headers. If a client does not
+    /// transmit the entire header within this time, the connection is closed.
+    ///
+    /// Default is None.
+    ///
+    /// # Cargo Feature
+    ///
+    /// Requires the `runtime` cargo feature to be enabled.
+    #[cfg(feature = "runtime")]
+    pub fn http1_header_read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.protocol.http1_header_read_timeout(read_timeout);
+        self
+    }
+
+    /// Set the maximum number of headers allowed in a request.
+    ///
+    /// Default is 100.
+    pub fn http1_max_headers(mut self, val: usize) -> Self {
+        self.protocol.http1_max_headers(val);
+        self
+    }
+
+    /// Set whether to record the original case of request header names.
+    ///
+    /// The original names are stored in the extensions of the `Request`.
+    ///
+    /// Default is false.
+    pub fn http1_preserve_header_case(mut self, val: bool) -> Self {
+        self.protocol.http1_preserve_header_case(val);
+        self
+    }
+
+    /// Set whether to record the original order of request headers.
+    ///
+    /// The original order is stored in the extensions of the `Request`.
+    ///
+    /// Default is false.
+    pub fn http1_preserve_header_order(mut self, val: bool) -> Self {
+        self.protocol.http1_preserve_header_order(val);
+        self
+    }
+
     /// Set the maximum buffer size.
     ///
     /// Default is ~ 400kb.
@@ -379,6 +436,19 @@ impl<I, E> Builder<I, E> {
         self
     }
 
+    /// Sets whether HTTP2 requests can push responses to the client.
+    ///
+    /// If enabled, a [`Pusher`](conn::Pusher) is placed in the extensions of
+    /// each HTTP2 request, to send `PUSH_PROMISE` frames on its stream.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_enable_push(mut self, enabled: bool) -> Self {
+        self.protocol.http2_enable_push(enabled);
+        self
+    }
+
     /// Sets an interval for HTTP2 Ping frames should be sent to keep a
     /// connection alive.
     ///
@@ -415,6 +485,128 @@ impl<I, E> Builder<I, E> {
         self
     }
 
+    /// Set whether HTTP/1 connections can be upgraded to HTTP/2.
+    ///
+    /// If enabled, a client can switch a connection to cleartext HTTP/2 by
+    /// sending `Upgrade: h2c` on its first request. Requests with a body
+    /// aren't upgraded.
+    ///
+    /// This does nothing if `http1_only` or `http2_only` is set.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_h2c_upgrade(mut self, enabled: bool) -> Self {
+        self.protocol.http2_h2c_upgrade(enabled);
+        self
+    }
+
+    /// Set a function to call with the metrics of each new connection.
+    ///
+    /// The function is called once the service for a connection is made,
+    /// before the connection is served. The [`ConnectionMetrics`] it is
+    /// given keep being updated while the connection is open, and can be
+    /// stored to be read later.
+    ///
+    /// [`ConnectionMetrics`]: crate::server::conn::ConnectionMetrics
+    pub fn on_connection<F>(mut self, f: F) -> Self
+    where
+        F: Fn(ConnectionMetrics) + Send + Sync + 'static,
+    {
+        self.hooks.on_connection = Some(Arc::new(f));
+        self
+    }
+
+    /// Set a function to call for each accepted connection, which decides
+    /// whether the connection is served.
+    ///
+    /// The function is given the remote address of the connection, if the
+    /// incoming stream knows it, such as with `AddrIncoming` or
+    /// `TlsIncoming`. If it returns `false`, the connection is closed right
+    /// away, without making a service for it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "tcp")]
+    /// # fn run() {
+    /// use std::collections::HashMap;
+    /// use std::net::IpAddr;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use hyper::Server;
+    ///
+    /// // Allow at most 10 connections from each IP address...
+    /// let conns = Arc::new(Mutex::new(HashMap::<IpAddr, usize>::new()));
+    /// let accepted = conns.clone();
+    ///
+    /// let builder = Server::bind(&([127, 0, 0, 1], 3000).into())
+    ///     .on_accept(move |addr| {
+    ///         let ip = match addr {
+    ///             Some(addr) => addr.ip(),
+    ///             None => return true,
+    ///         };
+    ///         let mut conns = accepted.lock().unwrap();
+    ///         let count = conns.entry(ip).or_insert(0);
+    ///         if *count == 10 {
+    ///             return false;
+    ///         }
+    ///         *count += 1;
+    ///         true
+    ///     })
+    ///     // ...and give the slot back once each one closes.
+    ///     .on_close(move |addr, _err| {
+    ///         if let Some(addr) = addr {
+    ///             let mut conns = conns.lock().unwrap();
+    ///             if let Some(count) = conns.get_mut(&addr.ip()) {
+    ///                 *count -= 1;
+    ///                 if *count == 0 {
+    ///                     conns.remove(&addr.ip());
+    ///                 }
+    ///             }
+    ///         }
+    ///     });
+    /// # }
+    /// ```
+    pub fn on_accept<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Option<SocketAddr>) -> bool + Send + Sync + 'static,
+    {
+        self.hooks.on_accept = Some(Arc::new(f));
+        self
+    }
+
+    /// Set a function to call once it is known which protocol a connection
+    /// speaks.
+    ///
+    /// The function is given the remote address of the connection, if known,
+    /// and `HTTP/1.1` for HTTP/1 connections or `HTTP/2.0` for HTTP/2 ones.
+    /// When HTTP/1 may fall back to HTTP/2, this is once the first request
+    /// is read. If an HTTP/1 connection is upgraded to HTTP/2 with `h2c`, the
+    /// function is called again.
+    pub fn on_protocol<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Option<SocketAddr>, Version) + Send + Sync + 'static,
+    {
+        self.hooks.on_protocol = Some(Arc::new(f));
+        self
+    }
+
+    /// Set a function to call when a connection closes.
+    ///
+    /// The function is given the remote address of the connection, if known,
+    /// and the error that ended the connection, if any. It is called once for
+    /// every connection that `on_accept` let through, including ones whose
+    /// service could not be made, and ones whose task is dropped before they
+    /// finish, such as when the runtime shuts down.
+    pub fn on_close<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Option<SocketAddr>, Option<&crate::Error>) + Send + Sync + 'static,
+    {
+        self.hooks.on_close = Some(Arc::new(f));
+        self
+    }
+
     /// Sets the `Executor` to deal with connection tasks.
     ///
     /// Default is `tokio::spawn`.
@@ -422,6 +614,7 @@ impl<I, E> Builder<I, E> {
         Builder {
             incoming: self.incoming,
             protocol: self.protocol.with_executor(executor),
+            hooks: self.hooks,
         }
     }
 
@@ -468,7 +661,7 @@ impl<I, E> Builder<I, E> {
         E: ConnStreamExec<<S::Service as HttpService<Body>>::Future, B>,
     {
         let serve = self.protocol.serve(self.incoming, new_service);
-        let spawn_all = serve.spawn_all();
+        let spawn_all = serve.spawn_all(self.hooks);
         Server { spawn_all }
     }
 }
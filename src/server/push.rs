@@ -0,0 +1,73 @@
+use std::fmt;
+
+use futures_channel::{mpsc, oneshot};
+
+use crate::common::Future;
+use crate::{Body, Request, Response};
+
+/// A handle to push responses to the client of an HTTP/2 request.
+///
+/// When enabled with [`Http::http2_enable_push`][push], a `Pusher` is found
+/// in the extensions of every HTTP/2 request, except `CONNECT` requests.
+///
+/// [push]: crate::server::conn::Http::http2_enable_push
+#[derive(Clone)]
+pub struct Pusher {
+    tx: mpsc::UnboundedSender<Push>,
+}
+
+/// A push queued by a `Pusher`, to be promised on the stream of its request.
+pub(crate) struct Push {
+    pub(crate) request: Request<()>,
+    pub(crate) response: Response<Body>,
+    pub(crate) tx: oneshot::Sender<crate::Result<()>>,
+}
+
+impl Pusher {
+    pub(crate) fn channel() -> (Pusher, mpsc::UnboundedReceiver<Push>) {
+        let (tx, rx) = mpsc::unbounded();
+        (Pusher { tx }, rx)
+    }
+
+    /// Promises `request` to the client, and then sends `response` to it.
+    ///
+    /// The request must be safe and cacheable, such as a `GET`, with an
+    /// absolute URI. A `PUSH_PROMISE` can only be sent while the response to
+    /// the original request isn't complete.
+    ///
+    /// The returned future resolves once the promise is sent. It yields an
+    /// error if the client disabled server push, if the request can't be
+    /// pushed, or if the original stream is already complete.
+    pub fn push(
+        &self,
+        request: Request<()>,
+        response: Response<Body>,
+    ) -> impl Future<Output = crate::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let queued = self
+            .tx
+            .unbounded_send(Push {
+                request,
+                response,
+                tx,
+            })
+            .is_ok();
+
+        async move {
+            if !queued {
+                return Err(stream_closed());
+            }
+            rx.await.unwrap_or_else(|_canceled| Err(stream_closed()))
+        }
+    }
+}
+
+impl fmt::Debug for Pusher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pusher").finish()
+    }
+}
+
+fn stream_closed() -> crate::Error {
+    crate::Error::new_canceled().with("stream closed before push")
+}
@@ -146,7 +146,10 @@ impl AddrIncoming {
                     if let Err(e) = socket.set_nodelay(self.tcp_nodelay) {
                         trace!("error trying to set TCP nodelay: {}", e);
                     }
-                    return Poll::Ready(Ok(AddrStream::new(socket, addr)));
+                    // If the socket's address can't be read, the listener's
+                    // is the best guess.
+                    let local_addr = socket.local_addr().unwrap_or(self.addr);
+                    return Poll::Ready(Ok(AddrStream::new(socket, addr, local_addr)));
                 }
                 Err(e) => {
                     // Connection errors can be ignored directly, continue by
@@ -192,6 +195,14 @@ impl Accept for AddrIncoming {
         let result = ready!(self.poll_next_(cx));
         Poll::Ready(Some(result))
     }
+
+    fn remote_addr(&self, conn: &Self::Conn) -> Option<SocketAddr> {
+        Some(conn.remote_addr())
+    }
+
+    fn local_addr(&self, conn: &Self::Conn) -> Option<SocketAddr> {
+        Some(conn.local_addr())
+    }
 }
 
 /// This function defines errors that are per-connection. Which basically
@@ -238,13 +249,15 @@ mod addr_stream {
         #[pin]
         inner: TcpStream,
         pub(super) remote_addr: SocketAddr,
+        local_addr: SocketAddr,
     }
 
     impl AddrStream {
-        pub(super) fn new(tcp: TcpStream, addr: SocketAddr) -> AddrStream {
+        pub(super) fn new(tcp: TcpStream, addr: SocketAddr, local_addr: SocketAddr) -> AddrStream {
             AddrStream {
                 inner: tcp,
                 remote_addr: addr,
+                local_addr,
             }
         }
 
@@ -254,6 +267,12 @@ mod addr_stream {
             self.remote_addr
         }
 
+        /// Returns the local address of this connection.
+        #[inline]
+        pub fn local_addr(&self) -> SocketAddr {
+            self.local_addr
+        }
+
         /// Consumes the AddrStream and returns the underlying IO object
         #[inline]
         pub fn into_inner(self) -> TcpStream {
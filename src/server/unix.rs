@@ -0,0 +1,76 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use tokio::net::unix::SocketAddr;
+use tokio::net::{UnixListener, UnixStream};
+
+use super::Accept;
+use crate::common::{task, Pin, Poll};
+
+/// A stream of connections from binding to a Unix domain socket.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn doc() -> hyper::Result<()> {
+/// use hyper::server::{conn::UnixIncoming, Server};
+/// use hyper::service::{make_service_fn, service_fn};
+/// use hyper::{Body, Response};
+///
+/// let incoming = UnixIncoming::bind("/tmp/hyper.sock")?;
+/// let make_svc = make_service_fn(|_conn| async {
+///     Ok::<_, hyper::Error>(service_fn(|_req| async {
+///         Ok::<_, hyper::Error>(Response::new(Body::from("Hello World")))
+///     }))
+/// });
+///
+/// Server::builder(incoming).serve(make_svc).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct UnixIncoming {
+    listener: UnixListener,
+}
+
+impl UnixIncoming {
+    /// Creates a new `UnixIncoming` binding to the socket at `path`.
+    ///
+    /// The socket file must not already exist.
+    pub fn bind<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let listener = UnixListener::bind(path).map_err(crate::Error::new_listen)?;
+        Ok(UnixIncoming::from_listener(listener))
+    }
+
+    /// Creates a new `UnixIncoming` from an existing `UnixListener`.
+    pub fn from_listener(listener: UnixListener) -> Self {
+        UnixIncoming { listener }
+    }
+
+    /// Get the local address bound to this listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+impl Accept for UnixIncoming {
+    type Conn = UnixStream;
+    type Error = io::Error;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        let (stream, _) = ready!(self.listener.poll_accept(cx))?;
+        Poll::Ready(Some(Ok(stream)))
+    }
+}
+
+impl fmt::Debug for UnixIncoming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnixIncoming")
+            .field("addr", &self.listener.local_addr().ok())
+            .finish()
+    }
+}
@@ -0,0 +1,152 @@
+use std::error::Error as StdError;
+use std::fmt;
+
+use bytes::Bytes;
+use http::{HeaderMap, Method};
+use http_body::SizeHint;
+use pin_project::pin_project;
+
+use crate::body::HttpBody;
+use crate::common::compression::{self, Encoding, MaybeCoded};
+use crate::common::{task, Future, Pin, Poll};
+use crate::{Request, Response};
+
+/// Compress the responses of a `Service`.
+///
+/// The body of each response is encoded with the best `Content-Encoding` that
+/// the request accepts, out of `gzip`, `deflate` and `br`. Responses that
+/// already have a `Content-Encoding`, or have no body, are left as they are,
+/// keeping their length and trailers.
+///
+/// # Example
+///
+/// ```
+/// use hyper::{Body, Request, Response};
+/// use hyper::service::{compress, service_fn};
+///
+/// let service = compress(service_fn(|_req: Request<Body>| async move {
+///     Ok::<_, hyper::Error>(Response::new(Body::from("Hello World")))
+/// }));
+/// ```
+///
+/// # Optional
+///
+/// This function requires enabling the `compression` feature in your
+/// `Cargo.toml`.
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+pub fn compress<S>(service: S) -> Compress<S> {
+    Compress { inner: service }
+}
+
+/// Service returned by [`compress`]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[derive(Clone, Debug)]
+pub struct Compress<S> {
+    inner: S,
+}
+
+/// Future returned by a [`Compress`] service.
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[must_use = "futures do nothing unless polled"]
+#[pin_project]
+pub struct CompressFuture<F> {
+    #[pin]
+    inner: F,
+    encoding: Option<Encoding>,
+}
+
+/// Body of the responses of a [`Compress`] service.
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+#[pin_project]
+pub struct CompressBody<B> {
+    #[pin]
+    inner: MaybeCoded<B>,
+}
+
+impl<S, ReqBody, ResBody> tower_service::Service<Request<ReqBody>> for Compress<S>
+where
+    S: tower_service::Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: HttpBody,
+    ResBody::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Response = Response<CompressBody<ResBody>>;
+    type Error = S::Error;
+    type Future = CompressFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let encoding = if req.method() == Method::HEAD {
+            None
+        } else {
+            compression::preferred_encoding(req.headers())
+        };
+        CompressFuture {
+            inner: self.inner.call(req),
+            encoding,
+        }
+    }
+}
+
+impl<F, B, E> Future for CompressFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+    B: HttpBody,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Output = Result<Response<CompressBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.poll(cx))?;
+        let res = compression::encode_response(res, *this.encoding);
+        Poll::Ready(Ok(res.map(|inner| CompressBody { inner })))
+    }
+}
+
+impl<F> fmt::Debug for CompressFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressFuture")
+            .field("encoding", &self.encoding)
+            .finish()
+    }
+}
+
+impl<B> HttpBody for CompressBody<B>
+where
+    B: HttpBody,
+    B::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = Box<dyn StdError + Send + Sync>;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B> fmt::Debug for CompressBody<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CompressBody").field(&self.inner).finish()
+    }
+}
@@ -37,6 +37,8 @@
 
 pub use tower_service::Service;
 
+#[cfg(feature = "compression")]
+mod compress;
 mod http;
 mod make;
 mod oneshot;
@@ -46,5 +48,7 @@ pub(crate) use self::http::HttpService;
 pub(crate) use self::make::{MakeConnection, MakeServiceRef};
 pub(crate) use self::oneshot::{oneshot, Oneshot};
 
+#[cfg(feature = "compression")]
+pub use self::compress::{compress, Compress, CompressBody, CompressFuture};
 pub use self::make::make_service_fn;
 pub use self::util::service_fn;
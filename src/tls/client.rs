@@ -0,0 +1,261 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+use http::uri::{Scheme, Uri};
+use pin_project::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+use super::{is_negotiated_h2, TlsConnect, TlsStream};
+use crate::client::connect::dns::{GaiResolver, Resolve};
+use crate::client::connect::{Connected, Connection, HttpConnector};
+use crate::common::{task, Future, Pin, Poll};
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// A connector that uses TLS for `https` destinations.
+///
+/// TCP connections are made with an [`HttpConnector`](HttpConnector), and
+/// `https` destinations are then wrapped with a [`TlsConnect`](TlsConnect)
+/// handshake. If `h2` is negotiated with ALPN, the `Client` uses HTTP/2 for
+/// the connection.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "runtime")]
+/// # fn doc<T>(tls: T)
+/// # where
+/// #     T: hyper::tls::TlsConnect + Clone + Send + Sync + 'static,
+/// #     T::Stream: Send + 'static,
+/// #     T::Future: Send + 'static,
+/// # {
+/// use hyper::client::HttpConnector;
+/// use hyper::tls::TlsConnector;
+///
+/// // `tls` implements `TlsConnect`, such as with rustls...
+/// let connector = TlsConnector::new(HttpConnector::new(), tls);
+/// let client = hyper::Client::builder().build::<_, hyper::Body>(connector);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TlsConnector<T, R = GaiResolver> {
+    http: HttpConnector<R>,
+    tls: T,
+    https_only: bool,
+}
+
+/// A stream from a [`TlsConnector`](TlsConnector).
+///
+/// This is a TLS stream for `https` destinations, and plain TCP otherwise.
+pub struct MaybeTlsStream<S> {
+    inner: MaybeTls<S>,
+}
+
+enum MaybeTls<S> {
+    Plain(TcpStream),
+    Tls {
+        stream: S,
+        // The `Connected` of the TCP stream the TLS session is over.
+        connected: Connected,
+    },
+}
+
+/// A future for a connection made by a [`TlsConnector`](TlsConnector).
+#[must_use = "futures do nothing unless polled"]
+#[pin_project]
+#[allow(missing_debug_implementations)]
+pub struct TlsConnecting<S> {
+    #[pin]
+    fut: BoxConnecting<S>,
+}
+
+type ConnectResult<S> = Result<MaybeTlsStream<S>, BoxError>;
+type BoxConnecting<S> = Pin<Box<dyn Future<Output = ConnectResult<S>> + Send>>;
+
+// ===== impl TlsConnector =====
+
+impl<T, R> TlsConnector<T, R> {
+    /// Construct a new `TlsConnector`.
+    ///
+    /// This allows `https` destinations on the `http` connector.
+    pub fn new(mut http: HttpConnector<R>, tls: T) -> TlsConnector<T, R> {
+        http.enforce_http(false);
+        TlsConnector {
+            http,
+            tls,
+            https_only: false,
+        }
+    }
+
+    /// Set whether to refuse destinations that aren't `https`.
+    ///
+    /// Default is `false`.
+    pub fn https_only(&mut self, val: bool) {
+        self.https_only = val;
+    }
+
+    /// Get a mutable reference to the `HttpConnector` used for TCP.
+    pub fn http_connector_mut(&mut self) -> &mut HttpConnector<R> {
+        &mut self.http
+    }
+}
+
+impl<T, R: fmt::Debug> fmt::Debug for TlsConnector<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector")
+            .field("http", &self.http)
+            .field("https_only", &self.https_only)
+            .finish()
+    }
+}
+
+impl<T, R> tower_service::Service<Uri> for TlsConnector<T, R>
+where
+    T: TlsConnect + Clone + Send + Sync + 'static,
+    T::Stream: Send + 'static,
+    T::Future: Send + 'static,
+    R: Resolve + Clone + Send + Sync + 'static,
+    R::Future: Send,
+{
+    type Response = MaybeTlsStream<T::Stream>;
+    type Error = BoxError;
+    type Future = TlsConnecting<T::Stream>;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        tower_service::Service::poll_ready(&mut self.http, cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let is_https = dst.scheme() == Some(&Scheme::HTTPS);
+        if !is_https && self.https_only {
+            return TlsConnecting {
+                fut: Box::pin(async { Err(INVALID_NOT_HTTPS.into()) }),
+            };
+        }
+
+        let connecting = tower_service::Service::call(&mut self.http, dst.clone());
+        let tls = self.tls.clone();
+        TlsConnecting {
+            fut: Box::pin(async move {
+                let tcp = connecting.await?;
+                if !is_https {
+                    return Ok(MaybeTlsStream {
+                        inner: MaybeTls::Plain(tcp),
+                    });
+                }
+
+                let host = dst.host().ok_or(INVALID_MISSING_HOST)?;
+                // IPv6 literals are bracketed in a `Uri`, but not in TLS.
+                let domain = host.trim_start_matches('[').trim_end_matches(']');
+                trace!("Tls::connect; domain={:?}", domain);
+                let connected = tcp.connected();
+                let stream = tls.connect(domain, tcp).await.map_err(Into::into)?;
+                Ok(MaybeTlsStream {
+                    inner: MaybeTls::Tls { stream, connected },
+                })
+            }),
+        }
+    }
+}
+
+static INVALID_NOT_HTTPS: &str = "invalid URL, scheme is not https";
+static INVALID_MISSING_HOST: &str = "invalid URL, host is missing";
+
+// ===== impl MaybeTlsStream =====
+
+impl<S> MaybeTlsStream<S> {
+    /// Returns whether this stream uses TLS.
+    pub fn is_tls(&self) -> bool {
+        match self.inner {
+            MaybeTls::Plain(_) => false,
+            MaybeTls::Tls { .. } => true,
+        }
+    }
+
+    /// Get a reference to the TLS stream, if this stream uses TLS.
+    pub fn tls_ref(&self) -> Option<&S> {
+        match self.inner {
+            MaybeTls::Plain(_) => None,
+            MaybeTls::Tls { ref stream, .. } => Some(stream),
+        }
+    }
+}
+
+impl<S: TlsStream> Connection for MaybeTlsStream<S> {
+    fn connected(&self) -> Connected {
+        match self.inner {
+            MaybeTls::Plain(ref tcp) => tcp.connected(),
+            MaybeTls::Tls {
+                ref stream,
+                ref connected,
+            } => {
+                if is_negotiated_h2(stream) {
+                    connected.clone().negotiated_h2()
+                } else {
+                    connected.clone()
+                }
+            }
+        }
+    }
+}
+
+impl<S: TlsStream> AsyncRead for MaybeTlsStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.inner {
+            MaybeTls::Plain(ref mut tcp) => Pin::new(tcp).poll_read(cx, buf),
+            MaybeTls::Tls { ref mut stream, .. } => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<S: TlsStream> AsyncWrite for MaybeTlsStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.inner {
+            MaybeTls::Plain(ref mut tcp) => Pin::new(tcp).poll_write(cx, buf),
+            MaybeTls::Tls { ref mut stream, .. } => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        match self.inner {
+            MaybeTls::Plain(ref mut tcp) => Pin::new(tcp).poll_flush(cx),
+            MaybeTls::Tls { ref mut stream, .. } => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        match self.inner {
+            MaybeTls::Plain(ref mut tcp) => Pin::new(tcp).poll_shutdown(cx),
+            MaybeTls::Tls { ref mut stream, .. } => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+impl<S> fmt::Debug for MaybeTlsStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner {
+            MaybeTls::Plain(ref tcp) => f.debug_tuple("Plain").field(tcp).finish(),
+            MaybeTls::Tls { .. } => f.debug_tuple("Tls").finish(),
+        }
+    }
+}
+
+// ===== impl TlsConnecting =====
+
+impl<S> Future for TlsConnecting<S> {
+    type Output = ConnectResult<S>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.project().fut.poll(cx)
+    }
+}
@@ -0,0 +1,77 @@
+//! Pluggable TLS for the `Client` and `Server`.
+//!
+//! hyper doesn't include a TLS implementation. Instead, this module defines
+//! small traits that a TLS library (such as rustls or native-tls) can be
+//! plugged into:
+//!
+//! - [`TlsConnect`](TlsConnect) performs the client side of a handshake, and
+//!   is used by the [`TlsConnector`](TlsConnector) to connect to `https`
+//!   destinations.
+//! - [`TlsAccept`](TlsAccept) performs the server side of a handshake, and is
+//!   used by the [`TlsIncoming`](TlsIncoming) to accept connections.
+//! - [`TlsStream`](TlsStream) is the stream they return, which reports the
+//!   protocol negotiated with ALPN.
+//!
+//! Implementations should offer [`ALPN_H2`](ALPN_H2) and
+//! [`ALPN_HTTP1`](ALPN_HTTP1) as ALPN protocols. When `h2` is negotiated, the
+//! `Client` uses HTTP/2 for the connection, and the `Server` serves it with
+//! HTTP/2. When `http/1.1` is negotiated, both use HTTP/1.
+//!
+//! # Optional
+//!
+//! This module requires enabling the `tls` feature in your `Cargo.toml`.
+use std::error::Error as StdError;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+
+use crate::common::Future;
+use crate::server::conn::AddrStream;
+
+pub use self::client::{MaybeTlsStream, TlsConnecting, TlsConnector};
+pub use self::server::TlsIncoming;
+
+mod client;
+mod server;
+
+/// The ALPN protocol identifier of HTTP/2.
+pub const ALPN_H2: &[u8] = b"h2";
+
+/// The ALPN protocol identifier of HTTP/1.1.
+pub const ALPN_HTTP1: &[u8] = b"http/1.1";
+
+/// A TLS session over a transport.
+pub trait TlsStream: AsyncRead + AsyncWrite + Unpin {
+    /// Returns the protocol negotiated with ALPN, if any.
+    fn alpn_protocol(&self) -> Option<&[u8]>;
+}
+
+/// Performs the client side of TLS handshakes.
+pub trait TlsConnect {
+    /// The TLS stream returned by a handshake.
+    type Stream: TlsStream;
+    /// The error that can occur during a handshake.
+    type Error: Into<Box<dyn StdError + Send + Sync>>;
+    /// The future of a handshake.
+    type Future: Future<Output = Result<Self::Stream, Self::Error>>;
+
+    /// Start a handshake over `io`, verifying the server as `domain`.
+    fn connect(&self, domain: &str, io: TcpStream) -> Self::Future;
+}
+
+/// Performs the server side of TLS handshakes.
+pub trait TlsAccept {
+    /// The TLS stream returned by a handshake.
+    type Stream: TlsStream;
+    /// The error that can occur during a handshake.
+    type Error: Into<Box<dyn StdError + Send + Sync>>;
+    /// The future of a handshake.
+    type Future: Future<Output = Result<Self::Stream, Self::Error>>;
+
+    /// Start a handshake over an accepted `io`.
+    fn accept(&self, io: AddrStream) -> Self::Future;
+}
+
+fn is_negotiated_h2<S: TlsStream>(stream: &S) -> bool {
+    stream.alpn_protocol() == Some(ALPN_H2)
+}
@@ -0,0 +1,207 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::time::Sleep;
+
+use super::{is_negotiated_h2, TlsAccept};
+use crate::common::{task, Future, Pin, Poll};
+use crate::server::accept::Accept;
+use crate::server::conn::AddrIncoming;
+
+/// An `Accept` of TLS connections.
+///
+/// Connections are accepted with an [`AddrIncoming`](AddrIncoming), and then
+/// wrapped with a [`TlsAccept`](TlsAccept) handshake. Handshakes run
+/// concurrently, so a slow client doesn't hold up other connections, and a
+/// failed handshake only closes that connection. Handshakes that take longer
+/// than the [handshake timeout](TlsIncoming::set_handshake_timeout) are
+/// closed too.
+///
+/// If `h2` is negotiated with ALPN, the `Server` serves the connection with
+/// HTTP/2, otherwise with HTTP/1.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "runtime")]
+/// # async fn doc<T>(tls: T) -> hyper::Result<()>
+/// # where
+/// #     T: hyper::tls::TlsAccept,
+/// #     T::Stream: Send + 'static,
+/// # {
+/// use hyper::server::conn::AddrIncoming;
+/// use hyper::tls::TlsIncoming;
+///
+/// // `tls` implements `TlsAccept`, such as with rustls...
+/// let incoming = AddrIncoming::bind(&([127, 0, 0, 1], 443).into())?;
+/// let builder = hyper::Server::builder(TlsIncoming::new(incoming, tls));
+/// # Ok(())
+/// # }
+/// ```
+pub struct TlsIncoming<T: TlsAccept> {
+    incoming: AddrIncoming,
+    incoming_done: bool,
+    tls: T,
+    handshake_timeout: Option<Duration>,
+    max_handshakes: usize,
+    handshakes: Vec<Handshake<T::Future>>,
+    // The addresses of the connection last returned by `poll_accept`.
+    accepted: Option<(SocketAddr, SocketAddr)>,
+}
+
+struct Handshake<F> {
+    remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    fut: Pin<Box<F>>,
+    timeout: Option<Sleep>,
+}
+
+impl<T: TlsAccept> TlsIncoming<T> {
+    /// Construct a new `TlsIncoming`.
+    pub fn new(incoming: AddrIncoming, tls: T) -> TlsIncoming<T> {
+        TlsIncoming {
+            incoming,
+            incoming_done: false,
+            tls,
+            handshake_timeout: Some(Duration::from_secs(10)),
+            max_handshakes: 1024,
+            handshakes: Vec::new(),
+            accepted: None,
+        }
+    }
+
+    /// Get the local address bound to this listener.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.incoming.local_addr()
+    }
+
+    /// Get a mutable reference to the `AddrIncoming` used for TCP.
+    pub fn incoming_mut(&mut self) -> &mut AddrIncoming {
+        &mut self.incoming
+    }
+
+    /// Set how long a handshake may take before the connection is closed.
+    ///
+    /// This includes waiting for the client to start the handshake, so that
+    /// clients that connect and send nothing don't hold on to connections.
+    ///
+    /// If `None`, handshakes can take any time.
+    ///
+    /// Default is 10 seconds.
+    pub fn set_handshake_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Set the maximum number of handshakes that run concurrently.
+    ///
+    /// Once reached, no more connections are accepted until a handshake
+    /// finishes, leaving them in the listener's backlog.
+    ///
+    /// Default is 1024.
+    pub fn set_max_pending_handshakes(&mut self, max: usize) -> &mut Self {
+        self.max_handshakes = max.max(1);
+        self
+    }
+}
+
+// The acceptor `T` is never pinned, and the handshakes are boxed.
+impl<T: TlsAccept> Unpin for TlsIncoming<T> {}
+
+impl<T: TlsAccept> Accept for TlsIncoming<T> {
+    type Conn = T::Stream;
+    type Error = io::Error;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        let me = self.get_mut();
+
+        // Start handshakes for all connections that are ready...
+        while !me.incoming_done && me.handshakes.len() < me.max_handshakes {
+            match Pin::new(&mut me.incoming).poll_accept(cx) {
+                Poll::Ready(Some(Ok(io))) => {
+                    let remote_addr = io.remote_addr();
+                    let local_addr = io.local_addr();
+                    me.handshakes.push(Handshake {
+                        remote_addr,
+                        local_addr,
+                        fut: Box::pin(me.tls.accept(io)),
+                        timeout: me.handshake_timeout.map(tokio::time::sleep),
+                    });
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => me.incoming_done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        // ...and yield the first that finishes.
+        let mut i = 0;
+        while i < me.handshakes.len() {
+            let handshake = &mut me.handshakes[i];
+            let result = match handshake.fut.as_mut().poll(cx) {
+                Poll::Ready(result) => result.map_err(Into::into),
+                Poll::Pending => {
+                    let timed_out = match handshake.timeout {
+                        Some(ref mut timeout) => Pin::new(timeout).poll(cx).is_ready(),
+                        None => false,
+                    };
+                    if !timed_out {
+                        i += 1;
+                        continue;
+                    }
+                    Err("timed out".into())
+                }
+            };
+            let handshake = me.handshakes.swap_remove(i);
+            match result {
+                Ok(stream) => {
+                    me.accepted = Some((handshake.remote_addr, handshake.local_addr));
+                    return Poll::Ready(Some(Ok(stream)));
+                }
+                Err(err) => {
+                    let err: Box<dyn StdError + Send + Sync> = err;
+                    debug!(
+                        "tls handshake error from {}: {}",
+                        handshake.remote_addr, err
+                    );
+                }
+            }
+        }
+
+        if me.incoming_done && me.handshakes.is_empty() {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    }
+
+    fn negotiated_h2(&self, conn: &Self::Conn) -> Option<bool> {
+        Some(is_negotiated_h2(conn))
+    }
+
+    /// Returns the remote address of the connection last returned by
+    /// `poll_accept`.
+    fn remote_addr(&self, _conn: &Self::Conn) -> Option<SocketAddr> {
+        self.accepted.map(|(remote_addr, _)| remote_addr)
+    }
+
+    /// Returns the local address of the connection last returned by
+    /// `poll_accept`.
+    fn local_addr(&self, _conn: &Self::Conn) -> Option<SocketAddr> {
+        self.accepted.map(|(_, local_addr)| local_addr)
+    }
+}
+
+impl<T: TlsAccept> fmt::Debug for TlsIncoming<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsIncoming")
+            .field("incoming", &self.incoming)
+            .field("handshakes", &self.handshakes.len())
+            .finish()
+    }
+}
@@ -4,6 +4,13 @@
 //! Clients and Servers.
 //!
 //! [example]: https://github.com/hyperium/hyper/blob/master/examples/upgrades.rs
+//!
+//! Over HTTP/2, `CONNECT` requests are upgraded to a tunnel on their stream.
+//! Extended `CONNECT` ([RFC 8441]), as used for WebSockets over HTTP/2, is
+//! not supported, since the `h2` dependency doesn't support the `:protocol`
+//! pseudo-header. Such upgrades need HTTP/1.
+//!
+//! [RFC 8441]: https://tools.ietf.org/html/rfc8441
 
 use std::any::TypeId;
 use std::error::Error as StdError;
@@ -264,6 +264,7 @@ macro_rules! test {
                 .expect("HttpConnector should set HttpInfo");
 
             assert_eq!(extra.remote_addr(), addr, "HttpInfo should have server addr");
+            assert!(!extra.is_reused(), "HttpInfo of a new connection isn't reused");
 
             resp
         })
@@ -968,7 +969,9 @@ mod dispatch_impl {
 
     use super::support;
     use hyper::body::HttpBody;
-    use hyper::client::connect::{Connected, Connection, HttpConnector};
+    use hyper::client::connect::{
+        Connected, Connection, HttpConnector, HttpInfo, Proxy, ProxyConnector,
+    };
     use hyper::Client;
 
     #[test]
@@ -1414,7 +1417,9 @@ mod dispatch_impl {
             .body(Body::empty())
             .unwrap();
         let res = client.request(req);
-        rt.block_on(future::join(res, rx).map(|r| r.0)).unwrap();
+        let res = rt.block_on(future::join(res, rx).map(|r| r.0)).unwrap();
+        let info = res.extensions().get::<HttpInfo>().expect("HttpInfo");
+        assert!(!info.is_reused());
 
         assert_eq!(connects.load(Ordering::SeqCst), 1);
 
@@ -1428,7 +1433,9 @@ mod dispatch_impl {
             .body(Body::empty())
             .unwrap();
         let res = client.request(req);
-        rt.block_on(future::join(res, rx).map(|r| r.0)).unwrap();
+        let res = rt.block_on(future::join(res, rx).map(|r| r.0)).unwrap();
+        let info = res.extensions().get::<HttpInfo>().expect("HttpInfo");
+        assert!(info.is_reused());
 
         assert_eq!(
             connects.load(Ordering::SeqCst),
@@ -1662,6 +1669,311 @@ mod dispatch_impl {
         drop(client);
     }
 
+    #[tokio::test]
+    async fn response_head_timeout_discards_connection() {
+        let _ = pretty_env_logger::try_init();
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let connector = DebugConnector::new();
+        let connects = connector.connects.clone();
+
+        let client = Client::builder()
+            .response_head_timeout(Duration::from_millis(100))
+            .build(connector);
+
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        thread::spawn(move || {
+            // never responds on the first connection
+            let mut sock1 = server.accept().unwrap().0;
+            sock1
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut buf = [0; 4096];
+            sock1.read(&mut buf).expect("read 1");
+
+            let mut sock2 = server.accept().unwrap().0;
+            sock2
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            sock2
+                .set_write_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            sock2.read(&mut buf).expect("read 2");
+            sock2
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .expect("write 2");
+
+            let _ = done_rx.recv();
+        });
+
+        let req = Request::builder()
+            .uri(&*format!("http://{}/a", addr))
+            .body(Body::empty())
+            .unwrap();
+        let err = client.request(req).await.unwrap_err();
+        assert!(err.is_timeout(), "{:?}", err);
+
+        let req = Request::builder()
+            .uri(&*format!("http://{}/b", addr))
+            .body(Body::empty())
+            .unwrap();
+        let res = client.request(req).await.expect("request 2");
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            connects.load(Ordering::SeqCst),
+            2,
+            "timed out connection should not be reused"
+        );
+        let _ = done_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn request_timeout() {
+        let _ = pretty_env_logger::try_init();
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let client = Client::builder()
+            .request_timeout(Duration::from_millis(100))
+            .response_head_timeout(Duration::from_secs(5))
+            .build(DebugConnector::new());
+
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        thread::spawn(move || {
+            let mut sock = server.accept().unwrap().0;
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut buf = [0; 4096];
+            sock.read(&mut buf).expect("read 1");
+            let _ = done_rx.recv();
+        });
+
+        let req = Request::builder()
+            .uri(&*format!("http://{}/a", addr))
+            .body(Body::empty())
+            .unwrap();
+        let err = client.request(req).await.unwrap_err();
+        assert!(err.is_timeout(), "{:?}", err);
+        assert_eq!(err.to_string(), "request timeout");
+        let _ = done_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn request_timeout_while_reading_body() {
+        let _ = pretty_env_logger::try_init();
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let client = Client::builder()
+            .request_timeout(Duration::from_millis(100))
+            .build(DebugConnector::new());
+
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        thread::spawn(move || {
+            let mut sock = server.accept().unwrap().0;
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            sock.set_write_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut buf = [0; 4096];
+            sock.read(&mut buf).expect("read 1");
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello")
+                .expect("write 1");
+            let _ = done_rx.recv();
+        });
+
+        let req = Request::builder()
+            .uri(&*format!("http://{}/a", addr))
+            .body(Body::empty())
+            .unwrap();
+        let res = client.request(req).await.expect("response head");
+        assert_eq!(res.status(), 200);
+
+        let err = hyper::body::to_bytes(res.into_body())
+            .await
+            .expect_err("body should time out");
+        assert!(err.is_timeout(), "{:?}", err);
+        let _ = done_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn client_follows_redirects() {
+        use hyper::client::redirect;
+
+        let _ = pretty_env_logger::try_init();
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let client = Client::builder()
+            .redirect_policy(redirect::Policy::default())
+            .build(DebugConnector::new());
+
+        let (heads_tx, heads_rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let responses: &[&[u8]] = &[
+                b"HTTP/1.1 303 See Other\r\nLocation: /b\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            ];
+            for response in responses {
+                let mut sock = server.accept().unwrap().0;
+                sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                sock.set_write_timeout(Some(Duration::from_secs(5)))
+                    .unwrap();
+                let mut buf = [0; 4096];
+                let n = sock.read(&mut buf).expect("read");
+                heads_tx
+                    .send(String::from_utf8_lossy(&buf[..n]).into_owned())
+                    .unwrap();
+                sock.write_all(response).expect("write");
+            }
+        });
+
+        let req = Request::builder()
+            .method("POST")
+            .uri(&*format!("http://{}/a", addr))
+            .header("content-length", "5")
+            .body(Body::from("hello"))
+            .unwrap();
+        let res = client.request(req).await.expect("request");
+        assert_eq!(res.status(), 200);
+
+        let history = res
+            .extensions()
+            .get::<redirect::History>()
+            .expect("redirect history");
+        assert_eq!(history.uri(), &*format!("http://{}/b", addr));
+        assert_eq!(history.previous().len(), 1);
+        assert_eq!(history.previous()[0], &*format!("http://{}/a", addr));
+
+        let head1 = heads_rx.recv().unwrap();
+        assert!(head1.starts_with("POST /a HTTP/1.1\r\n"), "{:?}", head1);
+        let head2 = heads_rx.recv().unwrap();
+        assert!(head2.starts_with("GET /b HTTP/1.1\r\n"), "{:?}", head2);
+        assert!(!head2.contains("content-length"), "{:?}", head2);
+    }
+
+    #[cfg(feature = "http2")]
+    #[tokio::test]
+    async fn client_h2c_upgrade_declined_keeps_http1() {
+        let _ = pretty_env_logger::try_init();
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let client = Client::builder()
+            .http2_h2c_upgrade(true)
+            .build(DebugConnector::new());
+
+        let (heads_tx, heads_rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let mut sock = server.accept().unwrap().0;
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            sock.set_write_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            // The server ignores the upgrade, and answers both requests
+            // on the same connection.
+            for _ in 0..2 {
+                let mut buf = [0; 4096];
+                let n = sock.read(&mut buf).expect("read");
+                heads_tx
+                    .send(String::from_utf8_lossy(&buf[..n]).into_owned())
+                    .unwrap();
+                sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .expect("write");
+            }
+        });
+
+        let uri: hyper::Uri = format!("http://{}/a", addr).parse().unwrap();
+        let res = client.get(uri.clone()).await.expect("first");
+        assert_eq!(res.version(), hyper::Version::HTTP_11);
+        // Let the connection go back to the pool.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let res = client.get(uri).await.expect("second");
+        assert_eq!(res.version(), hyper::Version::HTTP_11);
+
+        let head1 = heads_rx.recv().unwrap().to_ascii_lowercase();
+        assert!(head1.contains("\r\nupgrade: h2c\r\n"), "{:?}", head1);
+        assert!(head1.contains("\r\nhttp2-settings: "), "{:?}", head1);
+        assert!(
+            head1.contains("\r\nconnection: upgrade, http2-settings\r\n"),
+            "{:?}",
+            head1
+        );
+        // A reused connection isn't asked to upgrade again.
+        let head2 = heads_rx.recv().unwrap().to_ascii_lowercase();
+        assert!(!head2.contains("upgrade"), "{:?}", head2);
+    }
+
+    #[cfg(feature = "http2")]
+    #[tokio::test]
+    async fn client_h2c_upgrade_switches_to_http2() {
+        use hyper::server::conn::Http;
+        use hyper::service::service_fn;
+        use hyper::Response;
+        use tokio::net::TcpListener;
+
+        let _ = pretty_env_logger::try_init();
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connector = DebugConnector::new();
+        let connects = connector.connects.clone();
+        let client = Client::builder()
+            .http2_h2c_upgrade(true)
+            .build::<_, Body>(connector);
+
+        let (paths_tx, paths_rx) = std::sync::mpsc::channel();
+        tokio::spawn(async move {
+            let (socket, _addr) = listener.accept().await.expect("accept");
+            Http::new()
+                .http2_h2c_upgrade(true)
+                .serve_connection(
+                    socket,
+                    service_fn(move |req: Request<Body>| {
+                        let paths_tx = paths_tx.clone();
+                        async move {
+                            let path = req.uri().path().to_owned();
+                            paths_tx.send((path.clone(), req.version())).unwrap();
+                            let body = hyper::body::to_bytes(req.into_body()).await?;
+                            let reply = format!("{} {}", path, body.len());
+                            Ok::<_, hyper::Error>(Response::new(Body::from(reply)))
+                        }
+                    }),
+                )
+                .await
+                .expect("server");
+        });
+
+        let uri = |path: &str| format!("http://{}{}", addr, path).parse::<Uri>().unwrap();
+        // The response to the upgrade request arrives over HTTP/2, as the
+        // response to the client's placeholder stream.
+        let res = client.get(uri("/a")).await.expect("upgrade request");
+        assert_eq!(res.version(), hyper::Version::HTTP_2);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "/a 0");
+
+        // Later requests are multiplexed on the pooled HTTP/2 connection.
+        let post = Request::post(uri("/c")).body(Body::from("hello")).unwrap();
+        let (res_b, res_c) = future::join(client.get(uri("/b")), client.request(post)).await;
+        for (res, expected) in vec![(res_b, "/b 0"), (res_c, "/c 5")] {
+            let res = res.expect("pooled request");
+            assert_eq!(res.version(), hyper::Version::HTTP_2);
+            let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+            assert_eq!(body, expected);
+        }
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+
+        // The server only saw the real requests, not the placeholder.
+        let mut paths = paths_rx.try_iter().collect::<Vec<_>>();
+        paths.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            paths,
+            vec![
+                ("/a".to_owned(), hyper::Version::HTTP_2),
+                ("/b".to_owned(), hyper::Version::HTTP_2),
+                ("/c".to_owned(), hyper::Version::HTTP_2),
+            ]
+        );
+    }
+
     #[test]
     fn connect_proxy_sends_absolute_uri() {
         let _ = pretty_env_logger::try_init();
@@ -1741,6 +2053,98 @@ mod dispatch_impl {
         rt.block_on(future::join(res, rx).map(|r| r.0)).unwrap();
     }
 
+    #[test]
+    fn proxy_connector_sends_absolute_uri() {
+        let _ = pretty_env_logger::try_init();
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let rt = support::runtime();
+        let mut connector = ProxyConnector::new(HttpConnector::new());
+        connector.set_http_proxy(Some(Proxy::new(
+            format!("http://foo:bar@{}", addr).parse().unwrap(),
+        )));
+
+        let client = Client::builder().build(connector);
+
+        let (tx1, rx1) = oneshot::channel();
+        thread::spawn(move || {
+            let mut sock = server.accept().unwrap().0;
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            sock.set_write_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut buf = [0; 4096];
+            let n = sock.read(&mut buf).expect("read 1");
+            let expected = "\
+                GET http://example.domain/foo/bar HTTP/1.1\r\n\
+                host: example.domain\r\n\
+                proxy-authorization: Basic Zm9vOmJhcg==\r\n\
+                \r\n\
+            ";
+            assert_eq!(s(&buf[..n]), expected);
+
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .expect("write 1");
+            let _ = tx1.send(());
+        });
+
+        let rx = rx1.expect("thread panicked");
+        let req = Request::builder()
+            .uri("http://example.domain/foo/bar")
+            .body(Body::empty())
+            .unwrap();
+        let res = client.request(req);
+        rt.block_on(future::join(res, rx).map(|r| r.0)).unwrap();
+    }
+
+    #[test]
+    fn proxy_connector_tunnels_with_connect() {
+        let _ = pretty_env_logger::try_init();
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let rt = support::runtime();
+        let mut connector = ProxyConnector::new(HttpConnector::new());
+        connector.set_https_proxy(Some(Proxy::new(
+            format!("http://foo:bar@{}", addr).parse().unwrap(),
+        )));
+
+        let client = Client::builder().build(connector);
+
+        let (tx1, rx1) = oneshot::channel();
+        thread::spawn(move || {
+            let mut sock = server.accept().unwrap().0;
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            sock.set_write_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut buf = [0; 4096];
+            let n = sock.read(&mut buf).expect("read 1");
+            let expected = "\
+                CONNECT example.domain:443 HTTP/1.1\r\n\
+                Host: example.domain:443\r\n\
+                Proxy-Authorization: Basic Zm9vOmJhcg==\r\n\
+                \r\n\
+            ";
+            assert_eq!(s(&buf[..n]), expected);
+            sock.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .expect("write 1");
+
+            // the rest goes through the tunnel, in origin-form
+            let n = sock.read(&mut buf).expect("read 2");
+            let expected = "GET /foo/bar HTTP/1.1\r\nhost: example.domain\r\n\r\n";
+            assert_eq!(s(&buf[..n]), expected);
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .expect("write 2");
+            let _ = tx1.send(());
+        });
+
+        let rx = rx1.expect("thread panicked");
+        let req = Request::builder()
+            .uri("https://example.domain/foo/bar")
+            .body(Body::empty())
+            .unwrap();
+        let res = client.request(req);
+        rt.block_on(future::join(res, rx).map(|r| r.0)).unwrap();
+    }
+
     #[test]
     fn client_upgrade() {
         use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -2043,6 +2447,73 @@ mod conn {
         future::join(server, client).await;
     }
 
+    #[tokio::test]
+    async fn http1_preserve_header_case_and_order() {
+        let _ = ::pretty_env_logger::try_init();
+        let listener = TkTcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = async move {
+            let mut sock = listener.accept().await.unwrap().0;
+            let mut buf = [0; 4096];
+            let n = sock.read(&mut buf).await.expect("read 1");
+            assert_eq!(s(&buf[..n]), "GET /a HTTP/1.1\r\n\r\n");
+
+            sock.write_all(
+                b"HTTP/1.1 200 OK\r\nX-Foo: a\r\nx-BAR: b\r\nx-foo: c\r\nContent-Length: 0\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+            let n = sock.read(&mut buf).await.expect("read 2");
+            assert_eq!(
+                s(&buf[..n]),
+                "GET /b HTTP/1.1\r\nX-Foo: a\r\nx-BAR: b\r\nx-foo: c\r\n\r\n"
+            );
+
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        };
+
+        let client = async move {
+            let tcp = tcp_connect(&addr).await.expect("connect");
+            let (mut client, conn) = conn::Builder::new()
+                .http1_preserve_header_case(true)
+                .http1_preserve_header_order(true)
+                .handshake(tcp)
+                .await
+                .expect("handshake");
+
+            tokio::task::spawn(async move {
+                conn.await.expect("http conn");
+            });
+
+            let req = Request::builder()
+                .uri("/a")
+                .body(Default::default())
+                .unwrap();
+            let res = client.send_request(req).await.expect("send_request 1");
+            assert_eq!(res.status(), hyper::StatusCode::OK);
+
+            // send the same headers back, as a proxy would
+            let (mut parts, _) = res.into_parts();
+            parts.headers.remove(hyper::header::CONTENT_LENGTH);
+            let mut req = Request::builder()
+                .uri("/b")
+                .body(Default::default())
+                .unwrap();
+            *req.headers_mut() = parts.headers;
+            *req.extensions_mut() = parts.extensions;
+            let res = client.send_request(req).await.expect("send_request 2");
+            assert_eq!(res.status(), hyper::StatusCode::OK);
+        };
+
+        future::join(server, client).await;
+    }
+
     #[test]
     fn incoming_content_length() {
         use hyper::body::HttpBody;
@@ -1479,6 +1479,353 @@ async fn http_connect_new() {
     assert_eq!(s(&vec), "bar=foo");
 }
 
+#[cfg(feature = "http2")]
+#[tokio::test]
+async fn h2_connect_upgrades_stream() {
+    use hyper::Method;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        Http::new()
+            .http2_only(true)
+            .serve_connection(
+                socket,
+                service_fn(|req: Request<Body>| async move {
+                    assert_eq!(req.method(), Method::CONNECT);
+                    assert_eq!(req.uri().authority().unwrap().as_str(), "example.com:443");
+                    tokio::spawn(async move {
+                        let mut io = req.into_body().on_upgrade().await.expect("upgrade");
+                        let mut buf = [0; 4];
+                        io.read_exact(&mut buf).await.unwrap();
+                        assert_eq!(&buf, b"ping");
+                        io.write_all(b"pong").await.unwrap();
+                        io.shutdown().await.unwrap();
+                    });
+                    Ok::<_, hyper::Error>(Response::new(Body::empty()))
+                }),
+            )
+            .await
+            .expect("server");
+    });
+
+    let tcp = TkTcpStream::connect(addr).await.unwrap();
+    let (mut client, conn) = hyper::client::conn::Builder::new()
+        .http2_only(true)
+        .handshake(tcp)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        conn.await.expect("client conn");
+    });
+
+    let req = Request::connect("example.com:443")
+        .body(Body::empty())
+        .unwrap();
+    let res = client.send_request(req).await.expect("connect");
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let mut io = res.into_body().on_upgrade().await.expect("upgrade");
+    io.write_all(b"ping").await.unwrap();
+    let mut buf = Vec::new();
+    io.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"pong");
+}
+
+#[cfg(feature = "http2")]
+#[tokio::test]
+async fn h2_server_push() {
+    use hyper::server::conn::Pusher;
+
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        Http::new()
+            .http2_only(true)
+            .http2_enable_push(true)
+            .serve_connection(
+                socket,
+                service_fn(move |req: Request<Body>| async move {
+                    let pusher = req.extensions().get::<Pusher>().expect("pusher");
+                    let promised = Request::get(format!("http://{}/style.css", addr))
+                        .body(())
+                        .unwrap();
+                    pusher
+                        .push(promised, Response::new(Body::from("pushed")))
+                        .await
+                        .expect("push");
+                    Ok::<_, hyper::Error>(Response::new(Body::from("main")))
+                }),
+            )
+            .await
+            .expect("server");
+    });
+
+    let tcp = TkTcpStream::connect(addr).await.unwrap();
+    let (mut client, conn) = hyper::client::conn::Builder::new()
+        .http2_only(true)
+        .http2_enable_push(true)
+        .handshake::<_, Body>(tcp)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        conn.await.expect("client conn");
+    });
+    let mut pushes = client.push_promises().expect("push_promises");
+
+    let req = Request::get(format!("http://{}/", addr))
+        .body(Body::empty())
+        .unwrap();
+    let res = client.send_request(req).await.expect("get");
+    let body = hyper::body::to_bytes(res.into_body()).await.expect("body");
+    assert_eq!(body, "main");
+
+    let promise = futures_util::stream::StreamExt::next(&mut pushes)
+        .await
+        .expect("push promise");
+    assert_eq!(promise.request().uri().path(), "/style.css");
+    let (_req, res) = promise.into_parts();
+    let res = res.await.expect("pushed response");
+    let body = hyper::body::to_bytes(res.into_body()).await.expect("body");
+    assert_eq!(body, "pushed");
+}
+
+#[tokio::test]
+async fn server_connection_metrics() {
+    let _ = pretty_env_logger::try_init();
+    let connections = Arc::new(Mutex::new(Vec::new()));
+    let connections2 = connections.clone();
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into())
+        .on_connection(move |metrics| connections2.lock().unwrap().push(metrics))
+        .serve(make_service_fn(|_| async {
+            Ok::<_, hyper::Error>(service_fn(|_req| async {
+                Ok::<_, hyper::Error>(Response::new(Body::from("hello")))
+            }))
+        }));
+    let addr = server.local_addr();
+    tokio::spawn(async move {
+        server.await.expect("server");
+    });
+
+    let requests: &[u8] = b"\
+        GET / HTTP/1.1\r\n\
+        Host: example.domain\r\n\
+        \r\n\
+        GET / HTTP/1.0\r\n\
+        Host: example.domain\r\n\
+        Connection: close\r\n\
+        \r\n\
+    ";
+    let mut tcp = connect_async(addr).await;
+    tcp.write_all(requests).await.expect("write");
+    let mut buf = Vec::new();
+    tcp.read_to_end(&mut buf).await.expect("read");
+
+    let connections = connections.lock().unwrap();
+    assert_eq!(connections.len(), 1);
+    let metrics = &connections[0];
+    assert_eq!(metrics.requests(), 2);
+    assert_eq!(metrics.keep_alive_reuses(), 1);
+    assert_eq!(metrics.bytes_read(), requests.len() as u64);
+    assert_eq!(metrics.bytes_written(), buf.len() as u64);
+    // The version of the last request.
+    assert_eq!(metrics.version(), Version::HTTP_10);
+}
+
+#[cfg(feature = "http2")]
+#[tokio::test]
+async fn server_connection_metrics_h2() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (metrics_tx, metrics_rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let conn = Http::new().http2_only(true).serve_connection(
+            socket,
+            service_fn(|_req| async { Ok::<_, hyper::Error>(Response::new(Body::from("hello"))) }),
+        );
+        let _ = metrics_tx.send(conn.metrics());
+        conn.await.expect("server conn");
+    });
+
+    let client = Client::builder()
+        .http2_only(true)
+        .build_http::<hyper::Body>();
+    let uri = format!("http://{}/", addr).parse::<hyper::Uri>().unwrap();
+    for _ in 0..3 {
+        let res = client.get(uri.clone()).await.expect("request");
+        hyper::body::to_bytes(res.into_body()).await.expect("body");
+    }
+
+    let metrics = metrics_rx.await.expect("metrics");
+    assert_eq!(metrics.requests(), 3);
+    assert_eq!(metrics.keep_alive_reuses(), 0);
+    assert!(metrics.bytes_read() > 0);
+    assert!(metrics.bytes_written() > 0);
+    assert_eq!(metrics.version(), Version::HTTP_2);
+}
+
+#[tokio::test]
+async fn server_request_addr_info() {
+    use hyper::server::conn::AddrInfo;
+
+    let _ = pretty_env_logger::try_init();
+    let infos = Arc::new(Mutex::new(Vec::new()));
+    let infos2 = infos.clone();
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service_fn(move |_| {
+        let infos = infos2.clone();
+        async move {
+            Ok::<_, hyper::Error>(service_fn(move |req: Request<Body>| {
+                let info = req.extensions().get::<AddrInfo>().cloned();
+                infos.lock().unwrap().push(info);
+                async { Ok::<_, hyper::Error>(Response::new(Body::from("hello"))) }
+            }))
+        }
+    }));
+    let addr = server.local_addr();
+    tokio::spawn(async move {
+        server.await.expect("server");
+    });
+
+    let mut tcp = connect_async(addr).await;
+    tcp.write_all(
+        b"\
+        GET / HTTP/1.1\r\n\
+        Host: example.domain\r\n\
+        \r\n\
+        GET / HTTP/1.1\r\n\
+        Host: example.domain\r\n\
+        Connection: close\r\n\
+        \r\n\
+    ",
+    )
+    .await
+    .expect("write");
+    let mut buf = Vec::new();
+    tcp.read_to_end(&mut buf).await.expect("read");
+
+    let infos = infos.lock().unwrap();
+    assert_eq!(infos.len(), 2);
+    let first = infos[0].as_ref().expect("first AddrInfo");
+    let second = infos[1].as_ref().expect("second AddrInfo");
+    assert_eq!(first.remote_addr(), tcp.local_addr().unwrap());
+    assert_eq!(first.local_addr(), addr);
+    assert!(!first.is_reused());
+    assert!(second.is_reused());
+    assert_eq!(first.accepted_at(), second.accepted_at());
+}
+
+#[tokio::test]
+async fn server_connection_hooks() {
+    let _ = pretty_env_logger::try_init();
+    let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+    let accept_tx = events_tx.clone();
+    let protocol_tx = events_tx.clone();
+    let rejected_first = AtomicBool::new(false);
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into())
+        .on_accept(move |addr| {
+            // Reject only the first connection.
+            let accept = rejected_first.swap(true, Ordering::SeqCst);
+            accept_tx
+                .send(format!("accept {} {}", accept, addr.is_some()))
+                .unwrap();
+            accept
+        })
+        .on_protocol(move |_addr, version| {
+            protocol_tx.send(format!("protocol {:?}", version)).unwrap();
+        })
+        .on_close(move |_addr, err| {
+            events_tx.send(format!("close {}", err.is_some())).unwrap();
+        })
+        .serve(make_service_fn(|_| async {
+            Ok::<_, hyper::Error>(service_fn(|_req| async {
+                Ok::<_, hyper::Error>(Response::new(Body::from("hello")))
+            }))
+        }));
+    let addr = server.local_addr();
+    tokio::spawn(async move {
+        server.await.expect("server");
+    });
+
+    let mut rejected = connect_async(addr).await;
+    let mut buf = Vec::new();
+    rejected.read_to_end(&mut buf).await.expect("read rejected");
+    assert!(buf.is_empty());
+    assert_eq!(events.recv().await.unwrap(), "accept false true");
+
+    let mut tcp = connect_async(addr).await;
+    tcp.write_all(
+        b"\
+        GET / HTTP/1.1\r\n\
+        Host: example.domain\r\n\
+        Connection: close\r\n\
+        \r\n\
+    ",
+    )
+    .await
+    .expect("write");
+    tcp.read_to_end(&mut buf).await.expect("read");
+    assert!(buf.starts_with(b"HTTP/1.1 200 OK\r\n"));
+
+    assert_eq!(events.recv().await.unwrap(), "accept true true");
+    assert_eq!(events.recv().await.unwrap(), "protocol HTTP/1.1");
+    assert_eq!(events.recv().await.unwrap(), "close false");
+}
+
+#[test]
+fn server_on_close_when_task_dropped() {
+    let _ = pretty_env_logger::try_init();
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("new rt");
+    let (connected_tx, mut connected) = tokio::sync::mpsc::unbounded_channel();
+    let closes = Arc::new(Mutex::new(Vec::new()));
+    let closes2 = closes.clone();
+
+    let addr = rt.block_on(async move {
+        let server = Server::bind(&([127, 0, 0, 1], 0).into())
+            .on_connection(move |_metrics| connected_tx.send(()).unwrap())
+            .on_close(move |addr, err| {
+                closes2
+                    .lock()
+                    .unwrap()
+                    .push((addr.is_some(), err.is_some()));
+            })
+            .serve(make_service_fn(|_| async {
+                Ok::<_, hyper::Error>(service_fn(|_req| async {
+                    Ok::<_, hyper::Error>(Response::new(Body::empty()))
+                }))
+            }));
+        let addr = server.local_addr();
+        tokio::spawn(async move {
+            server.await.expect("server");
+        });
+        addr
+    });
+
+    // a connection that never sends a request...
+    let _tcp = connect(&addr);
+    rt.block_on(connected.recv()).expect("connected");
+    assert!(closes.lock().unwrap().is_empty());
+
+    // ...is still reported once its task is dropped with the runtime
+    drop(rt);
+    assert_eq!(*closes.lock().unwrap(), vec![(true, false)]);
+}
+
 #[tokio::test]
 async fn parse_errors_send_4xx_response() {
     let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
@@ -1524,6 +1871,638 @@ async fn illegal_request_length_returns_400_response() {
         .expect_err("illegal Content-Length should error");
 }
 
+#[tokio::test]
+async fn http1_request_trailers() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            POST / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Transfer-Encoding: chunked\r\n\
+            Connection: close\r\n\
+            \r\n\
+            5\r\n\
+            hello\r\n\
+            0\r\n\
+            chunky-trailer: header data\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read");
+
+        let expected = "HTTP/1.1 200 ";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(
+            socket,
+            service_fn(|req: Request<Body>| async move {
+                let mut body = req.into_body();
+                let mut data = Vec::new();
+                while let Some(chunk) = body.data().await {
+                    data.extend_from_slice(&chunk?);
+                }
+                assert_eq!(data, b"hello");
+
+                let trailers = body.trailers().await?.expect("trailers");
+                assert_eq!(trailers["chunky-trailer"], "header data");
+
+                Ok::<_, hyper::Error>(Response::new(Body::empty()))
+            }),
+        )
+        .await
+        .expect("serve_connection");
+}
+
+async fn http1_response_trailers(te_trailers: bool) -> String {
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = oneshot::channel();
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        let te = if te_trailers { "TE: trailers\r\n" } else { "" };
+        write!(
+            tcp,
+            "GET / HTTP/1.1\r\nHost: example.domain\r\n{}Connection: close\r\n\r\n",
+            te,
+        )
+        .expect("write");
+        let mut buf = Vec::new();
+        tcp.read_to_end(&mut buf).expect("read");
+        let _ = tx.send(String::from_utf8(buf).expect("utf8"));
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(
+            socket,
+            service_fn(|_| async move {
+                let (mut tx, body) = Body::channel();
+                tokio::spawn(async move {
+                    tx.send_data("hello".into()).await.expect("send_data");
+                    let mut trailers = http::HeaderMap::new();
+                    trailers.insert("chunky-trailer", HeaderValue::from_static("header data"));
+                    tx.send_trailers(trailers).await.expect("send_trailers");
+                });
+                Ok::<_, hyper::Error>(Response::new(body))
+            }),
+        )
+        .await
+        .expect("serve_connection");
+
+    rx.await.expect("client thread")
+}
+
+#[tokio::test]
+async fn http1_response_trailers_with_te() {
+    let _ = pretty_env_logger::try_init();
+    let response = http1_response_trailers(true).await;
+    assert!(
+        response.ends_with("5\r\nhello\r\n0\r\nchunky-trailer: header data\r\n\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn http1_response_trailers_without_te() {
+    let _ = pretty_env_logger::try_init();
+    let response = http1_response_trailers(false).await;
+    assert!(
+        response.ends_with("5\r\nhello\r\n0\r\n\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn header_read_timeout_slow_writes() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"GET / HTTP/1.1\r\n").expect("write 1");
+        thread::sleep(Duration::from_millis(200));
+        tcp.write_all(b"Host: example.domain\r\n").expect("write 2");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read 1");
+
+        let expected = "HTTP/1.1 408 ";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let err = Http::new()
+        .http1_header_read_timeout(Duration::from_millis(100))
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect_err("header read timeout should error");
+    assert!(err.is_timeout(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn header_read_timeout_in_time() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"GET / HTTP/1.1\r\n").expect("write 1");
+        thread::sleep(Duration::from_millis(50));
+        tcp.write_all(b"Host: example.domain\r\nConnection: close\r\n\r\n")
+            .expect("write 2");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read 1");
+
+        let expected = "HTTP/1.1 200 ";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_header_read_timeout(Duration::from_secs(5))
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect("serve_connection");
+}
+
+#[cfg(all(unix, feature = "unix"))]
+#[tokio::test]
+async fn unix_socket_incoming() {
+    use hyper::client::connect::{UnixConnector, UnixInfo};
+    use hyper::server::conn::UnixIncoming;
+
+    let _ = pretty_env_logger::try_init();
+    let path = std::env::temp_dir().join(format!("hyper-test-unix-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let incoming = UnixIncoming::bind(&path).expect("bind");
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = Server::builder(incoming)
+        .serve(make_service_fn(|_| async {
+            Ok::<_, hyper::Error>(service_fn(|req: Request<Body>| async move {
+                Ok::<_, hyper::Error>(Response::new(Body::from(req.uri().path().to_owned())))
+            }))
+        }))
+        .with_graceful_shutdown(async {
+            let _ = shutdown_rx.await;
+        });
+    let server = tokio::spawn(server);
+
+    let client = Client::builder().build::<_, Body>(UnixConnector::new());
+    let res = client
+        .get(UnixConnector::uri(&path, "/hello").unwrap())
+        .await
+        .expect("get");
+    assert_eq!(res.status(), StatusCode::OK);
+    let info = res.extensions().get::<UnixInfo>().expect("UnixInfo");
+    assert_eq!(info.peer_path(), Some(&*path));
+    let body = hyper::body::to_bytes(res.into_body()).await.expect("body");
+    assert_eq!(body, "/hello");
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server.await.unwrap().expect("server");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn compress_responses() {
+    use hyper::service::compress;
+
+    let _ = pretty_env_logger::try_init();
+    let text = "hello compression ".repeat(1000);
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server_text = text.clone();
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service_fn(move |_| {
+        let text = server_text.clone();
+        async move {
+            Ok::<_, hyper::Error>(compress(service_fn(move |_req: Request<Body>| {
+                let text = text.clone();
+                async move { Ok::<_, hyper::Error>(Response::new(Body::from(text))) }
+            })))
+        }
+    }));
+    let addr = server.local_addr();
+    let server = tokio::spawn(server.with_graceful_shutdown(async {
+        let _ = shutdown_rx.await;
+    }));
+    let uri: hyper::Uri = format!("http://{}/", addr).parse().unwrap();
+
+    for &encoding in &["gzip", "deflate", "br"] {
+        let req = Request::get(uri.clone())
+            .header("accept-encoding", encoding)
+            .body(Body::empty())
+            .unwrap();
+        let res = Client::new().request(req).await.expect("request");
+        assert_eq!(res.headers()["content-encoding"], encoding);
+        assert_eq!(res.headers()["vary"], "accept-encoding");
+        let body = hyper::body::to_bytes(res.into_body()).await.expect("body");
+        assert!(body.len() < text.len(), "{} not compressed", encoding);
+    }
+
+    let res = Client::new().get(uri.clone()).await.expect("get");
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.headers()["content-length"], text.len().to_string());
+
+    let client = Client::builder().decompress(true).build_http::<Body>();
+    let res = client.get(uri).await.expect("get");
+    assert!(res.headers().get("content-encoding").is_none());
+    let body = hyper::body::to_bytes(res.into_body()).await.expect("body");
+    assert_eq!(body, text);
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server.await.unwrap().expect("server");
+}
+
+/// A "TLS" stream that passes bytes through as they are, and reports the
+/// ALPN protocol it was created with.
+#[cfg(feature = "tls")]
+struct FakeTlsStream<T>(T, Option<&'static [u8]>);
+
+#[cfg(feature = "tls")]
+impl<T: AsyncRead + AsyncWrite + Unpin> hyper::tls::TlsStream for FakeTlsStream<T> {
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.1
+    }
+}
+
+#[cfg(feature = "tls")]
+impl<T: AsyncRead + Unpin> AsyncRead for FakeTlsStream<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl<T: AsyncWrite + Unpin> AsyncWrite for FakeTlsStream<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn tls_handshake_timeout() {
+    use hyper::server::conn::{AddrIncoming, AddrInfo, AddrStream};
+    use hyper::tls::{TlsAccept, TlsIncoming};
+
+    // A "TLS" whose handshake is the client sending a byte.
+    struct ByteTls;
+
+    impl TlsAccept for ByteTls {
+        type Stream = FakeTlsStream<AddrStream>;
+        type Error = io::Error;
+        type Future = Pin<Box<dyn Future<Output = io::Result<Self::Stream>> + Send>>;
+
+        fn accept(&self, mut io: AddrStream) -> Self::Future {
+            Box::pin(async move {
+                io.read_exact(&mut [0]).await?;
+                Ok(FakeTlsStream(io, None))
+            })
+        }
+    }
+
+    let _ = pretty_env_logger::try_init();
+    let incoming = AddrIncoming::bind(&([127, 0, 0, 1], 0).into()).unwrap();
+    let addr = incoming.local_addr();
+    let mut tls = TlsIncoming::new(incoming, ByteTls);
+    tls.set_handshake_timeout(Some(Duration::from_millis(100)))
+        .set_max_pending_handshakes(1);
+    let server = Server::builder(tls).serve(make_service_fn(|_| async {
+        Ok::<_, hyper::Error>(service_fn(|req: Request<Body>| async move {
+            // `TlsIncoming` knows the addresses of the TCP connection.
+            let info = req.extensions().get::<AddrInfo>().expect("AddrInfo");
+            let body = format!("{} {}", info.remote_addr(), info.local_addr());
+            Ok::<_, hyper::Error>(Response::new(Body::from(body)))
+        }))
+    }));
+    tokio::spawn(async move {
+        server.await.expect("server");
+    });
+
+    let mut silent = connect_async(addr).await;
+    let mut tcp = connect_async(addr).await;
+    tcp.write_all(
+        b"\
+        xGET / HTTP/1.1\r\n\
+        Host: example.domain\r\n\
+        Connection: close\r\n\
+        \r\n\
+    ",
+    )
+    .await
+    .expect("write");
+
+    // Only one handshake runs at a time, so this connection is served once
+    // the silent one has timed out.
+    let mut buf = Vec::new();
+    tcp.read_to_end(&mut buf).await.expect("read");
+    assert!(buf.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    let addrs = format!("{} {}", tcp.local_addr().unwrap(), addr);
+    assert!(
+        buf.ends_with(addrs.as_bytes()),
+        "{:?}",
+        String::from_utf8_lossy(&buf)
+    );
+
+    let n = silent.read(&mut [0]).await.expect("read silent");
+    assert_eq!(n, 0, "silent connection should be closed");
+}
+
+#[cfg(all(feature = "tls", feature = "http2"))]
+#[tokio::test]
+async fn tls_alpn_negotiates_h2() {
+    use hyper::client::HttpConnector;
+    use hyper::server::conn::{AddrIncoming, AddrStream};
+    use hyper::tls::{TlsAccept, TlsConnect, TlsConnector, TlsIncoming, ALPN_H2};
+
+    // A "TLS" that reports `h2`.
+    #[derive(Clone)]
+    struct FakeTls;
+
+    impl TlsConnect for FakeTls {
+        type Stream = FakeTlsStream<TkTcpStream>;
+        type Error = io::Error;
+        type Future = future::Ready<io::Result<Self::Stream>>;
+
+        fn connect(&self, domain: &str, io: TkTcpStream) -> Self::Future {
+            assert_eq!(domain, "127.0.0.1");
+            future::ok(FakeTlsStream(io, Some(ALPN_H2)))
+        }
+    }
+
+    impl TlsAccept for FakeTls {
+        type Stream = FakeTlsStream<AddrStream>;
+        type Error = io::Error;
+        type Future = future::Ready<io::Result<Self::Stream>>;
+
+        fn accept(&self, io: AddrStream) -> Self::Future {
+            future::ok(FakeTlsStream(io, Some(ALPN_H2)))
+        }
+    }
+
+    let _ = pretty_env_logger::try_init();
+    let incoming = AddrIncoming::bind(&([127, 0, 0, 1], 0).into()).unwrap();
+    let addr = incoming.local_addr();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = Server::builder(TlsIncoming::new(incoming, FakeTls))
+        .serve(make_service_fn(|_| async {
+            Ok::<_, hyper::Error>(service_fn(|req: Request<Body>| async move {
+                assert_eq!(req.version(), Version::HTTP_2);
+                Ok::<_, hyper::Error>(Response::new(Body::from("hello")))
+            }))
+        }))
+        .with_graceful_shutdown(async {
+            let _ = shutdown_rx.await;
+        });
+    let server = tokio::spawn(server);
+
+    let connector = TlsConnector::new(HttpConnector::new(), FakeTls);
+    let client = Client::builder().build::<_, Body>(connector);
+    let res = client
+        .get(format!("https://{}/", addr).parse().unwrap())
+        .await
+        .expect("get");
+    assert_eq!(res.version(), Version::HTTP_2);
+    let body = hyper::body::to_bytes(res.into_body()).await.expect("body");
+    assert_eq!(body, "hello");
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server.await.unwrap().expect("server");
+}
+
+#[cfg(feature = "http2")]
+#[tokio::test]
+async fn h2c_upgrade_serves_request_as_http2() {
+    use std::sync::atomic::AtomicUsize;
+
+    let _ = pretty_env_logger::try_init();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = Server::bind(&([127, 0, 0, 1], 0).into())
+        .http2_h2c_upgrade(true)
+        .serve(make_service_fn(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                Ok::<_, hyper::Error>(service_fn(|req: Request<Body>| async move {
+                    assert_eq!(req.version(), Version::HTTP_2);
+                    assert!(req.headers().get("upgrade").is_none());
+                    Ok::<_, hyper::Error>(Response::new(Body::from(req.uri().path().to_owned())))
+                }))
+            }
+        }));
+    let addr = server.local_addr();
+    let server = tokio::spawn(server.with_graceful_shutdown(async {
+        let _ = shutdown_rx.await;
+    }));
+
+    let client = Client::builder()
+        .http2_h2c_upgrade(true)
+        .build_http::<Body>();
+    for path in &["/a", "/b"] {
+        let res = client
+            .get(format!("http://{}{}", addr, path).parse().unwrap())
+            .await
+            .expect("get");
+        assert_eq!(res.version(), Version::HTTP_2);
+        let body = hyper::body::to_bytes(res.into_body()).await.expect("body");
+        assert_eq!(body, *path);
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server.await.unwrap().expect("server");
+}
+
+#[cfg(feature = "http2")]
+#[tokio::test]
+async fn h2c_upgrade_ignored_for_request_with_body() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            POST / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Connection: Upgrade, HTTP2-Settings, close\r\n\
+            Upgrade: h2c\r\n\
+            HTTP2-Settings: AAIAAAAA\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello\
+        ",
+        )
+        .expect("write");
+        let mut buf = Vec::new();
+        tcp.read_to_end(&mut buf).expect("read");
+
+        let expected = "HTTP/1.1 200 OK\r\n";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http2_h2c_upgrade(true)
+        .serve_connection(
+            socket,
+            service_fn(|req: Request<Body>| async move {
+                assert_eq!(req.version(), Version::HTTP_11);
+                Ok::<_, hyper::Error>(Response::new(Body::empty()))
+            }),
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn http1_preserve_header_case_and_order() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            X-Bar: 1\r\n\
+            host: example.domain\r\n\
+            X-BAR: 2\r\n\
+            CONNECTION: close\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write");
+        let mut buf = Vec::new();
+        tcp.read_to_end(&mut buf).expect("read");
+
+        let expected = "\
+            HTTP/1.1 200 OK\r\n\
+            X-Bar: 1\r\n\
+            host: example.domain\r\n\
+            X-BAR: 2\r\n\
+            CONNECTION: close\r\n\
+            content-length: 0\r\n\
+        ";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_preserve_header_case(true)
+        .http1_preserve_header_order(true)
+        .serve_connection(
+            socket,
+            service_fn(|req: Request<Body>| async move {
+                // echo the request headers, as received
+                let (parts, _) = req.into_parts();
+                let mut res = Response::new(Body::empty());
+                *res.headers_mut() = parts.headers;
+                *res.extensions_mut() = parts.extensions;
+                Ok::<_, hyper::Error>(res)
+            }),
+        )
+        .await
+        .expect("serve_connection");
+}
+
+#[tokio::test]
+async fn http1_max_headers_exceeded_returns_431() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        let mut req = String::from("GET / HTTP/1.1\r\nHost: example.domain\r\n");
+        for i in 0..10 {
+            req.push_str(&format!("x-header-{}: value\r\n", i));
+        }
+        req.push_str("\r\n");
+        tcp.write_all(req.as_bytes()).expect("write");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read");
+
+        let expected = "HTTP/1.1 431 ";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let err = Http::new()
+        .http1_max_headers(10)
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect_err("too many headers should error");
+    assert!(err.is_parse(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn http1_max_headers_raised() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        let mut req = String::from("GET / HTTP/1.1\r\nHost: example.domain\r\n");
+        for i in 0..200 {
+            req.push_str(&format!("x-header-{}: value\r\n", i));
+        }
+        req.push_str("Connection: close\r\n\r\n");
+        tcp.write_all(req.as_bytes()).expect("write");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read");
+
+        let expected = "HTTP/1.1 200 ";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_max_headers(1000)
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect("serve_connection");
+}
+
 #[test]
 #[should_panic]
 fn max_buf_size_panic_too_small() {